@@ -0,0 +1,549 @@
+//! Portable cache bundles (`cache export` / `cache import`)
+//!
+//! A bundle is a zstd-compressed tar archive containing `manifest.json`,
+//! `cache.json` and one `contents/<gist_id>/<filename>` entry per cached
+//! file. It is built from and merged into the configured storage backend,
+//! so a snapshot taken from a SQLite cache imports into a JSON cache and
+//! vice versa.
+
+use crate::cache::content::sha256_hex;
+use crate::cache::storage::{open_content_store, open_metadata_store};
+use crate::cache::types::GistCache;
+use crate::config::Config;
+use crate::error::{GistCacheError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Bundle layout version written to the manifest
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const CACHE_ENTRY: &str = "cache.json";
+const CONTENTS_PREFIX: &str = "contents/";
+
+/// Describes what a bundle contains; checked on import before anything is written
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub created_at: DateTime<Utc>,
+    pub tool_version: String,
+    pub github_user: String,
+    pub gists: usize,
+    pub files: Vec<BundleFile>,
+}
+
+/// One cached file in a bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleFile {
+    pub gist_id: String,
+    pub filename: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Result of `export_bundle`
+#[derive(Debug, Clone, Default)]
+pub struct ExportSummary {
+    pub gists: usize,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Result of `import_bundle`
+#[derive(Debug, Clone, Default)]
+pub struct ImportSummary {
+    /// Gists that were not in the local cache
+    pub added: usize,
+    /// Gists whose bundled copy was newer than the local one
+    pub updated: usize,
+    /// Gists whose local copy was as new or newer than the bundled one
+    pub kept: usize,
+    /// Cached files written from the bundle
+    pub files: usize,
+}
+
+/// Pack the metadata cache and every verified cached file into `path`
+pub fn export_bundle(config: &Config, path: &Path) -> Result<ExportSummary> {
+    let cache = open_metadata_store(config)?.load()?;
+    let content_store = open_content_store(config)?;
+
+    let mut files = Vec::new();
+    let mut entries = Vec::new();
+    for gist in &cache.gists {
+        for filename in content_store.list_files(&gist.id)? {
+            // Self-healing principle: files that fail verification are left
+            // out rather than shipped, they will be re-fetched on next use
+            let Ok(content) = content_store.read_verified(&gist.id, &filename) else {
+                continue;
+            };
+            files.push(BundleFile {
+                gist_id: gist.id.clone(),
+                filename: filename.clone(),
+                size: content.len() as u64,
+                sha256: sha256_hex(content.as_bytes()),
+            });
+            entries.push((
+                format!("{}{}/{}", CONTENTS_PREFIX, gist.id, filename),
+                content,
+            ));
+        }
+    }
+
+    let manifest = BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        created_at: Utc::now(),
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        github_user: cache.metadata.github_user.clone(),
+        gists: cache.gists.len(),
+        files,
+    };
+
+    let summary = ExportSummary {
+        gists: manifest.gists,
+        files: manifest.files.len(),
+        bytes: manifest.files.iter().map(|f| f.size).sum(),
+    };
+
+    // Written next to `path` and renamed into place; a failed export
+    // leaves neither a partial bundle nor the temp file behind
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    let written = write_bundle(&tmp_path, &manifest, &cache, &entries)
+        .and_then(|()| Ok(fs::rename(&tmp_path, path)?));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    Ok(summary)
+}
+
+fn write_bundle(
+    path: &Path,
+    manifest: &BundleManifest,
+    cache: &GistCache,
+    entries: &[(String, String)],
+) -> Result<()> {
+    let file = File::create(path)?;
+    let mut builder = tar::Builder::new(zstd::Encoder::new(file, 0)?);
+    append_entry(
+        &mut builder,
+        MANIFEST_ENTRY,
+        serde_json::to_string_pretty(manifest)?.as_bytes(),
+    )?;
+    append_entry(
+        &mut builder,
+        CACHE_ENTRY,
+        serde_json::to_string_pretty(cache)?.as_bytes(),
+    )?;
+    for (name, content) in entries {
+        append_entry(&mut builder, name, content.as_bytes())?;
+    }
+    builder.into_inner()?.finish()?.sync_all()?;
+    Ok(())
+}
+
+fn append_entry<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    name: &str,
+    data: &[u8],
+) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, name, data)?;
+    Ok(())
+}
+
+/// Contents of a bundle after it has been read and checked against its manifest
+struct Bundle {
+    cache: GistCache,
+    contents: HashMap<String, Vec<(String, String)>>,
+}
+
+fn read_bundle(path: &Path) -> Result<Bundle> {
+    let mut archive = tar::Archive::new(zstd::Decoder::new(File::open(path)?)?);
+
+    let mut manifest: Option<BundleManifest> = None;
+    let mut cache: Option<GistCache> = None;
+    let mut raw_contents: HashMap<(String, String), String> = HashMap::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut data = String::new();
+        entry
+            .read_to_string(&mut data)
+            .map_err(|e| invalid(format!("{}: {}", name, e)))?;
+
+        if name == MANIFEST_ENTRY {
+            manifest = Some(serde_json::from_str(&data)?);
+        } else if name == CACHE_ENTRY {
+            cache = Some(serde_json::from_str(&data)?);
+        } else if let Some(key) = parse_content_path(&name) {
+            raw_contents.insert(key, data);
+        } else {
+            return Err(invalid(format!("unexpected entry {}", name)));
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| invalid(format!("missing {}", MANIFEST_ENTRY)))?;
+    let cache = cache.ok_or_else(|| invalid(format!("missing {}", CACHE_ENTRY)))?;
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(invalid(format!(
+            "format version {} is newer than supported version {}",
+            manifest.format_version, BUNDLE_FORMAT_VERSION
+        )));
+    }
+
+    // Every bundled file must be listed in the manifest with a matching hash
+    let mut contents: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    for file in &manifest.files {
+        let key = (file.gist_id.clone(), file.filename.clone());
+        let content = raw_contents
+            .remove(&key)
+            .ok_or_else(|| invalid(format!("missing {}/{}", file.gist_id, file.filename)))?;
+        if sha256_hex(content.as_bytes()) != file.sha256 {
+            return Err(GistCacheError::IntegrityCheckFailed(format!(
+                "{}/{}",
+                file.gist_id, file.filename
+            )));
+        }
+        contents.entry(key.0).or_default().push((key.1, content));
+    }
+    if let Some((gist_id, filename)) = raw_contents.keys().next() {
+        return Err(invalid(format!(
+            "{}/{} is not listed in the manifest",
+            gist_id, filename
+        )));
+    }
+
+    Ok(Bundle { cache, contents })
+}
+
+/// Split `contents/<gist_id>/<filename>`, rejecting anything that could
+/// escape the gist's directory
+fn parse_content_path(name: &str) -> Option<(String, String)> {
+    let rest = name.strip_prefix(CONTENTS_PREFIX)?;
+    let (gist_id, filename) = rest.split_once('/')?;
+    let is_safe =
+        |part: &str| !part.is_empty() && part != "." && part != ".." && !part.contains(['/', '\\']);
+    (is_safe(gist_id) && is_safe(filename)).then(|| (gist_id.to_string(), filename.to_string()))
+}
+
+fn invalid(message: String) -> GistCacheError {
+    GistCacheError::InvalidBundle(message)
+}
+
+/// Merge the bundle at `path` into the local cache
+///
+/// Gists missing locally are added. When both sides have a Gist, the copy
+/// with the later `updated_at` wins; on a tie the local metadata is kept and
+/// only files not cached locally are filled in from the bundle.
+///
+/// Callers hold the `CacheLock`.
+pub fn import_bundle(config: &Config, path: &Path) -> Result<ImportSummary> {
+    let bundle = read_bundle(path)?;
+    let metadata_store = open_metadata_store(config)?;
+    let content_store = open_content_store(config)?;
+
+    let mut merged = if metadata_store.cache_exists() {
+        metadata_store.load()?
+    } else {
+        GistCache {
+            metadata: bundle.cache.metadata.clone(),
+            gists: Vec::new(),
+        }
+    };
+
+    let mut summary = ImportSummary::default();
+    let no_files = Vec::new();
+    for gist in bundle.cache.gists {
+        let files = bundle.contents.get(&gist.id).unwrap_or(&no_files);
+
+        match merged.gists.iter().position(|g| g.id == gist.id) {
+            None => {
+                for (filename, content) in files {
+                    content_store.write(&gist.id, filename, content)?;
+                    summary.files += 1;
+                }
+                merged.gists.push(gist);
+                summary.added += 1;
+            }
+            Some(index) if gist.updated_at > merged.gists[index].updated_at => {
+                // Local contents belong to an older revision of the gist
+                content_store.delete_gist(&gist.id)?;
+                for (filename, content) in files {
+                    content_store.write(&gist.id, filename, content)?;
+                    summary.files += 1;
+                }
+                merged.gists[index] = gist;
+                summary.updated += 1;
+            }
+            Some(index) => {
+                let local = &merged.gists[index];
+                if gist.updated_at == local.updated_at {
+                    for (filename, content) in files {
+                        let known = local.files.iter().any(|f| &f.filename == filename);
+                        if known && !content_store.exists(&gist.id, filename) {
+                            content_store.write(&gist.id, filename, content)?;
+                            summary.files += 1;
+                        }
+                    }
+                }
+                summary.kept += 1;
+            }
+        }
+    }
+
+    merged
+        .gists
+        .sort_by_key(|g| std::cmp::Reverse(g.updated_at));
+    merged.metadata.total_count = merged.gists.len();
+    metadata_store.save(&merged)?;
+
+    // Writes skip eviction while the cache is locked, so enforce the size
+    // limit once everything is in
+    if let Some(max_size) = config.cache_max_size() {
+        content_store.evict_lru(max_size, None, &[], false)?;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::ContentCache;
+    use crate::cache::types::{CacheMetadata, GistFile, GistInfo};
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn test_gist(id: &str, day: u32, filenames: &[&str]) -> GistInfo {
+        GistInfo {
+            id: id.to_string(),
+            description: Some(format!("Gist {} day {}", id, day)),
+            files: filenames
+                .iter()
+                .map(|name| GistFile {
+                    filename: name.to_string(),
+                    language: None,
+                    size: 10,
+                    revision: None,
+                })
+                .collect(),
+            updated_at: Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+            public: true,
+            html_url: format!("https://gist.github.com/{}", id),
+        }
+    }
+
+    fn seed(config: &Config, gists: Vec<GistInfo>, files: &[(&str, &str, &str)]) {
+        let cache = GistCache {
+            metadata: CacheMetadata {
+                last_updated: Utc::now(),
+                total_count: gists.len(),
+                github_user: "testuser".to_string(),
+            },
+            gists,
+        };
+        open_metadata_store(config).unwrap().save(&cache).unwrap();
+        let contents = ContentCache::new(config.contents_dir.clone());
+        for (gist_id, filename, content) in files {
+            contents.write(gist_id, filename, content).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_export_and_import_into_empty_cache() {
+        let source = TempDir::new().unwrap();
+        let target = TempDir::new().unwrap();
+        let bundle_path = source.path().join("snapshot.tar.zst");
+
+        let source_config = Config::for_test(source.path());
+        seed(
+            &source_config,
+            vec![
+                test_gist("aaa", 2, &["a.sh"]),
+                test_gist("bbb", 1, &["b.py"]),
+            ],
+            &[("aaa", "a.sh", "echo a"), ("bbb", "b.py", "print('b')")],
+        );
+
+        let exported = export_bundle(&source_config, &bundle_path).unwrap();
+        assert_eq!(exported.gists, 2);
+        assert_eq!(exported.files, 2);
+        assert!(!source.path().join("snapshot.tar.zst.tmp").exists());
+
+        let target_config = Config::for_test(target.path());
+        let imported = import_bundle(&target_config, &bundle_path).unwrap();
+        assert_eq!(imported.added, 2);
+        assert_eq!(imported.files, 2);
+
+        let cache = open_metadata_store(&target_config).unwrap().load().unwrap();
+        assert_eq!(cache.metadata.github_user, "testuser");
+        assert_eq!(cache.metadata.total_count, 2);
+        let contents = ContentCache::new(target_config.contents_dir.clone());
+        assert_eq!(contents.read_verified("bbb", "b.py").unwrap(), "print('b')");
+    }
+
+    #[test]
+    fn test_failed_export_removes_temp_file() {
+        let source = TempDir::new().unwrap();
+        let config = Config::for_test(source.path());
+        seed(
+            &config,
+            vec![test_gist("aaa", 1, &["a.sh"])],
+            &[("aaa", "a.sh", "echo a")],
+        );
+
+        // The bundle is written, but cannot be renamed onto a directory
+        let bundle_path = source.path().join("occupied");
+        fs::create_dir_all(bundle_path.join("inside")).unwrap();
+
+        assert!(export_bundle(&config, &bundle_path).is_err());
+        assert!(!source.path().join("occupied.tmp").exists());
+        assert!(bundle_path.join("inside").is_dir());
+    }
+
+    #[test]
+    fn test_import_resolves_conflicts_by_updated_at() {
+        let source = TempDir::new().unwrap();
+        let target = TempDir::new().unwrap();
+        let bundle_path = source.path().join("snapshot.tar.zst");
+
+        let source_config = Config::for_test(source.path());
+        seed(
+            &source_config,
+            vec![
+                test_gist("newer", 5, &["n.sh"]),
+                test_gist("older", 1, &["o.sh"]),
+                test_gist("same", 3, &["s.sh", "t.sh"]),
+            ],
+            &[
+                ("newer", "n.sh", "bundle n"),
+                ("older", "o.sh", "bundle o"),
+                ("same", "s.sh", "bundle s"),
+                ("same", "t.sh", "bundle t"),
+            ],
+        );
+        export_bundle(&source_config, &bundle_path).unwrap();
+
+        let target_config = Config::for_test(target.path());
+        seed(
+            &target_config,
+            vec![
+                test_gist("newer", 2, &["n.sh"]),
+                test_gist("older", 4, &["o.sh"]),
+                test_gist("same", 3, &["s.sh", "t.sh"]),
+            ],
+            &[
+                ("newer", "n.sh", "local n"),
+                ("older", "o.sh", "local o"),
+                ("same", "s.sh", "local s"),
+            ],
+        );
+
+        let summary = import_bundle(&target_config, &bundle_path).unwrap();
+        assert_eq!(summary.added, 0);
+        assert_eq!(summary.updated, 1);
+        assert_eq!(summary.kept, 2);
+
+        let contents = ContentCache::new(target_config.contents_dir.clone());
+        assert_eq!(contents.read("newer", "n.sh").unwrap(), "bundle n");
+        assert_eq!(contents.read("older", "o.sh").unwrap(), "local o");
+        // On a tie, local files win and missing ones are filled in
+        assert_eq!(contents.read("same", "s.sh").unwrap(), "local s");
+        assert_eq!(contents.read("same", "t.sh").unwrap(), "bundle t");
+
+        let cache = open_metadata_store(&target_config).unwrap().load().unwrap();
+        let ids: Vec<_> = cache.gists.iter().map(|g| g.id.as_str()).collect();
+        assert_eq!(ids, vec!["newer", "older", "same"]);
+        assert_eq!(
+            cache.gists[0].description.as_deref(),
+            Some("Gist newer day 5")
+        );
+    }
+
+    #[test]
+    fn test_import_rejects_unsafe_or_tampered_bundles() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+
+        let write_bundle = |entries: &[(&str, &str)]| {
+            let path = temp_dir.path().join("bad.tar.zst");
+            let mut builder =
+                tar::Builder::new(zstd::Encoder::new(File::create(&path).unwrap(), 0).unwrap());
+            for (name, data) in entries {
+                append_entry(&mut builder, name, data.as_bytes()).unwrap();
+            }
+            builder.into_inner().unwrap().finish().unwrap();
+            path
+        };
+
+        let cache = serde_json::to_string(&GistCache {
+            metadata: CacheMetadata {
+                last_updated: Utc::now(),
+                total_count: 0,
+                github_user: "testuser".to_string(),
+            },
+            gists: vec![],
+        })
+        .unwrap();
+        let manifest = |sha256: &str| {
+            serde_json::to_string(&BundleManifest {
+                format_version: BUNDLE_FORMAT_VERSION,
+                created_at: Utc::now(),
+                tool_version: "test".to_string(),
+                github_user: "testuser".to_string(),
+                gists: 0,
+                files: vec![BundleFile {
+                    gist_id: "abc".to_string(),
+                    filename: "run.sh".to_string(),
+                    size: 4,
+                    sha256: sha256.to_string(),
+                }],
+            })
+            .unwrap()
+        };
+
+        let tampered = write_bundle(&[
+            (MANIFEST_ENTRY, &manifest(&sha256_hex(b"safe"))),
+            (CACHE_ENTRY, &cache),
+            ("contents/abc/run.sh", "evil"),
+        ]);
+        assert!(matches!(
+            import_bundle(&config, &tampered),
+            Err(GistCacheError::IntegrityCheckFailed(_))
+        ));
+
+        let nested = write_bundle(&[
+            (MANIFEST_ENTRY, &manifest(&sha256_hex(b"safe"))),
+            (CACHE_ENTRY, &cache),
+            ("contents/abc/nested/run.sh", "safe"),
+        ]);
+        assert!(matches!(
+            import_bundle(&config, &nested),
+            Err(GistCacheError::InvalidBundle(_))
+        ));
+
+        let missing_cache = write_bundle(&[(MANIFEST_ENTRY, &manifest(&sha256_hex(b"safe")))]);
+        assert!(matches!(
+            import_bundle(&config, &missing_cache),
+            Err(GistCacheError::InvalidBundle(_))
+        ));
+
+        // Nothing was written by the rejected imports
+        assert!(!config.cache_file.exists());
+    }
+}
@@ -1,13 +1,68 @@
+use crate::cache::lock::{CacheLock, DEFAULT_LOCK_TIMEOUT_SECS, unless_locked};
+use crate::cache::storage::{ContentStore, is_temp_file, write_atomic};
 use crate::cache::types::GistCache;
 use crate::error::{GistCacheError, Result};
-use chrono::{Duration, Utc};
-use std::collections::HashSet;
+use chrono::{DateTime, Duration, Utc};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration as StdDuration;
+
+/// Suffix of the per-Gist integrity manifest stored next to the Gist directory
+/// (`contents/<id>.manifest.json`), so the Gist directory itself only ever
+/// holds the cached files
+const MANIFEST_SUFFIX: &str = ".manifest.json";
+
+/// Suffix of the per-Gist access time file (`contents/<id>.access`), kept
+/// apart from the manifest so reads never rewrite the integrity manifest
+const ACCESS_SUFFIX: &str = ".access";
+
+/// Lock file serializing manifest updates (`contents/.manifest.lock`)
+const MANIFEST_LOCK_FILE: &str = ".manifest.lock";
+
+/// Integrity manifest for the cached files of one Gist
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContentManifest {
+    /// Entries keyed by file name
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+/// Integrity record for a single cached file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// SHA-256 of the file content (lowercase hex)
+    pub sha256: String,
+}
+
+/// Result of checking a cached file against its manifest entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityStatus {
+    /// Content matches the recorded hash
+    Valid,
+    /// Content differs from the recorded hash
+    Mismatch { expected: String, actual: String },
+    /// Manifest has an entry but the file is gone
+    Missing,
+    /// File exists but has no recorded hash (cached before manifests existed,
+    /// or its manifest entry was removed)
+    Untracked,
+}
+
+/// Compute the SHA-256 of `content` as lowercase hex
+pub fn sha256_hex(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
 
 /// Structure for managing Gist content cache
 pub struct ContentCache {
     cache_dir: PathBuf,
+    /// Size limit enforced after each write (`cache.max_size`)
+    max_size: Option<u64>,
+    /// Directory holding the `CacheLock` (see `with_lock_dir`)
+    lock_dir: Option<PathBuf>,
 }
 
 impl ContentCache {
@@ -16,7 +71,32 @@ impl ContentCache {
     /// # Arguments
     /// * `cache_dir` - Cache directory path (~/.cache/gist-cache/contents)
     pub fn new(cache_dir: PathBuf) -> Self {
-        Self { cache_dir }
+        Self {
+            cache_dir,
+            max_size: None,
+            lock_dir: None,
+        }
+    }
+
+    /// Evict least recently used Gists after each write so the cache stays
+    /// within `max_size` bytes (`None` = unlimited)
+    pub fn with_max_size(mut self, max_size: Option<u64>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Only record access times on `read` and evict on `write` while the
+    /// `CacheLock` in `lock_dir` is free, skipping them otherwise
+    ///
+    /// Without a lock directory the caller holds the lock itself.
+    pub fn with_lock_dir(mut self, lock_dir: PathBuf) -> Self {
+        self.lock_dir = Some(lock_dir);
+        self
+    }
+
+    /// Directory holding the cached contents
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
     }
 
     /// Ensure cache directory exists
@@ -50,6 +130,23 @@ impl ContentCache {
         self.cache_dir.join(gist_id)
     }
 
+    /// Get integrity manifest path for a Gist
+    ///
+    /// # Arguments
+    /// * `gist_id` - Gist ID
+    ///
+    /// # Returns
+    /// Full path to manifest file
+    fn get_manifest_path(&self, gist_id: &str) -> PathBuf {
+        self.cache_dir
+            .join(format!("{}{}", gist_id, MANIFEST_SUFFIX))
+    }
+
+    /// Get access time file path for a Gist
+    fn get_access_path(&self, gist_id: &str) -> PathBuf {
+        self.cache_dir.join(format!("{}{}", gist_id, ACCESS_SUFFIX))
+    }
+
     /// Check if cache file exists
     ///
     /// # Arguments
@@ -77,7 +174,17 @@ impl ContentCache {
     /// # Self-healing Principle
     /// If cache file is corrupted, return error instead of panicking,
     /// allowing caller to re-fetch from API
+    ///
+    /// # Implementation Details
+    /// - Records the access time for LRU eviction (best-effort, skipped while
+    ///   the cache is locked)
     pub fn read(&self, gist_id: &str, filename: &str) -> Result<String> {
+        let bytes = self.read_bytes(gist_id, filename)?;
+        self.decode(gist_id, filename, bytes)
+    }
+
+    /// Raw bytes of a cached file, recording the access (see `read`)
+    fn read_bytes(&self, gist_id: &str, filename: &str) -> Result<Vec<u8>> {
         let path = self.get_cache_path(gist_id, filename);
 
         if !path.exists() {
@@ -87,7 +194,9 @@ impl ContentCache {
             )));
         }
 
-        fs::read_to_string(&path).map_err(|e| {
+        let _ = unless_locked(self.lock_dir.as_deref(), || self.record_access(gist_id));
+
+        fs::read(&path).map_err(|e| {
             GistCacheError::CacheReadError(format!(
                 "Failed to read cache file {}: {}",
                 path.display(),
@@ -96,6 +205,28 @@ impl ContentCache {
         })
     }
 
+    /// Decode the bytes of a cached file as UTF-8 text
+    fn decode(&self, gist_id: &str, filename: &str, bytes: Vec<u8>) -> Result<String> {
+        String::from_utf8(bytes).map_err(|e| {
+            GistCacheError::CacheReadError(format!(
+                "Failed to read cache file {}: {}",
+                self.get_cache_path(gist_id, filename).display(),
+                e
+            ))
+        })
+    }
+
+    /// Record that a cached Gist was just used, for LRU eviction
+    ///
+    /// Replaces the Gist's access time file (`<id>.access`); the integrity
+    /// manifest is never touched. Does nothing for a Gist that is not cached.
+    pub fn record_access(&self, gist_id: &str) -> Result<()> {
+        if !self.get_gist_dir(gist_id).exists() {
+            return Ok(());
+        }
+        write_atomic(&self.get_access_path(gist_id), &Utc::now().to_rfc3339())
+    }
+
     /// Write content to cache file
     ///
     /// # Arguments
@@ -109,6 +240,10 @@ impl ContentCache {
     /// # Implementation Details
     /// - Atomic write (temp file → rename) to avoid concurrent access conflicts
     /// - Automatically creates Gist directory if it doesn't exist
+    /// - Records the SHA-256 of `content` in the Gist's integrity manifest,
+    ///   under the manifest lock so concurrent writes keep each other's entries
+    /// - Evicts least recently used Gists (never this one) when over `max_size`,
+    ///   unless the cache is locked
     pub fn write(&self, gist_id: &str, filename: &str, content: &str) -> Result<()> {
         let gist_dir = self.get_gist_dir(gist_id);
         let cache_path = self.get_cache_path(gist_id, filename);
@@ -124,30 +259,312 @@ impl ContentCache {
             })?;
         }
 
-        // Atomic write: temp file → rename
-        let temp_path = cache_path.with_extension("tmp");
-
-        // Write to temp file
-        fs::write(&temp_path, content).map_err(|e| {
+        // Atomic write: unique temp file → rename
+        write_atomic(&cache_path, content).map_err(|e| {
             GistCacheError::CacheWriteError(format!(
-                "Failed to write temp file {}: {}",
-                temp_path.display(),
+                "Failed to write cache file {}: {}",
+                cache_path.display(),
+                e
+            ))
+        })?;
+
+        // Record hash for tamper detection
+        self.update_manifest(gist_id, |manifest| {
+            manifest.files.insert(
+                filename.to_string(),
+                ManifestEntry {
+                    sha256: sha256_hex(content.as_bytes()),
+                },
+            );
+            true
+        })?;
+        self.record_access(gist_id)?;
+
+        // Self-healing principle: the write itself succeeded, eviction is best-effort
+        if let Some(max_size) = self.max_size
+            && let Some(Err(e)) = unless_locked(self.lock_dir.as_deref(), || {
+                self.evict_lru(max_size, Some(gist_id), &[], false)
+            })
+        {
+            eprintln!(
+                "{}",
+                format!("Warning: Failed to evict old cache entries: {}", e).yellow()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// When a Gist was last run or written
+    ///
+    /// # Returns
+    /// The time in the Gist's access time file, or the Gist directory's
+    /// modification time for Gists cached before access times were recorded
+    pub fn last_accessed(&self, gist_id: &str) -> Option<DateTime<Utc>> {
+        if let Some(time) = fs::read_to_string(self.get_access_path(gist_id))
+            .ok()
+            .and_then(|t| DateTime::parse_from_rfc3339(t.trim()).ok())
+        {
+            return Some(time.with_timezone(&Utc));
+        }
+
+        fs::metadata(self.get_gist_dir(gist_id))
+            .and_then(|m| m.modified())
+            .ok()
+            .map(DateTime::<Utc>::from)
+    }
+
+    /// Load the integrity manifest of a Gist
+    ///
+    /// # Returns
+    /// Empty manifest if none has been recorded yet
+    ///
+    /// # Errors
+    /// Returns error if the manifest exists but cannot be read or parsed
+    pub fn load_manifest(&self, gist_id: &str) -> Result<ContentManifest> {
+        let path = self.get_manifest_path(gist_id);
+
+        if !path.exists() {
+            return Ok(ContentManifest::default());
+        }
+
+        let content = fs::read_to_string(&path).map_err(|e| {
+            GistCacheError::CacheReadError(format!(
+                "Failed to read manifest {}: {}",
+                path.display(),
                 e
             ))
         })?;
+        Ok(serde_json::from_str(&content)?)
+    }
 
-        // Atomically replace file with rename
-        fs::rename(&temp_path, &cache_path).map_err(|e| {
-            // Remove temp file if failed
-            let _ = fs::remove_file(&temp_path);
+    /// Update the integrity manifest of a Gist
+    ///
+    /// The read-modify-write runs under the manifest lock, so two processes
+    /// caching files of the same Gist never drop each other's entries. An
+    /// unreadable manifest is replaced. The manifest is only saved when `f`
+    /// returns `true`.
+    fn update_manifest(
+        &self,
+        gist_id: &str,
+        f: impl FnOnce(&mut ContentManifest) -> bool,
+    ) -> Result<()> {
+        let _lock = CacheLock::acquire_at(
+            &self.cache_dir.join(MANIFEST_LOCK_FILE),
+            StdDuration::from_secs(DEFAULT_LOCK_TIMEOUT_SECS),
+        )?;
+
+        let mut manifest = self.load_manifest(gist_id).unwrap_or_default();
+        if !f(&mut manifest) {
+            return Ok(());
+        }
+
+        let path = self.get_manifest_path(gist_id);
+        write_atomic(&path, &serde_json::to_string_pretty(&manifest)?).map_err(|e| {
             GistCacheError::CacheWriteError(format!(
-                "Failed to rename temp file to cache file {}: {}",
-                cache_path.display(),
+                "Failed to write manifest {}: {}",
+                path.display(),
                 e
             ))
+        })
+    }
+
+    /// Check a cached file against its recorded hash
+    ///
+    /// # Arguments
+    /// * `gist_id` - Gist ID
+    /// * `filename` - File name
+    ///
+    /// # Returns
+    /// Integrity status of the file
+    pub fn verify(&self, gist_id: &str, filename: &str) -> Result<IntegrityStatus> {
+        let manifest = self.load_manifest(gist_id)?;
+        let path = self.get_cache_path(gist_id, filename);
+
+        let Some(entry) = manifest.files.get(filename) else {
+            return Ok(if path.exists() {
+                IntegrityStatus::Untracked
+            } else {
+                IntegrityStatus::Missing
+            });
+        };
+
+        if !path.exists() {
+            return Ok(IntegrityStatus::Missing);
+        }
+
+        let actual = sha256_hex(&fs::read(&path)?);
+        if actual == entry.sha256 {
+            Ok(IntegrityStatus::Valid)
+        } else {
+            Ok(IntegrityStatus::Mismatch {
+                expected: entry.sha256.clone(),
+                actual,
+            })
+        }
+    }
+
+    /// List the files cached for a Gist (in-flight temp files excluded)
+    ///
+    /// # Returns
+    /// File names sorted by name; empty if the Gist is not cached
+    pub fn list_files(&self, gist_id: &str) -> Result<Vec<String>> {
+        let gist_dir = self.get_gist_dir(gist_id);
+        let mut filenames = Vec::new();
+
+        if gist_dir.exists() {
+            for entry in fs::read_dir(&gist_dir)?.flatten() {
+                let path = entry.path();
+                if path.is_file()
+                    && let Some(name) = path.file_name().and_then(|n| n.to_str())
+                    && !is_temp_file(name)
+                {
+                    filenames.push(name.to_string());
+                }
+            }
+        }
+        filenames.sort();
+
+        Ok(filenames)
+    }
+
+    /// Check that a cached file can be read as UTF-8 text
+    ///
+    /// # Returns
+    /// `None` if it reads fine, otherwise the reason it cannot be read
+    pub fn check_readable(&self, gist_id: &str, filename: &str) -> Option<String> {
+        let path = self.get_cache_path(gist_id, filename);
+
+        match fs::read(&path) {
+            Ok(bytes) => String::from_utf8(bytes)
+                .err()
+                .map(|_| "not valid UTF-8".to_string()),
+            Err(e) => Some(e.to_string()),
+        }
+    }
+
+    /// Find temp files left behind by interrupted `write` or manifest saves
+    ///
+    /// # Returns
+    /// Paths of temp files (`.<name>.<random>.partial`) in the contents
+    /// directory and Gist directories, sorted by path
+    pub fn temp_files(&self) -> Result<Vec<PathBuf>> {
+        fn collect(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) -> std::io::Result<()> {
+            for entry in fs::read_dir(dir)?.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    if depth > 0 {
+                        collect(&path, depth - 1, found)?;
+                    }
+                } else if path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(is_temp_file)
+                {
+                    found.push(path);
+                }
+            }
+            Ok(())
+        }
+
+        let mut found = Vec::new();
+        if self.cache_dir.exists() {
+            collect(&self.cache_dir, 1, &mut found)?;
+        }
+        found.sort();
+
+        Ok(found)
+    }
+
+    /// Check every file of a cached Gist (cached files plus manifest entries)
+    ///
+    /// # Returns
+    /// Status per file name, sorted by file name
+    pub fn verify_gist(&self, gist_id: &str) -> Result<Vec<(String, IntegrityStatus)>> {
+        let manifest = self.load_manifest(gist_id)?;
+        let mut filenames: Vec<String> = manifest.files.keys().cloned().collect();
+
+        for name in self.list_files(gist_id)? {
+            if !manifest.files.contains_key(&name) {
+                filenames.push(name);
+            }
+        }
+        filenames.sort();
+
+        filenames
+            .into_iter()
+            .map(|name| {
+                let status = self.verify(gist_id, &name)?;
+                Ok((name, status))
+            })
+            .collect()
+    }
+
+    /// Read content from cache after checking it against the manifest
+    ///
+    /// # Errors
+    /// Returns `IntegrityCheckFailed` if the file no longer matches its
+    /// recorded hash or has no recorded hash at all, otherwise the same
+    /// errors as `read`
+    ///
+    /// # Implementation Details
+    /// - The file is read once; the bytes that were hashed are the bytes returned
+    /// - Files cached before manifests existed are only trusted once `track`
+    ///   has recorded their hash (`cache verify --track`)
+    pub fn read_verified(&self, gist_id: &str, filename: &str) -> Result<String> {
+        let manifest = self.load_manifest(gist_id)?;
+        let bytes = self.read_bytes(gist_id, filename)?;
+        let actual = sha256_hex(&bytes);
+
+        match manifest.files.get(filename) {
+            Some(entry) if entry.sha256 == actual => {}
+            Some(entry) => {
+                return Err(GistCacheError::IntegrityCheckFailed(format!(
+                    "{}/{} (expected sha256 {}, found {})",
+                    gist_id, filename, entry.sha256, actual
+                )));
+            }
+            None => {
+                return Err(GistCacheError::IntegrityCheckFailed(format!(
+                    "{}/{} (no recorded sha256)",
+                    gist_id, filename
+                )));
+            }
+        }
+
+        self.decode(gist_id, filename, bytes)
+    }
+
+    /// Record the hash of a file cached before manifests existed
+    ///
+    /// One-time migration for old caches: a file that already has a manifest
+    /// entry is left alone, so a modified file is never accepted this way.
+    ///
+    /// # Returns
+    /// `Ok(true)` if a hash was recorded, `Ok(false)` if the file was already
+    /// tracked or isn't cached
+    pub fn track(&self, gist_id: &str, filename: &str) -> Result<bool> {
+        if self.verify(gist_id, filename)? != IntegrityStatus::Untracked {
+            return Ok(false);
+        }
+
+        let content = fs::read(self.get_cache_path(gist_id, filename))?;
+        let mut tracked = false;
+        self.update_manifest(gist_id, |manifest| {
+            // Another process may have recorded it in the meantime
+            tracked = !manifest.files.contains_key(filename);
+            if tracked {
+                manifest.files.insert(
+                    filename.to_string(),
+                    ManifestEntry {
+                        sha256: sha256_hex(&content),
+                    },
+                );
+            }
+            tracked
         })?;
 
-        Ok(())
+        Ok(tracked)
     }
 
     /// Delete cache for a specific Gist
@@ -163,6 +580,10 @@ impl ContentCache {
     pub fn delete_gist(&self, gist_id: &str) -> Result<bool> {
         let gist_dir = self.get_gist_dir(gist_id);
 
+        // The manifest and access time describe the directory, so they go with it
+        let _ = fs::remove_file(self.get_manifest_path(gist_id));
+        let _ = fs::remove_file(self.get_access_path(gist_id));
+
         if !gist_dir.exists() {
             // Skip if directory doesn't exist (not an error)
             return Ok(false); // Not deleted
@@ -181,6 +602,46 @@ impl ContentCache {
         Ok(true) // Deleted
     }
 
+    /// Delete a single cached file of a Gist
+    ///
+    /// # Arguments
+    /// * `gist_id` - Gist ID
+    /// * `filename` - File name
+    ///
+    /// # Returns
+    /// `Ok(true)` if actually deleted, `Ok(false)` if it wasn't cached
+    ///
+    /// # Errors
+    /// Returns error if the file exists but cannot be removed
+    ///
+    /// # Self-healing Principle
+    /// The Gist directory (and manifest) is removed once its last file is gone,
+    /// so an emptied Gist never shows up as cached
+    pub fn delete_file(&self, gist_id: &str, filename: &str) -> Result<bool> {
+        let cache_path = self.get_cache_path(gist_id, filename);
+        let existed = cache_path.exists();
+
+        if existed {
+            fs::remove_file(&cache_path).map_err(|e| {
+                GistCacheError::CacheDeleteError(format!(
+                    "Failed to delete cache file {}: {}",
+                    cache_path.display(),
+                    e
+                ))
+            })?;
+        }
+
+        if self.list_files(gist_id)?.is_empty() {
+            self.delete_gist(gist_id)?;
+        } else {
+            self.update_manifest(gist_id, |manifest| {
+                manifest.files.remove(filename).is_some()
+            })?;
+        }
+
+        Ok(existed)
+    }
+
     /// Get all cached Gist IDs
     ///
     /// # Returns
@@ -294,7 +755,7 @@ impl ContentCache {
         // Process each cached gist
         for gist_id in &cached_gist_ids {
             let should_delete =
-                self.should_delete_gist(gist_id, &valid_gist_ids, metadata_cache, options)?;
+                should_clean_gist(gist_id, &valid_gist_ids, metadata_cache, options);
 
             if should_delete {
                 // Calculate size before deletion
@@ -313,32 +774,6 @@ impl ContentCache {
         Ok(result)
     }
 
-    /// Check if a gist should be deleted based on clean options
-    fn should_delete_gist(
-        &self,
-        gist_id: &str,
-        valid_gist_ids: &HashSet<String>,
-        metadata_cache: &GistCache,
-        options: &CleanOptions,
-    ) -> Result<bool> {
-        // Check for orphaned (not in metadata)
-        if options.orphaned && !valid_gist_ids.contains(gist_id) {
-            return Ok(true);
-        }
-
-        // Check for old entries
-        if let Some(days) = options.older_than_days {
-            if let Some(gist_info) = metadata_cache.gists.iter().find(|g| g.id == gist_id) {
-                let cutoff_date = Utc::now() - Duration::days(days as i64);
-                if gist_info.updated_at < cutoff_date {
-                    return Ok(true);
-                }
-            }
-        }
-
-        Ok(false)
-    }
-
     /// Calculate size of a directory
     fn calculate_dir_size(&self, path: &Path) -> Result<u64> {
         let mut total_size = 0u64;
@@ -368,6 +803,34 @@ impl ContentCache {
     }
 }
 
+/// Check if a gist should be deleted based on clean options
+///
+/// Shared by every content store so `cache clean` behaves the same
+/// regardless of the storage backend.
+pub(crate) fn should_clean_gist(
+    gist_id: &str,
+    valid_gist_ids: &HashSet<String>,
+    metadata_cache: &GistCache,
+    options: &CleanOptions,
+) -> bool {
+    // Check for orphaned (not in metadata)
+    if options.orphaned && !valid_gist_ids.contains(gist_id) {
+        return true;
+    }
+
+    // Check for old entries
+    if let Some(days) = options.older_than_days
+        && let Some(gist_info) = metadata_cache.gists.iter().find(|g| g.id == gist_id)
+    {
+        let cutoff_date = Utc::now() - Duration::days(days as i64);
+        if gist_info.updated_at < cutoff_date {
+            return true;
+        }
+    }
+
+    false
+}
+
 /// Options for cache cleaning
 #[derive(Debug, Clone, Default)]
 pub struct CleanOptions {
@@ -680,6 +1143,7 @@ mod tests {
                 filename: "old.sh".to_string(),
                 language: Some("Shell".to_string()),
                 size: 100,
+                revision: None,
             }],
             updated_at: old_date,
             public: true,
@@ -693,6 +1157,7 @@ mod tests {
                 filename: "new.sh".to_string(),
                 language: Some("Shell".to_string()),
                 size: 100,
+                revision: None,
             }],
             updated_at: new_date,
             public: true,
@@ -743,6 +1208,7 @@ mod tests {
                 filename: "valid.sh".to_string(),
                 language: Some("Shell".to_string()),
                 size: 100,
+                revision: None,
             }],
             updated_at: Utc::now(),
             public: true,
@@ -794,6 +1260,7 @@ mod tests {
                 filename: "old.sh".to_string(),
                 language: Some("Shell".to_string()),
                 size: 100,
+                revision: None,
             }],
             updated_at: Utc::now() - Duration::days(40),
             public: true,
@@ -841,6 +1308,7 @@ mod tests {
                 filename: "test.sh".to_string(),
                 language: Some("Shell".to_string()),
                 size: 100,
+                revision: None,
             }],
             updated_at: Utc::now(),
             public: true,
@@ -887,6 +1355,7 @@ mod tests {
                 filename: "old.sh".to_string(),
                 language: Some("Shell".to_string()),
                 size: 100,
+                revision: None,
             }],
             updated_at: Utc::now() - Duration::days(40),
             public: true,
@@ -939,6 +1408,7 @@ mod tests {
                 filename: "recent.sh".to_string(),
                 language: Some("Shell".to_string()),
                 size: 100,
+                revision: None,
             }],
             updated_at: Utc::now(),
             public: true,
@@ -988,4 +1458,242 @@ mod tests {
         // file1.sh: 5 bytes, file2.sh: 10 bytes
         assert_eq!(size, 15);
     }
+
+    #[test]
+    fn test_write_records_manifest_hash() {
+        let (_temp, cache) = setup_test_cache();
+
+        cache.write("hashed", "run.sh", "echo hi").unwrap();
+
+        let manifest = cache.load_manifest("hashed").unwrap();
+        assert_eq!(
+            manifest.files["run.sh"].sha256,
+            sha256_hex("echo hi".as_bytes())
+        );
+        assert_eq!(
+            cache.verify("hashed", "run.sh").unwrap(),
+            IntegrityStatus::Valid
+        );
+    }
+
+    #[test]
+    fn test_verify_detects_tampered_file() {
+        let (_temp, cache) = setup_test_cache();
+
+        cache.write("tampered", "run.sh", "echo safe").unwrap();
+        fs::write(cache.get_cache_path("tampered", "run.sh"), "rm -rf /").unwrap();
+
+        assert!(matches!(
+            cache.verify("tampered", "run.sh").unwrap(),
+            IntegrityStatus::Mismatch { .. }
+        ));
+        assert!(matches!(
+            cache.read_verified("tampered", "run.sh").unwrap_err(),
+            GistCacheError::IntegrityCheckFailed(_)
+        ));
+
+        // Rewriting through the cache heals the entry
+        cache.write("tampered", "run.sh", "echo safe").unwrap();
+        assert_eq!(
+            cache.read_verified("tampered", "run.sh").unwrap(),
+            "echo safe"
+        );
+    }
+
+    #[test]
+    fn test_verify_untracked_and_missing() {
+        let (_temp, cache) = setup_test_cache();
+
+        // File placed without going through write() (pre-manifest cache)
+        fs::create_dir_all(cache.get_gist_dir("legacy")).unwrap();
+        fs::write(cache.get_cache_path("legacy", "old.sh"), "echo old").unwrap();
+        assert_eq!(
+            cache.verify("legacy", "old.sh").unwrap(),
+            IntegrityStatus::Untracked
+        );
+        assert!(matches!(
+            cache.read_verified("legacy", "old.sh").unwrap_err(),
+            GistCacheError::IntegrityCheckFailed(_)
+        ));
+
+        // Tracking it once trusts it from then on
+        assert!(cache.track("legacy", "old.sh").unwrap());
+        assert_eq!(cache.read_verified("legacy", "old.sh").unwrap(), "echo old");
+
+        // Removing the manifest does not get a modified file past the check,
+        // and tracking never accepts a file that already had a hash
+        cache.write("removed", "run.sh", "echo safe").unwrap();
+        assert!(!cache.track("removed", "run.sh").unwrap());
+        fs::remove_file(cache.get_manifest_path("removed")).unwrap();
+        fs::write(cache.get_cache_path("removed", "run.sh"), "rm -rf /").unwrap();
+        assert!(matches!(
+            cache.read_verified("removed", "run.sh").unwrap_err(),
+            GistCacheError::IntegrityCheckFailed(_)
+        ));
+
+        cache.write("gone", "run.sh", "echo").unwrap();
+        fs::remove_file(cache.get_cache_path("gone", "run.sh")).unwrap();
+        assert_eq!(
+            cache.verify("gone", "run.sh").unwrap(),
+            IntegrityStatus::Missing
+        );
+    }
+
+    #[test]
+    fn test_verify_gist_lists_all_files() {
+        let (_temp, cache) = setup_test_cache();
+
+        cache.write("multi", "a.sh", "a").unwrap();
+        cache.write("multi", "b.sh", "b").unwrap();
+        fs::write(cache.get_cache_path("multi", "b.sh"), "changed").unwrap();
+        fs::write(cache.get_cache_path("multi", "c.sh"), "c").unwrap();
+
+        let statuses = cache.verify_gist("multi").unwrap();
+        let names: Vec<&str> = statuses.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["a.sh", "b.sh", "c.sh"]);
+        assert_eq!(statuses[0].1, IntegrityStatus::Valid);
+        assert!(matches!(statuses[1].1, IntegrityStatus::Mismatch { .. }));
+        assert_eq!(statuses[2].1, IntegrityStatus::Untracked);
+    }
+
+    #[test]
+    fn test_delete_gist_removes_manifest() {
+        let (_temp, cache) = setup_test_cache();
+
+        cache.write("with_manifest", "run.sh", "echo").unwrap();
+        assert!(cache.get_manifest_path("with_manifest").exists());
+
+        cache.delete_gist("with_manifest").unwrap();
+        assert!(!cache.get_manifest_path("with_manifest").exists());
+    }
+
+    #[test]
+    fn test_delete_file_keeps_other_files() {
+        let (_temp, cache) = setup_test_cache();
+
+        cache.write("partial", "script.sh", "echo").unwrap();
+        cache.write("partial", "data.csv", "a,b\n1,2").unwrap();
+
+        assert!(cache.delete_file("partial", "script.sh").unwrap());
+        assert!(!cache.delete_file("partial", "script.sh").unwrap());
+        assert!(!cache.exists("partial", "script.sh"));
+        assert_eq!(cache.read("partial", "data.csv").unwrap(), "a,b\n1,2");
+
+        let manifest = cache.load_manifest("partial").unwrap();
+        assert!(!manifest.files.contains_key("script.sh"));
+        assert!(manifest.files.contains_key("data.csv"));
+
+        // Removing the last file removes the Gist entirely
+        assert!(cache.delete_file("partial", "data.csv").unwrap());
+        assert!(cache.list_cached_gists().unwrap().is_empty());
+        assert!(!cache.get_manifest_path("partial").exists());
+    }
+
+    #[test]
+    fn test_concurrent_writes_keep_manifest_entries() {
+        let (temp, _cache) = setup_test_cache();
+        let cache_dir = temp.path().join("contents");
+
+        // Separate instances, as separate processes would have, caching
+        // different files of the same Gist at once
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let cache_dir = cache_dir.clone();
+                std::thread::spawn(move || {
+                    let cache = ContentCache::new(cache_dir);
+                    for j in 0..5 {
+                        let name = format!("file{}_{}.sh", i, j);
+                        cache.write("shared", &name, &name).unwrap();
+                        cache.read("shared", &name).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let cache = ContentCache::new(cache_dir);
+        assert_eq!(cache.load_manifest("shared").unwrap().files.len(), 40);
+        assert!(
+            cache
+                .verify_gist("shared")
+                .unwrap()
+                .iter()
+                .all(|(_, status)| *status == IntegrityStatus::Valid)
+        );
+        assert!(cache.temp_files().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_record_access() {
+        let (_temp, cache) = setup_test_cache();
+
+        cache.write("accessed", "run.sh", "echo").unwrap();
+        let written = cache.last_accessed("accessed").unwrap();
+
+        // Reading records the access
+        cache.read("accessed", "run.sh").unwrap();
+        let read = cache.last_accessed("accessed").unwrap();
+        assert!(read >= written);
+
+        cache.record_access("accessed").unwrap();
+        assert!(cache.last_accessed("accessed").unwrap() >= read);
+
+        // A Gist that is not cached gets no access time
+        cache.record_access("missing").unwrap();
+        assert!(!cache.get_access_path("missing").exists());
+
+        // Gists cached before access times were recorded fall back to the mtime
+        fs::create_dir_all(cache.cache_dir().join("legacy")).unwrap();
+        fs::write(cache.cache_dir().join("legacy").join("old.sh"), "echo").unwrap();
+        assert!(cache.last_accessed("legacy").is_some());
+    }
+
+    #[test]
+    fn test_write_evicts_least_recently_used_gists() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = ContentCache::new(temp_dir.path().join("contents")).with_max_size(Some(25));
+
+        cache.write("first", "a.txt", "0123456789").unwrap();
+        cache.write("second", "b.txt", "0123456789").unwrap();
+        // Running "first" makes "second" the least recently used
+        cache.record_access("first").unwrap();
+
+        cache.write("third", "c.txt", "0123456789").unwrap();
+
+        assert!(cache.exists("first", "a.txt"));
+        assert!(!cache.exists("second", "b.txt"));
+        assert!(cache.exists("third", "c.txt"));
+
+        // The Gist just written is never evicted, even if it alone is too big
+        cache.write("huge", "big.txt", &"x".repeat(100)).unwrap();
+        assert_eq!(cache.list_cached_gists().unwrap(), vec!["huge".to_string()]);
+    }
+
+    #[test]
+    fn test_locked_cache_skips_access_and_eviction() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = ContentCache::new(temp_dir.path().join("contents"))
+            .with_max_size(Some(15))
+            .with_lock_dir(temp_dir.path().to_path_buf());
+
+        cache.write("first", "a.txt", "0123456789").unwrap();
+        let access_path = cache.get_access_path("first");
+        let before = fs::read_to_string(&access_path).unwrap();
+
+        // While another holder (e.g. `update` or `cache export`) has the lock,
+        // reads leave the access time alone and writes evict nothing
+        let held = crate::cache::CacheLock::try_acquire_in(temp_dir.path()).unwrap();
+        cache.read("first", "a.txt").unwrap();
+        assert_eq!(fs::read_to_string(&access_path).unwrap(), before);
+        cache.write("second", "b.txt", "0123456789").unwrap();
+        assert!(cache.exists("first", "a.txt"));
+
+        // Once it is released, the next write evicts as usual
+        drop(held);
+        cache.write("third", "c.txt", "0123456789").unwrap();
+        assert_eq!(cache.list_cached_gists().unwrap().len(), 1);
+        assert!(cache.exists("third", "c.txt"));
+    }
 }
@@ -0,0 +1,330 @@
+//! Whole-cache health check (`cache fsck`)
+//!
+//! Reads already heal themselves one file at a time; this walks the entire
+//! content cache against the metadata cache and reports everything that
+//! would otherwise linger until it happened to be touched.
+
+use crate::cache::storage::ContentStore;
+use crate::cache::types::GistCache;
+use crate::error::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// A problem found by `check`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FsckIssue {
+    /// Cached gist that is not in the metadata cache
+    OrphanedGist { gist_id: String },
+    /// Cached file or manifest entry whose name is not among the gist's files
+    StaleFile { gist_id: String, filename: String },
+    /// Manifest entry of one of the gist's files that is missing on disk
+    MissingFile { gist_id: String, filename: String },
+    /// Temp file left behind by an interrupted write
+    TempFile { path: PathBuf },
+    /// Cached file that cannot be read as UTF-8 text
+    UnreadableFile {
+        gist_id: String,
+        filename: String,
+        reason: String,
+    },
+    /// Integrity manifest that cannot be read or parsed
+    UnreadableManifest { gist_id: String, reason: String },
+}
+
+impl fmt::Display for FsckIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsckIssue::OrphanedGist { gist_id } => {
+                write!(f, "{}: cached but not in metadata", gist_id)
+            }
+            FsckIssue::StaleFile { gist_id, filename } => {
+                write!(f, "{}/{}: no longer a file of the Gist", gist_id, filename)
+            }
+            FsckIssue::MissingFile { gist_id, filename } => {
+                write!(
+                    f,
+                    "{}/{}: in the manifest but not cached",
+                    gist_id, filename
+                )
+            }
+            FsckIssue::TempFile { path } => {
+                write!(f, "{}: leftover temp file", path.display())
+            }
+            FsckIssue::UnreadableFile {
+                gist_id,
+                filename,
+                reason,
+            } => write!(f, "{}/{}: unreadable ({})", gist_id, filename, reason),
+            FsckIssue::UnreadableManifest { gist_id, reason } => {
+                write!(f, "{}: unreadable manifest ({})", gist_id, reason)
+            }
+        }
+    }
+}
+
+/// Result of `check`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FsckReport {
+    pub gists_checked: usize,
+    pub files_checked: usize,
+    pub issues: Vec<FsckIssue>,
+}
+
+/// Check the content cache against the metadata cache
+///
+/// # Arguments
+/// * `content_store` - Content cache to check
+/// * `metadata_cache` - Metadata the cached gists are checked against
+///
+/// # Returns
+/// Report listing every problem found; nothing is modified
+pub fn check(content_store: &dyn ContentStore, metadata_cache: &GistCache) -> Result<FsckReport> {
+    let gists: HashMap<&str, _> = metadata_cache
+        .gists
+        .iter()
+        .map(|g| (g.id.as_str(), g))
+        .collect();
+    let mut report = FsckReport::default();
+
+    let mut gist_ids = content_store.list_cached_gists()?;
+    gist_ids.sort();
+    for gist_id in gist_ids {
+        report.gists_checked += 1;
+
+        let Some(gist) = gists.get(gist_id.as_str()) else {
+            report.issues.push(FsckIssue::OrphanedGist { gist_id });
+            continue;
+        };
+
+        let filenames = content_store.list_files(&gist_id)?;
+        let mut unreadable = false;
+        for filename in &filenames {
+            report.files_checked += 1;
+
+            if !gist.files.iter().any(|f| &f.filename == filename) {
+                report.issues.push(FsckIssue::StaleFile {
+                    gist_id: gist_id.clone(),
+                    filename: filename.clone(),
+                });
+            } else if let Some(reason) = content_store.check_readable(&gist_id, filename) {
+                unreadable = true;
+                report.issues.push(FsckIssue::UnreadableFile {
+                    gist_id: gist_id.clone(),
+                    filename: filename.clone(),
+                    reason,
+                });
+            }
+        }
+
+        // Manifest entries whose file is already gone
+        match content_store.verify_gist(&gist_id) {
+            Ok(statuses) => {
+                for (filename, _) in statuses {
+                    if filenames.contains(&filename) {
+                        continue;
+                    }
+                    let gist_id = gist_id.clone();
+                    report
+                        .issues
+                        .push(if gist.files.iter().any(|f| f.filename == filename) {
+                            FsckIssue::MissingFile { gist_id, filename }
+                        } else {
+                            FsckIssue::StaleFile { gist_id, filename }
+                        });
+                }
+            }
+            // An unreadable file also fails verification; only blame the
+            // manifest when every file read fine
+            Err(e) if !unreadable => report.issues.push(FsckIssue::UnreadableManifest {
+                gist_id: gist_id.clone(),
+                reason: e.to_string(),
+            }),
+            Err(_) => {}
+        }
+    }
+
+    for path in content_store.temp_files()? {
+        report.issues.push(FsckIssue::TempFile { path });
+    }
+
+    Ok(report)
+}
+
+/// Fix the problems found by `check`
+///
+/// Orphaned gists and gists with an unreadable manifest are removed, stale
+/// and unreadable files are deleted (they are re-fetched on next use), the
+/// manifest entries of missing files are dropped and temp files are removed.
+///
+/// # Returns
+/// Number of issues repaired
+pub fn repair(content_store: &dyn ContentStore, issues: &[FsckIssue]) -> Result<usize> {
+    for issue in issues {
+        match issue {
+            FsckIssue::OrphanedGist { gist_id } | FsckIssue::UnreadableManifest { gist_id, .. } => {
+                content_store.delete_gist(gist_id)?;
+            }
+            FsckIssue::StaleFile { gist_id, filename }
+            | FsckIssue::MissingFile { gist_id, filename }
+            | FsckIssue::UnreadableFile {
+                gist_id, filename, ..
+            } => {
+                content_store.delete_file(gist_id, filename)?;
+            }
+            FsckIssue::TempFile { path } => {
+                // May already be gone with its Gist directory
+                if let Err(e) = fs::remove_file(path)
+                    && e.kind() != std::io::ErrorKind::NotFound
+                {
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+
+    Ok(issues.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::ContentCache;
+    use crate::cache::types::{CacheMetadata, GistFile, GistInfo};
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    fn test_metadata(gists: &[(&str, &[&str])]) -> GistCache {
+        GistCache {
+            metadata: CacheMetadata {
+                last_updated: Utc::now(),
+                total_count: gists.len(),
+                github_user: "testuser".to_string(),
+            },
+            gists: gists
+                .iter()
+                .map(|(id, filenames)| GistInfo {
+                    id: id.to_string(),
+                    description: None,
+                    files: filenames
+                        .iter()
+                        .map(|name| GistFile {
+                            filename: name.to_string(),
+                            language: None,
+                            size: 10,
+                            revision: None,
+                        })
+                        .collect(),
+                    updated_at: Utc::now(),
+                    public: true,
+                    html_url: format!("https://gist.github.com/{}", id),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_check_and_repair_json_layout() {
+        let temp_dir = TempDir::new().unwrap();
+        let contents_dir = temp_dir.path().join("contents");
+        let cache = ContentCache::new(contents_dir.clone());
+
+        cache.write("healthy", "run.sh", "echo ok").unwrap();
+        cache.write("orphan", "old.sh", "echo orphan").unwrap();
+        cache.write("renamed", "old.py", "print('old')").unwrap();
+        cache.write("renamed", "new.py", "print('new')").unwrap();
+        cache.write("binary", "data.txt", "placeholder").unwrap();
+        fs::write(contents_dir.join("binary").join("data.txt"), [0xff, 0xfe]).unwrap();
+        fs::write(
+            contents_dir.join("healthy").join(".run.sh.a1b2c3.partial"),
+            "partial",
+        )
+        .unwrap();
+        fs::write(
+            contents_dir.join(".healthy.manifest.json.d4e5f6.partial"),
+            "{",
+        )
+        .unwrap();
+        // A Gist file that only looks like a temp file
+        cache.write("healthy", "notes.tmp", "keep me").unwrap();
+
+        let metadata = test_metadata(&[
+            ("healthy", &["run.sh", "notes.tmp"]),
+            ("renamed", &["new.py"]),
+            ("binary", &["data.txt"]),
+        ]);
+
+        let report = check(&cache, &metadata).unwrap();
+        assert_eq!(report.gists_checked, 4);
+        assert_eq!(report.files_checked, 5);
+        assert!(report.issues.contains(&FsckIssue::OrphanedGist {
+            gist_id: "orphan".to_string()
+        }));
+        assert!(report.issues.contains(&FsckIssue::StaleFile {
+            gist_id: "renamed".to_string(),
+            filename: "old.py".to_string()
+        }));
+        assert!(report.issues.iter().any(|issue| matches!(
+            issue,
+            FsckIssue::UnreadableFile { gist_id, .. } if gist_id == "binary"
+        )));
+        let temp_files = report
+            .issues
+            .iter()
+            .filter(|issue| matches!(issue, FsckIssue::TempFile { .. }))
+            .count();
+        assert_eq!(temp_files, 2);
+        assert_eq!(report.issues.len(), 5);
+
+        assert_eq!(repair(&cache, &report.issues).unwrap(), 5);
+        assert!(check(&cache, &metadata).unwrap().issues.is_empty());
+        assert_eq!(cache.read("healthy", "run.sh").unwrap(), "echo ok");
+        assert_eq!(cache.read("healthy", "notes.tmp").unwrap(), "keep me");
+        assert_eq!(cache.read("renamed", "new.py").unwrap(), "print('new')");
+        assert!(!cache.exists("renamed", "old.py"));
+        assert!(!cache.exists("binary", "data.txt"));
+    }
+
+    #[test]
+    fn test_check_reports_stale_manifest_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = ContentCache::new(temp_dir.path().join("contents"));
+
+        cache.write("abc", "keep.sh", "echo keep").unwrap();
+        cache.write("abc", "gone.sh", "echo gone").unwrap();
+        cache.write("abc", "lost.sh", "echo lost").unwrap();
+        fs::remove_file(temp_dir.path().join("contents/abc/gone.sh")).unwrap();
+        fs::remove_file(temp_dir.path().join("contents/abc/lost.sh")).unwrap();
+
+        // gone.sh left the Gist, lost.sh is still one of its files
+        let metadata = test_metadata(&[("abc", &["keep.sh", "lost.sh"])]);
+        let report = check(&cache, &metadata).unwrap();
+        assert_eq!(
+            report.issues,
+            vec![
+                FsckIssue::StaleFile {
+                    gist_id: "abc".to_string(),
+                    filename: "gone.sh".to_string()
+                },
+                FsckIssue::MissingFile {
+                    gist_id: "abc".to_string(),
+                    filename: "lost.sh".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            report.issues[1].to_string(),
+            "abc/lost.sh: in the manifest but not cached"
+        );
+
+        repair(&cache, &report.issues).unwrap();
+        let manifest = cache.load_manifest("abc").unwrap();
+        assert!(!manifest.files.contains_key("gone.sh"));
+        assert!(!manifest.files.contains_key("lost.sh"));
+        assert!(cache.exists("abc", "keep.sh"));
+        assert!(check(&cache, &metadata).unwrap().issues.is_empty());
+    }
+}
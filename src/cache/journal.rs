@@ -0,0 +1,198 @@
+//! Append-only journal of cache changes (`cache log`)
+//!
+//! Every `update` (even one that changed nothing), `cache clean` and
+//! `cache clear` append one JSON line to `journal.jsonl` in the cache
+//! directory, so it is possible to tell later when a gist changed or was
+//! dropped from the cache. Once the file passes `MAX_JOURNAL_BYTES` it is
+//! rotated to `journal.jsonl.1` (see `cache::jsonl`).
+
+use crate::cache::jsonl::JsonLines;
+use crate::cache::report::{InvalidatedFile, UpdateMode, UpdateReport};
+use crate::config::Config;
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Size at which the journal file is rotated
+const MAX_JOURNAL_BYTES: u64 = 1024 * 1024;
+
+/// What happened to the cache
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum JournalEvent {
+    /// Metadata update (`update`, `run --force`, automatic refresh), with empty
+    /// lists when nothing changed
+    Update {
+        mode: UpdateMode,
+        added: Vec<String>,
+        modified: Vec<String>,
+        removed: Vec<String>,
+        invalidated: Vec<InvalidatedFile>,
+    },
+    /// Cached gists removed by `cache clean`
+    Clean {
+        criteria: Vec<String>,
+        removed: Vec<String>,
+        freed_bytes: u64,
+    },
+    /// Every cached gist removed by `cache clear`
+    Clear { removed: Vec<String> },
+}
+
+impl From<&UpdateReport> for JournalEvent {
+    fn from(report: &UpdateReport) -> Self {
+        let ids = |changes: &[crate::cache::report::GistChange]| {
+            changes.iter().map(|c| c.id.clone()).collect()
+        };
+        JournalEvent::Update {
+            mode: report.mode,
+            added: ids(&report.added),
+            modified: ids(&report.modified),
+            removed: ids(&report.removed),
+            invalidated: report.invalidated.clone(),
+        }
+    }
+}
+
+/// One line of the journal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: JournalEvent,
+}
+
+impl JournalEntry {
+    /// Whether this entry added, changed or removed `gist_id`
+    pub fn involves(&self, gist_id: &str) -> bool {
+        let contains = |ids: &[String]| ids.iter().any(|id| id == gist_id);
+        match &self.event {
+            JournalEvent::Update {
+                added,
+                modified,
+                removed,
+                invalidated,
+                ..
+            } => {
+                contains(added)
+                    || contains(modified)
+                    || contains(removed)
+                    || invalidated.iter().any(|f| f.gist_id == gist_id)
+            }
+            JournalEvent::Clean { removed, .. } | JournalEvent::Clear { removed } => {
+                contains(removed)
+            }
+        }
+    }
+}
+
+/// The journal file of a cache directory
+pub struct Journal {
+    file: JsonLines,
+}
+
+impl Journal {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            file: JsonLines::new(config.journal_file(), MAX_JOURNAL_BYTES),
+        }
+    }
+
+    /// Append an event stamped with the current time, rotating the file
+    /// first when it is full
+    pub fn append(&self, event: JournalEvent) -> Result<()> {
+        self.file.append(&JournalEntry {
+            timestamp: Utc::now(),
+            event,
+        })
+    }
+
+    /// All entries in the rotated and the current file, oldest first
+    pub fn entries(&self) -> Result<Vec<JournalEntry>> {
+        self.file.entries()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_journal_append_and_read() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+        let journal = Journal::new(&config);
+        assert!(journal.entries().unwrap().is_empty());
+
+        journal
+            .append(JournalEvent::Update {
+                mode: UpdateMode::Differential,
+                added: vec!["new1".to_string()],
+                modified: vec![],
+                removed: vec![],
+                invalidated: vec![InvalidatedFile {
+                    gist_id: "abc".to_string(),
+                    filename: "run.sh".to_string(),
+                }],
+            })
+            .unwrap();
+        journal
+            .append(JournalEvent::Clear {
+                removed: vec!["old".to_string()],
+            })
+            .unwrap();
+
+        // A torn line does not hide the others
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(config.journal_file())
+            .unwrap();
+        file.write_all(b"{\"timestamp\":").unwrap();
+
+        // ...and the next entry starts on a new line instead of being lost
+        journal
+            .append(JournalEvent::Clear {
+                removed: vec!["after-crash".to_string()],
+            })
+            .unwrap();
+
+        let entries = journal.entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert!(entries[2].involves("after-crash"));
+        assert!(entries[0].involves("abc"));
+        assert!(entries[0].involves("new1"));
+        assert!(!entries[0].involves("old"));
+        assert!(entries[1].involves("old"));
+
+        let line = fs::read_to_string(config.journal_file()).unwrap();
+        assert!(line.starts_with("{\"timestamp\":"));
+        assert!(line.contains("\"operation\":\"update\",\"mode\":\"differential\""));
+    }
+
+    #[test]
+    fn test_journal_rotates_by_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+        let journal = Journal {
+            file: JsonLines::new(config.journal_file(), 1),
+        };
+
+        // Every append finds a full file and rotates it first, so only the
+        // last two entries survive, still in order
+        for id in ["first", "second", "third"] {
+            journal
+                .append(JournalEvent::Clear {
+                    removed: vec![id.to_string()],
+                })
+                .unwrap();
+        }
+        assert!(config.journal_file().with_extension("jsonl.1").exists());
+        let entries = journal.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].involves("second"));
+        assert!(entries[1].involves("third"));
+    }
+}
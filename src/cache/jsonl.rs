@@ -0,0 +1,90 @@
+//! Append-only JSON Lines files rotated by size
+//!
+//! Shared by the cache journal (`journal.jsonl`) and the execution history
+//! (`history.jsonl`). Once a file passes its size limit it is renamed to
+//! `<name>.1`, replacing the previous rotated file, so at most two files'
+//! worth of entries are kept.
+
+use crate::error::Result;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// A JSON Lines file and its rotated predecessor
+pub struct JsonLines {
+    path: PathBuf,
+    max_bytes: u64,
+}
+
+impl JsonLines {
+    /// # Arguments
+    /// * `path` - The current file (e.g. `journal.jsonl`)
+    /// * `max_bytes` - Size at which it is rotated on the next append
+    pub fn new(path: PathBuf, max_bytes: u64) -> Self {
+        Self { path, max_bytes }
+    }
+
+    /// Where the previous file goes on rotation
+    fn rotated_path(&self) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(".1");
+        PathBuf::from(name)
+    }
+
+    /// Append `entry` as one line, rotating the file first when it is full
+    pub fn append<T: Serialize>(&self, entry: &T) -> Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        // Replaces the previous rotated file. Two processes rotating at once
+        // can lose the older one's entries, which is fine for a log.
+        if fs::metadata(&self.path).is_ok_and(|m| m.len() >= self.max_bytes) {
+            fs::rename(&self.path, self.rotated_path())?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+
+        // A line torn by a crash has no newline; start on a line of our own
+        // so this entry is not glued to it
+        if file.metadata()?.len() > 0 {
+            let mut last = [0u8];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                line.insert(0, '\n');
+            }
+        }
+
+        // One write per entry, so concurrent appends never interleave lines
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// All entries in the rotated and the current file, oldest first
+    pub fn entries<T: DeserializeOwned>(&self) -> Result<Vec<T>> {
+        let mut entries = read_entries(&self.rotated_path())?;
+        entries.extend(read_entries(&self.path)?);
+        Ok(entries)
+    }
+}
+
+/// Entries of one file (none if it doesn't exist)
+///
+/// # Self-healing Principle
+/// Lines that cannot be parsed (e.g. cut off by a crash) are skipped
+fn read_entries<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
@@ -0,0 +1,160 @@
+use crate::config::Config;
+use crate::error::{GistCacheError, Result};
+use fs4::fs_std::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Lock file name inside the cache directory
+const LOCK_FILE: &str = "cache.lock";
+
+/// Default time to wait for another process to release the lock
+pub const DEFAULT_LOCK_TIMEOUT_SECS: u64 = 30;
+
+/// Interval between lock attempts while waiting
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Advisory, cross-process lock over `cache.json` and `contents/`
+///
+/// Held for the duration of operations that rewrite the cache (update, clean,
+/// clear). Released when dropped; the OS also releases it if the process dies,
+/// so a crashed run never leaves the cache locked.
+pub struct CacheLock {
+    _file: File,
+    path: PathBuf,
+}
+
+impl CacheLock {
+    /// Acquire the lock using the timeout configured in `cache.lock_timeout`
+    ///
+    /// # Errors
+    /// Returns `CacheLocked` if another process still holds the lock when the
+    /// timeout expires (immediately when the timeout is 0)
+    pub fn acquire(config: &Config) -> Result<Self> {
+        let timeout = config
+            .user_config
+            .cache
+            .as_ref()
+            .and_then(|c| c.lock_timeout)
+            .unwrap_or(DEFAULT_LOCK_TIMEOUT_SECS);
+
+        Self::acquire_in(&config.cache_dir, Duration::from_secs(timeout))
+    }
+
+    /// Acquire the lock in `cache_dir`, waiting up to `timeout`
+    ///
+    /// # Arguments
+    /// * `cache_dir` - Cache directory containing the lock file
+    /// * `timeout` - Maximum wait; `Duration::ZERO` fails fast
+    pub fn acquire_in(cache_dir: &Path, timeout: Duration) -> Result<Self> {
+        Self::acquire_at(&cache_dir.join(LOCK_FILE), timeout)
+    }
+
+    /// Acquire a lock on the file at `path`, waiting up to `timeout`
+    ///
+    /// Same as `acquire_in` for a lock file other than `cache.lock` (e.g. the
+    /// content cache's manifest lock)
+    pub(crate) fn acquire_at(path: &Path, timeout: Duration) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let path = path.to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+
+        let start = Instant::now();
+        loop {
+            if file.try_lock_exclusive()? {
+                return Ok(Self { _file: file, path });
+            }
+
+            if start.elapsed() >= timeout {
+                return Err(GistCacheError::CacheLocked(path.display().to_string()));
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Acquire the lock in `cache_dir` only if it is free right now
+    ///
+    /// # Returns
+    /// `None` while it is held, including by another `CacheLock` in this
+    /// process
+    pub fn try_acquire_in(cache_dir: &Path) -> Option<Self> {
+        Self::acquire_in(cache_dir, Duration::ZERO).ok()
+    }
+
+    /// Path of the lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Run a best-effort side effect of a content store under the lock in
+/// `lock_dir`
+///
+/// # Returns
+/// `None` (and `f` is skipped) while the lock is held elsewhere. Without a
+/// `lock_dir` the caller coordinates locking itself and `f` always runs.
+pub(crate) fn unless_locked<T>(lock_dir: Option<&Path>, f: impl FnOnce() -> T) -> Option<T> {
+    let _lock = match lock_dir {
+        Some(dir) => Some(CacheLock::try_acquire_in(dir)?),
+        None => None,
+    };
+    Some(f())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lock_is_exclusive() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let held = CacheLock::acquire_in(temp_dir.path(), Duration::ZERO).unwrap();
+        assert!(held.path().exists());
+
+        // Second acquisition fails fast while the first is held
+        let result = CacheLock::acquire_in(temp_dir.path(), Duration::ZERO);
+        assert!(matches!(result, Err(GistCacheError::CacheLocked(_))));
+
+        // Dropping releases the lock
+        drop(held);
+        assert!(CacheLock::acquire_in(temp_dir.path(), Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn test_unless_locked() {
+        let temp_dir = TempDir::new().unwrap();
+
+        assert_eq!(unless_locked(Some(temp_dir.path()), || 1), Some(1));
+
+        let held = CacheLock::try_acquire_in(temp_dir.path()).unwrap();
+        assert!(CacheLock::try_acquire_in(temp_dir.path()).is_none());
+        assert_eq!(unless_locked(Some(temp_dir.path()), || 1), None);
+        // Without a lock directory the caller is responsible for locking
+        assert_eq!(unless_locked(None, || 1), Some(1));
+        drop(held);
+    }
+
+    #[test]
+    fn test_lock_waits_for_release() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().to_path_buf();
+
+        let held = CacheLock::acquire_in(&dir, Duration::ZERO).unwrap();
+        let waiter =
+            std::thread::spawn(move || CacheLock::acquire_in(&dir, Duration::from_secs(5)));
+
+        std::thread::sleep(Duration::from_millis(300));
+        drop(held);
+
+        assert!(waiter.join().unwrap().is_ok());
+    }
+}
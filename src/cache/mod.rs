@@ -1,7 +1,28 @@
+pub mod bundle;
 pub mod content;
+pub mod fsck;
+pub mod journal;
+pub mod jsonl;
+pub mod lock;
+pub mod report;
+pub mod storage;
+pub mod tags;
 pub mod types;
 pub mod update;
+pub mod watch;
 
-pub use content::{CleanOptions, CleanResult, ContentCache};
+pub use bundle::{ExportSummary, ImportSummary, export_bundle, import_bundle};
+pub use content::{
+    CleanOptions, CleanResult, ContentCache, ContentManifest, IntegrityStatus, ManifestEntry,
+};
+pub use fsck::{FsckIssue, FsckReport};
+pub use journal::{Journal, JournalEntry, JournalEvent};
+pub use lock::CacheLock;
+pub use report::{GistChange, InvalidatedFile, UpdateMode, UpdateReport};
+pub use storage::{
+    ContentStore, MetadataStore, SqliteStore, UsageStats, open_content_store, open_metadata_store,
+};
+pub use tags::GistTags;
 pub use types::{GistCache, GistFile, GistInfo};
 pub use update::CacheUpdater;
+pub use watch::{WatchCycle, WatchOptions, WatchState};
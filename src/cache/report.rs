@@ -0,0 +1,160 @@
+//! What an `update` changed, returned by `CacheUpdater::update`
+
+use crate::cache::types::GistInfo;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Whether the update fetched every gist or only those changed since the last one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateMode {
+    Full,
+    Differential,
+}
+
+/// A gist that was added, modified or removed by an update
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GistChange {
+    pub id: String,
+    pub description: Option<String>,
+    pub files: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+    pub html_url: String,
+}
+
+impl From<&GistInfo> for GistChange {
+    fn from(gist: &GistInfo) -> Self {
+        GistChange {
+            id: gist.id.clone(),
+            description: gist.description.clone(),
+            files: gist.files.iter().map(|f| f.filename.clone()).collect(),
+            updated_at: gist.updated_at,
+            html_url: gist.html_url.clone(),
+        }
+    }
+}
+
+/// A cached file deleted because its gist file changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvalidatedFile {
+    pub gist_id: String,
+    pub filename: String,
+}
+
+/// Result of `CacheUpdater::update`
+///
+/// `removed` can only be filled by full updates: a differential update asks
+/// GitHub for gists changed since the last update, which never includes
+/// deleted ones.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateReport {
+    pub mode: UpdateMode,
+    pub github_user: String,
+    /// Last update the differential fetch started from
+    pub since: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+    /// Gists returned by the GitHub API
+    pub fetched: usize,
+    pub added: Vec<GistChange>,
+    pub modified: Vec<GistChange>,
+    pub removed: Vec<GistChange>,
+    pub invalidated: Vec<InvalidatedFile>,
+    /// Core API requests remaining before the update
+    pub rate_limit_remaining: i64,
+    pub total_gists: usize,
+}
+
+impl UpdateReport {
+    /// Whether anything was added, modified or removed
+    pub fn has_changes(&self) -> bool {
+        !(self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty())
+    }
+}
+
+impl fmt::Display for UpdateReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sections = [
+            ("Added", "+", &self.added),
+            ("Modified", "~", &self.modified),
+            ("Removed", "-", &self.removed),
+        ];
+        for (title, marker, changes) in sections {
+            if changes.is_empty() {
+                continue;
+            }
+            writeln!(f, "{} ({}):", title, changes.len())?;
+            for change in changes {
+                writeln!(
+                    f,
+                    "  {} {} {}",
+                    marker,
+                    change.id,
+                    change.description.as_deref().unwrap_or("No description")
+                )?;
+            }
+        }
+        if !self.invalidated.is_empty() {
+            writeln!(f, "Invalidated cache files ({}):", self.invalidated.len())?;
+            for file in &self.invalidated {
+                writeln!(f, "  {}/{}", file.gist_id, file.filename)?;
+            }
+        }
+        if !self.has_changes() {
+            writeln!(f, "No changes")?;
+        }
+        write!(f, "Rate limit remaining: {}", self.rate_limit_remaining)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(id: &str) -> GistChange {
+        GistChange {
+            id: id.to_string(),
+            description: Some(format!("Gist {}", id)),
+            files: vec!["run.sh".to_string()],
+            updated_at: Utc::now(),
+            html_url: format!("https://gist.github.com/{}", id),
+        }
+    }
+
+    #[test]
+    fn test_update_report_text_and_json() {
+        let mut report = UpdateReport {
+            mode: UpdateMode::Differential,
+            github_user: "testuser".to_string(),
+            since: None,
+            updated_at: Utc::now(),
+            fetched: 2,
+            added: vec![change("new1")],
+            modified: vec![change("mod1")],
+            removed: vec![],
+            invalidated: vec![InvalidatedFile {
+                gist_id: "mod1".to_string(),
+                filename: "run.sh".to_string(),
+            }],
+            rate_limit_remaining: 4990,
+            total_gists: 10,
+        };
+
+        let text = report.to_string();
+        assert!(text.contains("Added (1):\n  + new1 Gist new1"));
+        assert!(text.contains("Modified (1):\n  ~ mod1 Gist mod1"));
+        assert!(!text.contains("Removed"));
+        assert!(text.contains("Invalidated cache files (1):\n  mod1/run.sh"));
+        assert!(text.ends_with("Rate limit remaining: 4990"));
+
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["mode"], "differential");
+        assert_eq!(json["added"][0]["id"], "new1");
+        assert_eq!(json["invalidated"][0]["filename"], "run.sh");
+
+        report.added.clear();
+        report.modified.clear();
+        assert!(!report.has_changes());
+        assert!(report.to_string().starts_with("Invalidated"));
+    }
+}
@@ -0,0 +1,1375 @@
+//! Storage backends for the metadata cache and cached file contents
+//!
+//! `MetadataStore` sits behind `GistCache` load/save and `ContentStore`
+//! behind `ContentCache`, so callers work the same way whether the cache
+//! lives in `cache.json` + `contents/` (the default) or in a single SQLite
+//! database (`cache.backend = "sqlite"`).
+
+use crate::cache::content::{
+    CleanOptions, CleanResult, ContentCache, IntegrityStatus, sha256_hex, should_clean_gist,
+};
+use crate::cache::lock::unless_locked;
+use crate::cache::tags::{GistTags, hashtags};
+use crate::cache::types::{CacheMetadata, GistCache, GistInfo};
+use crate::config::{Config, StorageBackend};
+use crate::error::{GistCacheError, Result};
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Schema version stored in `PRAGMA user_version`
+const SCHEMA_VERSION: i32 = 3;
+
+/// How long a SQLite connection waits for another writer before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often a gist has been run, and when it was last run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageStats {
+    pub run_count: u64,
+    pub last_used: DateTime<Utc>,
+}
+
+/// Storage for the gist metadata cache, per-gist usage counters and local
+/// tags
+pub trait MetadataStore {
+    /// Whether a metadata cache has been saved yet
+    fn cache_exists(&self) -> bool;
+
+    /// Load the whole metadata cache
+    ///
+    /// # Errors
+    /// Returns `CacheNotFound` if nothing has been saved yet
+    fn load(&self) -> Result<GistCache>;
+
+    /// Replace the whole metadata cache (atomically)
+    fn save(&self, cache: &GistCache) -> Result<()>;
+
+    /// Cache-wide metadata (owner, last update) without the gist list
+    fn metadata(&self) -> Result<CacheMetadata> {
+        Ok(self.load()?.metadata)
+    }
+
+    /// Look up a single gist by its exact ID
+    fn find_by_id(&self, gist_id: &str) -> Result<Option<GistInfo>> {
+        Ok(self.load()?.gists.into_iter().find(|g| g.id == gist_id))
+    }
+
+    /// Gists having a file whose name contains `query` (case-insensitive),
+    /// most recently updated first
+    fn find_by_filename(&self, query: &str) -> Result<Vec<GistInfo>> {
+        let query = query.to_lowercase();
+        let mut gists: Vec<GistInfo> = self
+            .load()?
+            .gists
+            .into_iter()
+            .filter(|g| {
+                g.files
+                    .iter()
+                    .any(|f| f.filename.to_lowercase().contains(&query))
+            })
+            .collect();
+        gists.sort_by_key(|g| std::cmp::Reverse(g.updated_at));
+        Ok(gists)
+    }
+
+    /// Count one run of a gist
+    fn record_usage(&self, gist_id: &str) -> Result<()>;
+
+    /// Usage counters keyed by gist ID (gists never run are absent)
+    fn usage(&self) -> Result<HashMap<String, UsageStats>>;
+
+    /// Local tags keyed by gist ID (gists without tags are absent)
+    fn tags(&self) -> Result<HashMap<String, GistTags>>;
+
+    /// Replace all local tags
+    fn save_tags(&self, tags: &HashMap<String, GistTags>) -> Result<()>;
+
+    /// Replace the local tags of one gist (dropping them when empty)
+    fn set_tags(&self, gist_id: &str, gist_tags: &GistTags) -> Result<()> {
+        let mut tags = self.tags()?;
+        if gist_tags.is_empty() {
+            tags.remove(gist_id);
+        } else {
+            tags.insert(gist_id.to_string(), gist_tags.clone());
+        }
+        self.save_tags(&tags)
+    }
+
+    /// Replace every gist's description tags with the `#hashtags` now in
+    /// its description; writes nothing when none changed
+    fn sync_description_tags(&self, gists: &[GistInfo]) -> Result<()> {
+        let mut tags = self.tags()?;
+        let mut changed = false;
+        for gist in gists {
+            let found = hashtags(gist.description.as_deref().unwrap_or_default());
+            let unchanged = match tags.get(&gist.id) {
+                Some(current) => current.description_tags == found,
+                None => found.is_empty(),
+            };
+            if !unchanged {
+                tags.entry(gist.id.clone()).or_default().description_tags = found;
+                changed = true;
+            }
+        }
+        if !changed {
+            return Ok(());
+        }
+        tags.retain(|_, t| !t.is_empty());
+        self.save_tags(&tags)
+    }
+}
+
+/// Storage for cached file contents
+pub trait ContentStore {
+    /// Check if a file is cached
+    fn exists(&self, gist_id: &str, filename: &str) -> bool;
+
+    /// Read a cached file, recording the access for LRU eviction
+    fn read(&self, gist_id: &str, filename: &str) -> Result<String>;
+
+    /// Read a cached file after checking it against its recorded hash
+    ///
+    /// # Errors
+    /// Returns `IntegrityCheckFailed` if the content no longer matches or
+    /// no hash was recorded for it
+    fn read_verified(&self, gist_id: &str, filename: &str) -> Result<String>;
+
+    /// Record the hash of a file cached before hashes were recorded, so
+    /// `read_verified` accepts it (callers hold the `CacheLock`)
+    ///
+    /// # Returns
+    /// `Ok(true)` if a hash was recorded; backends that always record one
+    /// have nothing to do
+    fn track(&self, _gist_id: &str, _filename: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Cache a file, replacing any previous content
+    fn write(&self, gist_id: &str, filename: &str, content: &str) -> Result<()>;
+
+    /// Drop one cached file of a gist
+    ///
+    /// # Returns
+    /// `Ok(true)` if it was deleted, `Ok(false)` if it wasn't cached
+    fn delete_file(&self, gist_id: &str, filename: &str) -> Result<bool>;
+
+    /// Drop every cached file of a gist
+    ///
+    /// # Returns
+    /// `Ok(true)` if something was deleted, `Ok(false)` if nothing was cached
+    fn delete_gist(&self, gist_id: &str) -> Result<bool>;
+
+    /// IDs of all gists with at least one cached file
+    fn list_cached_gists(&self) -> Result<Vec<String>>;
+
+    /// Names of the cached files of a gist, sorted
+    fn list_files(&self, gist_id: &str) -> Result<Vec<String>>;
+
+    /// Bytes used by the cached contents of one gist
+    fn gist_size(&self, gist_id: &str) -> Result<u64>;
+
+    /// Bytes used by all cached contents
+    fn total_size(&self) -> Result<u64>;
+
+    /// Drop every cached file
+    fn clear_all(&self) -> Result<()>;
+
+    /// Check every cached file of a gist against its recorded hash
+    fn verify_gist(&self, gist_id: &str) -> Result<Vec<(String, IntegrityStatus)>>;
+
+    /// Where the contents live (shown by `cache size`)
+    fn location(&self) -> &Path;
+
+    /// When the gist was last run or a file of it written
+    fn last_accessed(&self, gist_id: &str) -> Result<Option<DateTime<Utc>>>;
+
+    /// Record that a cached gist was just used, for LRU eviction (callers
+    /// hold the `CacheLock`)
+    fn record_access(&self, gist_id: &str) -> Result<()>;
+
+    /// Why a cached file cannot be read as UTF-8 text; `None` when it reads
+    /// fine
+    fn check_readable(&self, gist_id: &str, filename: &str) -> Option<String>;
+
+    /// Temp files left behind by interrupted writes
+    fn temp_files(&self) -> Result<Vec<PathBuf>> {
+        Ok(Vec::new())
+    }
+
+    /// Remove least recently used gists until the cached contents fit in
+    /// `max_size` bytes
+    ///
+    /// `keep` is never evicted (the gist that was just written) and gists in
+    /// `removed` are treated as already gone (e.g. picked by a dry-run clean).
+    /// Gists without a recorded access time go first.
+    fn evict_lru(
+        &self,
+        max_size: u64,
+        keep: Option<&str>,
+        removed: &[String],
+        dry_run: bool,
+    ) -> Result<CleanResult> {
+        let mut entries = Vec::new();
+        let mut total = 0;
+        for gist_id in self.list_cached_gists()? {
+            if removed.contains(&gist_id) {
+                continue;
+            }
+            let size = self.gist_size(&gist_id)?;
+            total += size;
+            if keep != Some(gist_id.as_str()) {
+                entries.push((self.last_accessed(&gist_id)?, size, gist_id));
+            }
+        }
+        entries.sort();
+
+        let mut result = CleanResult::default();
+        for (_, size, gist_id) in entries {
+            if total <= max_size {
+                break;
+            }
+            if !dry_run {
+                self.delete_gist(&gist_id)?;
+            }
+            total -= size;
+            result.deleted_size += size;
+            result.deleted_gists.push(gist_id);
+        }
+
+        Ok(result)
+    }
+
+    /// Remove cached gists matching the clean options
+    fn clean(&self, metadata_cache: &GistCache, options: &CleanOptions) -> Result<CleanResult> {
+        let valid_gist_ids: HashSet<String> =
+            metadata_cache.gists.iter().map(|g| g.id.clone()).collect();
+        let mut result = CleanResult::default();
+
+        for gist_id in self.list_cached_gists()? {
+            if should_clean_gist(&gist_id, &valid_gist_ids, metadata_cache, options) {
+                result.deleted_size += self.gist_size(&gist_id)?;
+                if !options.dry_run {
+                    self.delete_gist(&gist_id)?;
+                }
+                result.deleted_gists.push(gist_id);
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Open the metadata store selected by `cache.backend`
+pub fn open_metadata_store(config: &Config) -> Result<Box<dyn MetadataStore>> {
+    Ok(match config.storage_backend() {
+        StorageBackend::Json => Box::new(JsonMetadataStore::new(config)),
+        StorageBackend::Sqlite => Box::new(SqliteStore::open(&config.cache_db_file())?),
+    })
+}
+
+/// Open the content store selected by `cache.backend`
+///
+/// Access times and LRU eviction are coordinated with the `CacheLock`, so
+/// they are skipped while the lock is held (including by the caller).
+pub fn open_content_store(config: &Config) -> Result<Box<dyn ContentStore>> {
+    Ok(match config.storage_backend() {
+        StorageBackend::Json => Box::new(
+            ContentCache::new(config.contents_dir.clone())
+                .with_max_size(config.cache_max_size())
+                .with_lock_dir(config.cache_dir.clone()),
+        ),
+        StorageBackend::Sqlite => Box::new(
+            SqliteStore::open(&config.cache_db_file())?
+                .with_max_size(config.cache_max_size())
+                .with_lock_dir(config.cache_dir.clone()),
+        ),
+    })
+}
+
+/// Suffix of the temp files written by `write_atomic`
+const TEMP_SUFFIX: &str = ".partial";
+
+/// Whether `name` is a temp file left by `write_atomic`
+/// (`.<name>.<random>.partial`), never a cached Gist file such as `notes.tmp`
+pub(crate) fn is_temp_file(name: &str) -> bool {
+    name.starts_with('.') && name.ends_with(TEMP_SUFFIX)
+}
+
+/// Write `data` to `path` via a temp file + rename so readers never see a
+/// truncated file
+///
+/// The temp file (`.<name>.<random>.partial` next to `path`) is unique per
+/// call, so concurrent writers never write into each other's temp file.
+pub(crate) fn write_atomic(path: &Path, data: &str) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut temp = tempfile::Builder::new()
+        .prefix(&format!(".{}.", name))
+        .suffix(TEMP_SUFFIX)
+        .tempfile_in(dir)?;
+    temp.write_all(data.as_bytes())?;
+    // The temp file is removed if the rename fails
+    temp.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// The original layout: pretty-printed `cache.json` plus `usage.json` and
+/// `tags.json`
+pub struct JsonMetadataStore {
+    cache_file: PathBuf,
+    usage_file: PathBuf,
+    tags_file: PathBuf,
+}
+
+impl JsonMetadataStore {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            cache_file: config.cache_file.clone(),
+            usage_file: config.cache_dir.join("usage.json"),
+            tags_file: config.cache_dir.join("tags.json"),
+        }
+    }
+}
+
+impl MetadataStore for JsonMetadataStore {
+    fn cache_exists(&self) -> bool {
+        self.cache_file.exists()
+    }
+
+    fn load(&self) -> Result<GistCache> {
+        if !self.cache_file.exists() {
+            return Err(GistCacheError::CacheNotFound);
+        }
+        let content = fs::read_to_string(&self.cache_file)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    fn save(&self, cache: &GistCache) -> Result<()> {
+        write_atomic(&self.cache_file, &serde_json::to_string_pretty(cache)?)
+    }
+
+    fn record_usage(&self, gist_id: &str) -> Result<()> {
+        let mut usage = self.usage()?;
+        let entry = usage.entry(gist_id.to_string()).or_insert(UsageStats {
+            run_count: 0,
+            last_used: Utc::now(),
+        });
+        entry.run_count += 1;
+        entry.last_used = Utc::now();
+
+        write_atomic(&self.usage_file, &serde_json::to_string_pretty(&usage)?)
+    }
+
+    fn usage(&self) -> Result<HashMap<String, UsageStats>> {
+        if !self.usage_file.exists() {
+            return Ok(HashMap::new());
+        }
+        let content = fs::read_to_string(&self.usage_file)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    fn tags(&self) -> Result<HashMap<String, GistTags>> {
+        if !self.tags_file.exists() {
+            return Ok(HashMap::new());
+        }
+        let content = fs::read_to_string(&self.tags_file)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    fn save_tags(&self, tags: &HashMap<String, GistTags>) -> Result<()> {
+        // Sorted by ID, so the file diffs cleanly
+        let sorted: std::collections::BTreeMap<_, _> = tags.iter().collect();
+        write_atomic(&self.tags_file, &serde_json::to_string_pretty(&sorted)?)
+    }
+}
+
+impl ContentStore for ContentCache {
+    fn exists(&self, gist_id: &str, filename: &str) -> bool {
+        ContentCache::exists(self, gist_id, filename)
+    }
+
+    fn read(&self, gist_id: &str, filename: &str) -> Result<String> {
+        ContentCache::read(self, gist_id, filename)
+    }
+
+    fn read_verified(&self, gist_id: &str, filename: &str) -> Result<String> {
+        ContentCache::read_verified(self, gist_id, filename)
+    }
+
+    fn track(&self, gist_id: &str, filename: &str) -> Result<bool> {
+        ContentCache::track(self, gist_id, filename)
+    }
+
+    fn write(&self, gist_id: &str, filename: &str, content: &str) -> Result<()> {
+        ContentCache::write(self, gist_id, filename, content)
+    }
+
+    fn delete_file(&self, gist_id: &str, filename: &str) -> Result<bool> {
+        ContentCache::delete_file(self, gist_id, filename)
+    }
+
+    fn delete_gist(&self, gist_id: &str) -> Result<bool> {
+        ContentCache::delete_gist(self, gist_id)
+    }
+
+    fn list_cached_gists(&self) -> Result<Vec<String>> {
+        ContentCache::list_cached_gists(self)
+    }
+
+    fn list_files(&self, gist_id: &str) -> Result<Vec<String>> {
+        ContentCache::list_files(self, gist_id)
+    }
+
+    fn gist_size(&self, gist_id: &str) -> Result<u64> {
+        self.list_files(gist_id)?
+            .iter()
+            .map(|name| Ok(fs::metadata(self.cache_dir().join(gist_id).join(name))?.len()))
+            .sum()
+    }
+
+    fn total_size(&self) -> Result<u64> {
+        ContentCache::total_size(self)
+    }
+
+    fn clear_all(&self) -> Result<()> {
+        ContentCache::clear_all(self)
+    }
+
+    fn verify_gist(&self, gist_id: &str) -> Result<Vec<(String, IntegrityStatus)>> {
+        ContentCache::verify_gist(self, gist_id)
+    }
+
+    fn location(&self) -> &Path {
+        self.cache_dir()
+    }
+
+    fn last_accessed(&self, gist_id: &str) -> Result<Option<DateTime<Utc>>> {
+        Ok(ContentCache::last_accessed(self, gist_id))
+    }
+
+    fn record_access(&self, gist_id: &str) -> Result<()> {
+        ContentCache::record_access(self, gist_id)
+    }
+
+    fn check_readable(&self, gist_id: &str, filename: &str) -> Option<String> {
+        ContentCache::check_readable(self, gist_id, filename)
+    }
+
+    fn temp_files(&self) -> Result<Vec<PathBuf>> {
+        ContentCache::temp_files(self)
+    }
+
+    fn clean(&self, metadata_cache: &GistCache, options: &CleanOptions) -> Result<CleanResult> {
+        ContentCache::clean(self, metadata_cache, options)
+    }
+}
+
+/// Metadata, contents and usage counters in one SQLite database
+///
+/// Gists are indexed by ID and by (lower-cased) file name, so `run` can
+/// resolve a gist without loading the whole cache, and `save` replaces the
+/// metadata in a single transaction.
+pub struct SqliteStore {
+    conn: Connection,
+    path: PathBuf,
+    /// Size limit enforced after each content write (`cache.max_size`)
+    max_size: Option<u64>,
+    /// Directory holding the `CacheLock` (see `with_lock_dir`)
+    lock_dir: Option<PathBuf>,
+}
+
+impl SqliteStore {
+    /// Open (creating if needed) the database at `path`
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+
+        let version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(GistCacheError::CacheDirectoryError(format!(
+                "{} was created by a newer gist-cache-rs (schema {})",
+                path.display(),
+                version
+            )));
+        }
+
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS cache_metadata (
+                 id INTEGER PRIMARY KEY CHECK (id = 1),
+                 data TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS gists (
+                 id TEXT PRIMARY KEY,
+                 position INTEGER NOT NULL,
+                 data TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS gist_files (
+                 gist_id TEXT NOT NULL,
+                 filename TEXT NOT NULL,
+                 filename_lower TEXT NOT NULL,
+                 PRIMARY KEY (gist_id, filename)
+             );
+             CREATE INDEX IF NOT EXISTS idx_gist_files_filename
+                 ON gist_files (filename_lower);
+             CREATE TABLE IF NOT EXISTS contents (
+                 gist_id TEXT NOT NULL,
+                 filename TEXT NOT NULL,
+                 content TEXT NOT NULL,
+                 sha256 TEXT NOT NULL,
+                 cached_at TEXT NOT NULL,
+                 PRIMARY KEY (gist_id, filename)
+             );
+             CREATE TABLE IF NOT EXISTS content_access (
+                 gist_id TEXT PRIMARY KEY,
+                 last_accessed TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS usage (
+                 gist_id TEXT PRIMARY KEY,
+                 run_count INTEGER NOT NULL,
+                 last_used TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS tags (
+                 gist_id TEXT PRIMARY KEY,
+                 data TEXT NOT NULL
+             );",
+        )?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+
+        Ok(Self {
+            conn,
+            path: path.to_path_buf(),
+            max_size: None,
+            lock_dir: None,
+        })
+    }
+
+    /// Evict least recently used gists after each content write so the
+    /// contents stay within `max_size` bytes (`None` = unlimited)
+    pub fn with_max_size(mut self, max_size: Option<u64>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Only record access times on `read` and evict on `write` while the
+    /// `CacheLock` in `lock_dir` is free, skipping them otherwise
+    pub fn with_lock_dir(mut self, lock_dir: PathBuf) -> Self {
+        self.lock_dir = Some(lock_dir);
+        self
+    }
+
+    fn touch(&self, gist_id: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO content_access (gist_id, last_accessed) VALUES (?1, ?2)
+             ON CONFLICT (gist_id) DO UPDATE SET last_accessed = excluded.last_accessed",
+            params![gist_id, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Gists matching an SQL filter, in their saved order
+    fn query_gists<P: rusqlite::Params>(&self, filter: &str, params: P) -> Result<Vec<GistInfo>> {
+        let sql = format!("SELECT data FROM gists WHERE {} ORDER BY position", filter);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params, |row| row.get::<_, String>(0))?;
+
+        let mut gists = Vec::new();
+        for data in rows {
+            gists.push(serde_json::from_str(&data?)?);
+        }
+        Ok(gists)
+    }
+
+    /// Check cached content against the hash recorded when it was written
+    fn verify_file(&self, gist_id: &str, filename: &str, content: &str) -> Result<IntegrityStatus> {
+        let expected: String = self.conn.query_row(
+            "SELECT sha256 FROM contents WHERE gist_id = ?1 AND filename = ?2",
+            params![gist_id, filename],
+            |row| row.get(0),
+        )?;
+        let actual = sha256_hex(content.as_bytes());
+
+        Ok(if actual == expected {
+            IntegrityStatus::Valid
+        } else {
+            IntegrityStatus::Mismatch { expected, actual }
+        })
+    }
+
+    /// Copy the JSON layout (`cache.json`, `contents/`, `usage.json`,
+    /// `tags.json`) into this database, replacing whatever it held before
+    pub fn import_json_layout(&mut self, config: &Config) -> Result<MigrationSummary> {
+        let metadata = JsonMetadataStore::new(config);
+        let cache = metadata.load()?;
+        let usage = metadata.usage()?;
+        let tags = metadata.tags()?;
+        let contents = ContentCache::new(config.contents_dir.clone());
+
+        let tx = self.conn.transaction()?;
+        tx.execute_batch("DELETE FROM contents; DELETE FROM usage;")?;
+        save_metadata(&tx, &cache)?;
+        save_tags(&tx, &tags)?;
+
+        let mut files = 0;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO contents (gist_id, filename, content, sha256, cached_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            let now = Utc::now().to_rfc3339();
+            for gist_id in contents.list_cached_gists()? {
+                for filename in contents.list_files(&gist_id)? {
+                    // Self-healing principle: skip files that no longer match
+                    // their manifest, they will be re-fetched on next use
+                    let Ok(content) = contents.read_verified(&gist_id, &filename) else {
+                        continue;
+                    };
+                    insert.execute(params![
+                        gist_id,
+                        filename,
+                        content,
+                        sha256_hex(content.as_bytes()),
+                        now
+                    ])?;
+                    files += 1;
+                }
+            }
+
+            let mut insert = tx
+                .prepare("INSERT INTO usage (gist_id, run_count, last_used) VALUES (?1, ?2, ?3)")?;
+            for (gist_id, stats) in &usage {
+                insert.execute(params![
+                    gist_id,
+                    stats.run_count as i64,
+                    stats.last_used.to_rfc3339()
+                ])?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(MigrationSummary {
+            gists: cache.gists.len(),
+            files,
+            usage_entries: usage.len(),
+        })
+    }
+}
+
+/// Replace the tags table inside an open transaction
+fn save_tags(tx: &rusqlite::Transaction, tags: &HashMap<String, GistTags>) -> Result<()> {
+    tx.execute("DELETE FROM tags", [])?;
+    let mut insert = tx.prepare("INSERT INTO tags (gist_id, data) VALUES (?1, ?2)")?;
+    for (gist_id, gist_tags) in tags {
+        insert.execute(params![gist_id, serde_json::to_string(gist_tags)?])?;
+    }
+    Ok(())
+}
+
+/// Replace the metadata tables inside an open transaction
+fn save_metadata(tx: &rusqlite::Transaction, cache: &GistCache) -> Result<()> {
+    tx.execute_batch("DELETE FROM gist_files; DELETE FROM gists;")?;
+    tx.execute(
+        "INSERT OR REPLACE INTO cache_metadata (id, data) VALUES (1, ?1)",
+        [serde_json::to_string(&cache.metadata)?],
+    )?;
+
+    let mut insert_gist =
+        tx.prepare("INSERT INTO gists (id, position, data) VALUES (?1, ?2, ?3)")?;
+    let mut insert_file = tx.prepare(
+        "INSERT OR IGNORE INTO gist_files (gist_id, filename, filename_lower) VALUES (?1, ?2, ?3)",
+    )?;
+    for (position, gist) in cache.gists.iter().enumerate() {
+        insert_gist.execute(params![
+            gist.id,
+            position as i64,
+            serde_json::to_string(gist)?
+        ])?;
+        for file in &gist.files {
+            insert_file.execute(params![
+                gist.id,
+                file.filename,
+                file.filename.to_lowercase()
+            ])?;
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| GistCacheError::CacheReadError(format!("Invalid timestamp {}: {}", value, e)))
+}
+
+impl MetadataStore for SqliteStore {
+    fn cache_exists(&self) -> bool {
+        self.conn
+            .query_row("SELECT 1 FROM cache_metadata WHERE id = 1", [], |_| Ok(()))
+            .optional()
+            .is_ok_and(|row| row.is_some())
+    }
+
+    fn load(&self) -> Result<GistCache> {
+        Ok(GistCache {
+            metadata: self.metadata()?,
+            gists: self.query_gists("1", [])?,
+        })
+    }
+
+    fn metadata(&self) -> Result<CacheMetadata> {
+        let metadata: Option<String> = self
+            .conn
+            .query_row("SELECT data FROM cache_metadata WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        let Some(metadata) = metadata else {
+            return Err(GistCacheError::CacheNotFound);
+        };
+
+        Ok(serde_json::from_str(&metadata)?)
+    }
+
+    fn save(&self, cache: &GistCache) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        save_metadata(&tx, cache)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn find_by_id(&self, gist_id: &str) -> Result<Option<GistInfo>> {
+        Ok(self.query_gists("id = ?1", [gist_id])?.into_iter().next())
+    }
+
+    fn find_by_filename(&self, query: &str) -> Result<Vec<GistInfo>> {
+        let mut gists = self.query_gists(
+            "id IN (SELECT gist_id FROM gist_files WHERE instr(filename_lower, ?1) > 0)",
+            [query.to_lowercase()],
+        )?;
+        gists.sort_by_key(|g| std::cmp::Reverse(g.updated_at));
+        Ok(gists)
+    }
+
+    fn record_usage(&self, gist_id: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO usage (gist_id, run_count, last_used) VALUES (?1, 1, ?2)
+             ON CONFLICT (gist_id) DO UPDATE
+             SET run_count = run_count + 1, last_used = excluded.last_used",
+            params![gist_id, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    fn usage(&self) -> Result<HashMap<String, UsageStats>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT gist_id, run_count, last_used FROM usage")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+
+        let mut usage = HashMap::new();
+        for row in rows {
+            let (gist_id, run_count, last_used) = row?;
+            usage.insert(
+                gist_id,
+                UsageStats {
+                    run_count: run_count as u64,
+                    last_used: parse_timestamp(&last_used)?,
+                },
+            );
+        }
+        Ok(usage)
+    }
+
+    fn tags(&self) -> Result<HashMap<String, GistTags>> {
+        let mut stmt = self.conn.prepare("SELECT gist_id, data FROM tags")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut tags = HashMap::new();
+        for row in rows {
+            let (gist_id, data) = row?;
+            tags.insert(gist_id, serde_json::from_str(&data)?);
+        }
+        Ok(tags)
+    }
+
+    fn save_tags(&self, tags: &HashMap<String, GistTags>) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        save_tags(&tx, tags)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_tags(&self, gist_id: &str, gist_tags: &GistTags) -> Result<()> {
+        if gist_tags.is_empty() {
+            self.conn
+                .execute("DELETE FROM tags WHERE gist_id = ?1", [gist_id])?;
+        } else {
+            self.conn.execute(
+                "INSERT INTO tags (gist_id, data) VALUES (?1, ?2)
+                 ON CONFLICT (gist_id) DO UPDATE SET data = excluded.data",
+                params![gist_id, serde_json::to_string(gist_tags)?],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl ContentStore for SqliteStore {
+    fn exists(&self, gist_id: &str, filename: &str) -> bool {
+        self.conn
+            .query_row(
+                "SELECT 1 FROM contents WHERE gist_id = ?1 AND filename = ?2",
+                params![gist_id, filename],
+                |_| Ok(()),
+            )
+            .optional()
+            .is_ok_and(|row| row.is_some())
+    }
+
+    fn read(&self, gist_id: &str, filename: &str) -> Result<String> {
+        // Access time is best-effort, same as for the JSON layout
+        let _ = unless_locked(self.lock_dir.as_deref(), || self.record_access(gist_id));
+
+        self.conn
+            .query_row(
+                "SELECT content FROM contents WHERE gist_id = ?1 AND filename = ?2",
+                params![gist_id, filename],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| {
+                GistCacheError::CacheReadError(format!("{}/{} is not cached", gist_id, filename))
+            })
+    }
+
+    fn read_verified(&self, gist_id: &str, filename: &str) -> Result<String> {
+        let content = self.read(gist_id, filename)?;
+        if let IntegrityStatus::Mismatch { expected, actual } =
+            self.verify_file(gist_id, filename, &content)?
+        {
+            return Err(GistCacheError::IntegrityCheckFailed(format!(
+                "{}/{} (expected sha256 {}, found {})",
+                gist_id, filename, expected, actual
+            )));
+        }
+        Ok(content)
+    }
+
+    fn write(&self, gist_id: &str, filename: &str, content: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO contents (gist_id, filename, content, sha256, cached_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                gist_id,
+                filename,
+                content,
+                sha256_hex(content.as_bytes()),
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        self.touch(gist_id)?;
+
+        // The write itself succeeded, eviction is best-effort
+        if let Some(max_size) = self.max_size
+            && let Some(Err(e)) = unless_locked(self.lock_dir.as_deref(), || {
+                self.evict_lru(max_size, Some(gist_id), &[], false)
+            })
+        {
+            eprintln!(
+                "{}",
+                format!("Warning: Failed to evict old cache entries: {}", e).yellow()
+            );
+        }
+        Ok(())
+    }
+
+    fn delete_file(&self, gist_id: &str, filename: &str) -> Result<bool> {
+        let deleted = self.conn.execute(
+            "DELETE FROM contents WHERE gist_id = ?1 AND filename = ?2",
+            params![gist_id, filename],
+        )?;
+        // Once its last file is gone the gist is no longer cached, same as
+        // after delete_gist
+        self.conn.execute(
+            "DELETE FROM content_access WHERE gist_id = ?1
+             AND NOT EXISTS (SELECT 1 FROM contents WHERE gist_id = ?1)",
+            [gist_id],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    fn delete_gist(&self, gist_id: &str) -> Result<bool> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM contents WHERE gist_id = ?1", [gist_id])?;
+        self.conn
+            .execute("DELETE FROM content_access WHERE gist_id = ?1", [gist_id])?;
+        Ok(deleted > 0)
+    }
+
+    fn list_cached_gists(&self) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT gist_id FROM contents ORDER BY gist_id")?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(ids)
+    }
+
+    fn list_files(&self, gist_id: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT filename FROM contents WHERE gist_id = ?1 ORDER BY filename")?;
+        let names = stmt
+            .query_map([gist_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(names)
+    }
+
+    fn gist_size(&self, gist_id: &str) -> Result<u64> {
+        let size: i64 = self.conn.query_row(
+            "SELECT COALESCE(SUM(length(CAST(content AS BLOB))), 0)
+             FROM contents WHERE gist_id = ?1",
+            [gist_id],
+            |row| row.get(0),
+        )?;
+        Ok(size as u64)
+    }
+
+    fn total_size(&self) -> Result<u64> {
+        let size: i64 = self.conn.query_row(
+            "SELECT COALESCE(SUM(length(CAST(content AS BLOB))), 0) FROM contents",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(size as u64)
+    }
+
+    fn clear_all(&self) -> Result<()> {
+        self.conn
+            .execute_batch("DELETE FROM contents; DELETE FROM content_access;")?;
+        Ok(())
+    }
+
+    fn verify_gist(&self, gist_id: &str) -> Result<Vec<(String, IntegrityStatus)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT filename, content FROM contents WHERE gist_id = ?1 ORDER BY filename",
+        )?;
+        let rows = stmt.query_map([gist_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut statuses = Vec::new();
+        for row in rows {
+            let (filename, content) = row?;
+            let status = self.verify_file(gist_id, &filename, &content)?;
+            statuses.push((filename, status));
+        }
+        Ok(statuses)
+    }
+
+    fn location(&self) -> &Path {
+        &self.path
+    }
+
+    fn last_accessed(&self, gist_id: &str) -> Result<Option<DateTime<Utc>>> {
+        let value: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT last_accessed FROM content_access WHERE gist_id = ?1",
+                [gist_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        value.as_deref().map(parse_timestamp).transpose()
+    }
+
+    fn record_access(&self, gist_id: &str) -> Result<()> {
+        if self.list_files(gist_id)?.is_empty() {
+            return Ok(());
+        }
+        self.touch(gist_id)
+    }
+
+    fn check_readable(&self, gist_id: &str, filename: &str) -> Option<String> {
+        self.conn
+            .query_row(
+                "SELECT content FROM contents WHERE gist_id = ?1 AND filename = ?2",
+                params![gist_id, filename],
+                |row| row.get::<_, String>(0),
+            )
+            .err()
+            .map(|e| e.to_string())
+    }
+}
+
+/// What `cache migrate` copied into the database
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationSummary {
+    pub gists: usize,
+    pub files: usize,
+    pub usage_entries: usize,
+}
+
+/// One-shot migration from the JSON layout to SQLite
+///
+/// Copies everything into `cache.db` in one transaction and switches
+/// `cache.backend` to `sqlite`. The JSON files are left untouched so the
+/// migration can be undone with `config set cache.backend json`.
+pub fn migrate_json_to_sqlite(config: &mut Config) -> Result<MigrationSummary> {
+    if !config.cache_file.exists() {
+        return Err(GistCacheError::CacheNotFound);
+    }
+
+    let summary = SqliteStore::open(&config.cache_db_file())?.import_json_layout(config)?;
+    config.set_config_value("cache.backend", StorageBackend::Sqlite.as_str())?;
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::types::{CacheMetadata, GistFile};
+    use crate::config::CacheConfig;
+    use tempfile::TempDir;
+
+    fn test_gist(id: &str, filenames: &[&str]) -> GistInfo {
+        GistInfo {
+            id: id.to_string(),
+            description: Some(format!("Gist {}", id)),
+            files: filenames
+                .iter()
+                .map(|name| GistFile {
+                    filename: name.to_string(),
+                    language: None,
+                    size: 10,
+                    revision: None,
+                })
+                .collect(),
+            updated_at: Utc::now(),
+            public: true,
+            html_url: format!("https://gist.github.com/{}", id),
+        }
+    }
+
+    fn test_cache(gists: Vec<GistInfo>) -> GistCache {
+        GistCache {
+            metadata: CacheMetadata {
+                last_updated: Utc::now(),
+                total_count: gists.len(),
+                github_user: "testuser".to_string(),
+            },
+            gists,
+        }
+    }
+
+    #[test]
+    fn test_sqlite_metadata_roundtrip_and_lookups() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SqliteStore::open(&temp_dir.path().join("cache.db")).unwrap();
+        assert!(!store.cache_exists());
+        assert!(matches!(store.load(), Err(GistCacheError::CacheNotFound)));
+
+        let cache = test_cache(vec![
+            test_gist("bbb", &["Deploy.sh"]),
+            test_gist("aaa", &["backup.py", "README.md"]),
+        ]);
+        store.save(&cache).unwrap();
+        assert!(store.cache_exists());
+
+        // Saved order is preserved
+        let loaded = store.load().unwrap();
+        let ids: Vec<_> = loaded.gists.iter().map(|g| g.id.as_str()).collect();
+        assert_eq!(ids, vec!["bbb", "aaa"]);
+        assert_eq!(loaded.metadata.github_user, "testuser");
+
+        assert_eq!(store.find_by_id("aaa").unwrap().unwrap().files.len(), 2);
+        assert!(store.find_by_id("zzz").unwrap().is_none());
+
+        let found = store.find_by_filename("deploy").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, "bbb");
+        assert_eq!(store.find_by_filename(".").unwrap().len(), 2);
+
+        // Saving again replaces everything
+        store
+            .save(&test_cache(vec![test_gist("ccc", &["x.rb"])]))
+            .unwrap();
+        assert!(store.find_by_id("aaa").unwrap().is_none());
+        assert!(store.find_by_filename("backup").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sqlite_content_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SqliteStore::open(&temp_dir.path().join("cache.db")).unwrap();
+
+        assert!(!ContentStore::exists(&store, "g1", "a.sh"));
+        assert!(ContentStore::read(&store, "g1", "a.sh").is_err());
+
+        ContentStore::write(&store, "g1", "a.sh", "echo a").unwrap();
+        ContentStore::write(&store, "g1", "b.sh", "echo bb").unwrap();
+        ContentStore::write(&store, "g2", "c.sh", "echo c").unwrap();
+
+        assert!(ContentStore::exists(&store, "g1", "a.sh"));
+        assert_eq!(store.read_verified("g1", "a.sh").unwrap(), "echo a");
+        assert_eq!(store.list_cached_gists().unwrap(), vec!["g1", "g2"]);
+        assert_eq!(store.gist_size("g1").unwrap(), 13);
+        assert_eq!(ContentStore::total_size(&store).unwrap(), 19);
+
+        // Tampered rows are refused by read_verified and reported by verify_gist
+        store
+            .conn
+            .execute(
+                "UPDATE contents SET content = 'rm -rf /' WHERE filename = 'b.sh'",
+                [],
+            )
+            .unwrap();
+        assert!(matches!(
+            store.read_verified("g1", "b.sh"),
+            Err(GistCacheError::IntegrityCheckFailed(_))
+        ));
+        let statuses = ContentStore::verify_gist(&store, "g1").unwrap();
+        assert_eq!(statuses[0], ("a.sh".to_string(), IntegrityStatus::Valid));
+        assert!(matches!(statuses[1].1, IntegrityStatus::Mismatch { .. }));
+
+        // Orphaned contents are cleaned against the metadata
+        let metadata = test_cache(vec![test_gist("g1", &["a.sh", "b.sh"])]);
+        let options = CleanOptions {
+            orphaned: true,
+            ..Default::default()
+        };
+        let result = ContentStore::clean(&store, &metadata, &options).unwrap();
+        assert_eq!(result.deleted_gists, vec!["g2"]);
+        assert_eq!(result.deleted_size, 6);
+        assert_eq!(store.list_cached_gists().unwrap(), vec!["g1"]);
+
+        assert_eq!(
+            ContentStore::list_files(&store, "g1").unwrap(),
+            vec!["a.sh", "b.sh"]
+        );
+        assert!(ContentStore::delete_file(&store, "g1", "b.sh").unwrap());
+        assert!(!ContentStore::delete_file(&store, "g1", "b.sh").unwrap());
+        assert_eq!(
+            ContentStore::list_files(&store, "g1").unwrap(),
+            vec!["a.sh"]
+        );
+
+        assert!(ContentStore::delete_gist(&store, "g1").unwrap());
+        assert!(!ContentStore::delete_gist(&store, "g1").unwrap());
+
+        // Deleting the last file of a gist drops its access time as well
+        ContentStore::write(&store, "g3", "d.sh", "echo d").unwrap();
+        ContentStore::write(&store, "g3", "e.sh", "echo e").unwrap();
+        assert!(ContentStore::delete_file(&store, "g3", "d.sh").unwrap());
+        assert!(ContentStore::last_accessed(&store, "g3").unwrap().is_some());
+        assert!(ContentStore::delete_file(&store, "g3", "e.sh").unwrap());
+        assert_eq!(ContentStore::last_accessed(&store, "g3").unwrap(), None);
+    }
+
+    #[test]
+    fn test_sqlite_lru_eviction() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SqliteStore::open(&temp_dir.path().join("cache.db"))
+            .unwrap()
+            .with_max_size(Some(25));
+
+        ContentStore::write(&store, "first", "a.txt", "0123456789").unwrap();
+        ContentStore::write(&store, "second", "b.txt", "0123456789").unwrap();
+        assert!(
+            ContentStore::last_accessed(&store, "first")
+                .unwrap()
+                .is_some()
+        );
+        // Reading "first" makes "second" the least recently used
+        ContentStore::read(&store, "first", "a.txt").unwrap();
+
+        ContentStore::write(&store, "third", "c.txt", "0123456789").unwrap();
+        assert_eq!(store.list_cached_gists().unwrap(), vec!["first", "third"]);
+        assert!(
+            ContentStore::last_accessed(&store, "second")
+                .unwrap()
+                .is_none()
+        );
+
+        // Manual eviction (cache clean --lru) honours dry runs
+        let result = store.evict_lru(10, None, &[], true).unwrap();
+        assert_eq!(result.deleted_gists, vec!["first"]);
+        assert_eq!(store.list_cached_gists().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_find_by_filename_newest_first_for_both_backends() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+
+        let json = JsonMetadataStore::new(&config);
+        let sqlite = SqliteStore::open(&config.cache_db_file()).unwrap();
+        let stores: [&dyn MetadataStore; 2] = [&json, &sqlite];
+
+        // Saved oldest first, e.g. by an older version or an import
+        let mut old = test_gist("old", &["deploy.sh"]);
+        old.updated_at = Utc::now() - chrono::TimeDelta::days(2);
+        let mut new = test_gist("new", &["deploy.py"]);
+        new.updated_at = Utc::now();
+        let cache = test_cache(vec![old, test_gist("other", &["notes.md"]), new]);
+
+        for store in stores {
+            store.save(&cache).unwrap();
+            let ids: Vec<String> = store
+                .find_by_filename("DEPLOY")
+                .unwrap()
+                .into_iter()
+                .map(|g| g.id)
+                .collect();
+            assert_eq!(ids, vec!["new", "old"]);
+        }
+    }
+
+    #[test]
+    fn test_usage_counters_for_both_backends() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+
+        let json = JsonMetadataStore::new(&config);
+        let sqlite = SqliteStore::open(&config.cache_db_file()).unwrap();
+        let stores: [&dyn MetadataStore; 2] = [&json, &sqlite];
+
+        for store in stores {
+            assert!(store.usage().unwrap().is_empty());
+            store.record_usage("g1").unwrap();
+            store.record_usage("g1").unwrap();
+            store.record_usage("g2").unwrap();
+
+            let usage = store.usage().unwrap();
+            assert_eq!(usage["g1"].run_count, 2);
+            assert_eq!(usage["g2"].run_count, 1);
+        }
+    }
+
+    #[test]
+    fn test_tags_for_both_backends() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+
+        let json = JsonMetadataStore::new(&config);
+        let sqlite = SqliteStore::open(&config.cache_db_file()).unwrap();
+        let stores: [&dyn MetadataStore; 2] = [&json, &sqlite];
+
+        for store in stores {
+            assert!(store.tags().unwrap().is_empty());
+            let mut g1 = GistTags {
+                favorite: true,
+                ..GistTags::default()
+            };
+            g1.tags.insert("deploy".to_string());
+            store.set_tags("g1", &g1).unwrap();
+
+            let mut described = test_gist("g2", &["b.py"]);
+            described.description = Some("b.py - #Python helper".to_string());
+            let mut g1_info = test_gist("g1", &["a.sh"]);
+            g1_info.description = Some("a.sh - #bash".to_string());
+            store
+                .sync_description_tags(&[g1_info.clone(), described.clone()])
+                .unwrap();
+
+            let tags = store.tags().unwrap();
+            assert!(tags["g1"].favorite);
+            assert_eq!(
+                tags["g1"].all().into_iter().collect::<Vec<_>>(),
+                vec!["bash", "deploy"]
+            );
+            assert_eq!(
+                tags["g2"].all().into_iter().collect::<Vec<_>>(),
+                vec!["python"]
+            );
+
+            // Hashtags follow the description; tags added by hand stay
+            g1_info.description = Some("a.sh".to_string());
+            described.description = None;
+            store.sync_description_tags(&[g1_info, described]).unwrap();
+            let tags = store.tags().unwrap();
+            assert_eq!(
+                tags["g1"].all().into_iter().collect::<Vec<_>>(),
+                vec!["deploy"]
+            );
+            assert!(!tags.contains_key("g2"));
+
+            store.set_tags("g1", &GistTags::default()).unwrap();
+            assert!(store.tags().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_migrate_json_to_sqlite() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = Config::for_test(temp_dir.path());
+        config.user_config.cache = Some(CacheConfig::default());
+
+        assert!(matches!(
+            migrate_json_to_sqlite(&mut config),
+            Err(GistCacheError::CacheNotFound)
+        ));
+
+        let json = JsonMetadataStore::new(&config);
+        json.save(&test_cache(vec![
+            test_gist("g1", &["a.sh"]),
+            test_gist("g2", &["b.py"]),
+        ]))
+        .unwrap();
+        json.record_usage("g1").unwrap();
+        let favorite = GistTags {
+            favorite: true,
+            ..GistTags::default()
+        };
+        json.set_tags("g2", &favorite).unwrap();
+        let contents = ContentCache::new(config.contents_dir.clone());
+        contents.write("g1", "a.sh", "echo a").unwrap();
+
+        let summary = migrate_json_to_sqlite(&mut config).unwrap();
+        assert_eq!(
+            summary,
+            MigrationSummary {
+                gists: 2,
+                files: 1,
+                usage_entries: 1,
+            }
+        );
+        assert_eq!(config.storage_backend(), StorageBackend::Sqlite);
+        assert!(config.cache_file.exists(), "JSON layout is kept");
+
+        let metadata = open_metadata_store(&config).unwrap();
+        assert_eq!(metadata.load().unwrap().gists.len(), 2);
+        assert_eq!(metadata.usage().unwrap()["g1"].run_count, 1);
+        assert_eq!(metadata.tags().unwrap()["g2"], favorite);
+
+        let content = open_content_store(&config).unwrap();
+        assert_eq!(content.read_verified("g1", "a.sh").unwrap(), "echo a");
+    }
+}
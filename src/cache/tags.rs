@@ -0,0 +1,128 @@
+//! Local tags and favorites
+//!
+//! Gist descriptions are the only metadata GitHub keeps, so tags live in
+//! the local store (`tags.json`, or the `tags` table on the SQLite backend)
+//! keyed by gist ID. They come from two places: `tag add`, and the
+//! `#hashtags` in a description, which every `update` re-extracts so they
+//! follow edits to the description. Favorites are pinned to the top of the
+//! picker.
+
+use crate::error::{GistCacheError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Tags and the favorite flag of one gist
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GistTags {
+    /// Added with `tag add`
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    /// `#hashtags` in the description, as of the last update
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub description_tags: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub favorite: bool,
+}
+
+impl GistTags {
+    /// Every tag of the gist, added or from its description
+    pub fn all(&self) -> BTreeSet<&str> {
+        self.tags
+            .iter()
+            .chain(&self.description_tags)
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Whether there is nothing worth storing
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.description_tags.is_empty() && !self.favorite
+    }
+}
+
+/// Characters a tag is made of, besides letters and digits
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/')
+}
+
+/// `tag` in its stored form: lowercase, without a leading `#`
+///
+/// # Errors
+/// Returns `InvalidTag` if it is empty or contains other characters than
+/// letters, digits, `-`, `_`, `.` and `/`
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let tag = tag.strip_prefix('#').unwrap_or(tag);
+    if tag.is_empty() || !tag.chars().all(is_tag_char) {
+        return Err(GistCacheError::InvalidTag(tag.to_string()));
+    }
+    Ok(tag.to_lowercase())
+}
+
+/// `#hashtags` in `text`, normalized like `normalize_tag`
+///
+/// A `#` only starts a tag at the start of a word, so URL fragments
+/// (`page#section`) are skipped, and so are numbers like `#42`. Trailing
+/// punctuation (`#bash.`) is not part of the tag.
+pub fn hashtags(text: &str) -> BTreeSet<String> {
+    let mut tags = BTreeSet::new();
+    let mut previous = None;
+    for (start, c) in text.char_indices() {
+        let starts_word = previous.is_none_or(|p: char| p.is_whitespace() || "([{,;".contains(p));
+        previous = Some(c);
+        if c != '#' || !starts_word {
+            continue;
+        }
+
+        let rest = &text[start + 1..];
+        let end = rest.find(|c| !is_tag_char(c)).unwrap_or(rest.len());
+        let tag = rest[..end].trim_end_matches(['.', '-', '/']);
+        if !tag.is_empty() && !tag.chars().all(|c| c.is_ascii_digit()) {
+            tags.insert(tag.to_lowercase());
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashtags() {
+        let tags =
+            hashtags("deploy.sh - Deploy to #Prod (#k8s, #ci/cd). See https://x.io/a#frag #42 ##");
+        assert_eq!(
+            tags.into_iter().collect::<Vec<_>>(),
+            vec!["ci/cd", "k8s", "prod"]
+        );
+        assert!(hashtags("").is_empty());
+        assert_eq!(
+            hashtags("#日本語 メモ").into_iter().collect::<Vec<_>>(),
+            vec!["日本語"]
+        );
+    }
+
+    #[test]
+    fn test_normalize_tag_and_all() {
+        assert_eq!(normalize_tag("#Bash").unwrap(), "bash");
+        assert_eq!(normalize_tag("ci/cd").unwrap(), "ci/cd");
+        for invalid in ["", "#", "two words", "a,b"] {
+            assert!(matches!(
+                normalize_tag(invalid),
+                Err(GistCacheError::InvalidTag(_))
+            ));
+        }
+
+        let mut tags = GistTags::default();
+        assert!(tags.is_empty());
+        tags.favorite = true;
+        assert!(!tags.is_empty());
+        tags.tags.insert("deploy".to_string());
+        tags.description_tags.insert("bash".to_string());
+        tags.description_tags.insert("deploy".to_string());
+        assert_eq!(
+            tags.all().into_iter().collect::<Vec<_>>(),
+            vec!["bash", "deploy"]
+        );
+    }
+}
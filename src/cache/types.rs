@@ -57,6 +57,10 @@ pub struct GistFile {
     pub filename: String,
     pub language: Option<String>,
     pub size: usize,
+    /// Per-file revision (the SHA in the file's `raw_url`); changes only
+    /// when this file changes, so updates can invalidate just this file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
 }
 
 // GitHub API response types
@@ -75,6 +79,17 @@ pub struct GitHubFile {
     pub filename: String,
     pub language: Option<String>,
     pub size: usize,
+    #[serde(default)]
+    pub raw_url: Option<String>,
+}
+
+impl GitHubFile {
+    /// Revision SHA from `raw_url` (`.../raw/<sha>/<filename>`)
+    pub fn revision(&self) -> Option<String> {
+        let url = self.raw_url.as_deref()?;
+        let sha = url.split("/raw/").nth(1)?.split('/').next()?;
+        (sha.len() == 40 && sha.chars().all(|c| c.is_ascii_hexdigit())).then(|| sha.to_string())
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -107,6 +122,7 @@ impl From<GitHubGist> for GistInfo {
                 .files
                 .into_values()
                 .map(|f| GistFile {
+                    revision: f.revision(),
                     filename: f.filename,
                     language: f.language,
                     size: f.size,
@@ -170,6 +186,7 @@ mod tests {
                     filename: "test.rs".to_string(),
                     language: Some("Rust".to_string()),
                     size: 100,
+                    revision: None,
                 }],
                 updated_at: Utc::now(),
                 public: true,
@@ -192,6 +209,7 @@ mod tests {
                 filename: "test.rs".to_string(),
                 language: Some("Rust".to_string()),
                 size: 100,
+                raw_url: None,
             },
         );
 
@@ -213,6 +231,35 @@ mod tests {
         assert_eq!(gist_info.files[0].size, 100);
         assert!(gist_info.public);
         assert_eq!(gist_info.html_url, "https://gist.github.com/abc123");
+        assert_eq!(gist_info.files[0].revision, None);
+    }
+
+    #[test]
+    fn test_github_file_revision_from_raw_url() {
+        let file: GitHubFile = serde_json::from_str(
+            r#"{
+                "filename": "hello.rb",
+                "language": "Ruby",
+                "size": 12,
+                "raw_url": "https://gist.githubusercontent.com/octocat/6cad326836d38bd3a7ae/raw/db9c55113504e46fa076e7df3a04ce592e2e86d8/hello.rb"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            file.revision().as_deref(),
+            Some("db9c55113504e46fa076e7df3a04ce592e2e86d8")
+        );
+
+        let file = GitHubFile {
+            raw_url: Some("https://example.com/not-a-raw-url".to_string()),
+            ..file
+        };
+        assert_eq!(file.revision(), None);
+
+        // Metadata cached before revisions were recorded still loads
+        let legacy: GistFile =
+            serde_json::from_str(r#"{"filename": "a.sh", "language": null, "size": 1}"#).unwrap();
+        assert_eq!(legacy.revision, None);
     }
 
     #[test]
@@ -221,6 +268,7 @@ mod tests {
             filename: "test.rs".to_string(),
             language: Some("Rust".to_string()),
             size: 100,
+            revision: None,
         };
 
         let cloned = file.clone();
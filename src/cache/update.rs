@@ -1,17 +1,21 @@
-use crate::cache::ContentCache;
-use crate::cache::types::{CacheMetadata, GistCache, GistInfo};
-use crate::config::Config;
+use crate::cache::CacheLock;
+use crate::cache::journal::{Journal, JournalEvent};
+use crate::cache::report::{GistChange, InvalidatedFile, UpdateMode, UpdateReport};
+use crate::cache::storage::{ContentStore, open_content_store, open_metadata_store};
+use crate::cache::types::{CacheMetadata, GistCache, GistInfo, GitHubGist};
+use crate::config::{Config, format_duration};
 use crate::error::Result;
 use crate::github::{GitHubApi, GitHubClient};
-use chrono::Utc;
+use chrono::{Duration, Utc};
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{HashMap, HashSet};
 
 pub struct CacheUpdater<C: GitHubClient = GitHubApi> {
     config: Config,
     verbose: bool,
+    /// Suppress progress output (e.g. when the report is printed as JSON)
+    quiet: bool,
     client: C,
 }
 
@@ -20,6 +24,7 @@ impl CacheUpdater<GitHubApi> {
         Self {
             config,
             verbose,
+            quiet: false,
             client: GitHubApi::new(),
         }
     }
@@ -30,27 +35,72 @@ impl<C: GitHubClient> CacheUpdater<C> {
         Self {
             config,
             verbose,
+            quiet: false,
             client,
         }
     }
 
-    pub fn update(&self, force: bool) -> Result<()> {
-        println!("{}", "Updating Gist cache...".cyan());
+    /// Suppress progress output; warnings still go to stderr
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Differential update, but only when the metadata cache is older than `max_age`
+    ///
+    /// # Returns
+    /// The update's report, or `None` if the cache was fresh enough
+    pub fn update_if_stale(&self, max_age: Duration) -> Result<Option<UpdateReport>> {
+        let last_updated = open_metadata_store(&self.config)?.metadata()?.last_updated;
+        if Utc::now() - last_updated <= max_age {
+            return Ok(None);
+        }
+
+        self.print(
+            format!(
+                "Cache is older than {} (last updated {})",
+                format_duration(max_age.num_seconds().max(0) as u64),
+                last_updated.format("%Y-%m-%dT%H:%M:%SZ")
+            )
+            .yellow(),
+        );
+        self.update(false).map(Some)
+    }
+
+    /// Print a progress line unless the updater is quiet
+    fn print(&self, line: impl std::fmt::Display) {
+        if !self.quiet {
+            println!("{}", line);
+        }
+    }
+
+    /// Fetch gists from GitHub and rewrite the metadata cache
+    ///
+    /// Differential unless `force`; cached files of changed gists are
+    /// invalidated file by file.
+    ///
+    /// # Returns
+    /// What was added, modified, removed and invalidated
+    pub fn update(&self, force: bool) -> Result<UpdateReport> {
+        self.print("Updating Gist cache...".cyan());
 
         if self.verbose {
             if force {
-                println!("{}", "Mode: Force full update".yellow());
+                self.print("Mode: Force full update".yellow());
             } else {
-                println!("{}", "Mode: Differential update".yellow());
+                self.print("Mode: Differential update".yellow());
             }
         }
 
         // Ensure cache directory exists
         self.config.ensure_cache_dir()?;
 
-        // Create ContentCache instance
-        let content_cache = ContentCache::new(self.config.contents_dir.clone());
-        content_cache.ensure_cache_dir()?;
+        // Serialize with other processes rewriting the cache (held until return)
+        let _lock = CacheLock::acquire(&self.config)?;
+
+        // Open the configured storage backend
+        let metadata_store = open_metadata_store(&self.config)?;
+        let content_cache = open_content_store(&self.config)?;
 
         // Check authentication
         self.client.check_auth()?;
@@ -58,34 +108,44 @@ impl<C: GitHubClient> CacheUpdater<C> {
         // Check rate limit
         let rate_limit = self.client.check_rate_limit()?;
         if rate_limit < 100 {
-            println!(
+            // A warning, so shown even when quiet
+            eprintln!(
                 "{}",
                 format!("Warning: Rate limit remaining is low at {}", rate_limit).yellow()
             );
         }
         if self.verbose {
-            println!(
-                "{}",
-                format!("Rate limit remaining: {}", rate_limit).green()
-            );
+            self.print(format!("Rate limit remaining: {}", rate_limit).green());
         }
 
-        // Load existing cache if available
-        let (github_user, last_updated, old_gists) = if self.config.cache_exists() && !force {
-            let cache = self.load_cache()?;
+        // Load existing cache if available (a full update only compares
+        // against it for the report, so an unreadable one is no obstacle)
+        let previous = if !metadata_store.cache_exists() {
+            None
+        } else if force {
+            metadata_store.load().ok()
+        } else {
+            Some(metadata_store.load()?)
+        };
+        let previous_gists = previous
+            .as_ref()
+            .map(|cache| cache.gists.clone())
+            .unwrap_or_default();
+
+        let (github_user, last_updated, old_gists) = if let Some(cache) =
+            previous.filter(|_| !force)
+        {
             if self.verbose {
-                println!("{}", "Detected existing cache".green());
-                println!(
-                    "{}",
-                    format!("GitHub user (cache reused): {}", cache.metadata.github_user).green()
+                self.print("Detected existing cache".green());
+                self.print(
+                    format!("GitHub user (cache reused): {}", cache.metadata.github_user).green(),
                 );
-                println!(
-                    "{}",
+                self.print(
                     format!(
                         "Last updated: {}",
                         cache.metadata.last_updated.format("%Y-%m-%dT%H:%M:%SZ")
                     )
-                    .green()
+                    .green(),
                 );
             }
             (
@@ -98,11 +158,11 @@ impl<C: GitHubClient> CacheUpdater<C> {
         };
 
         if self.verbose && last_updated.is_none() {
-            println!("{}", format!("GitHub user: {}", github_user).green());
+            self.print(format!("GitHub user: {}", github_user).green());
         }
 
         // Fetch gists from GitHub
-        let spinner = if !self.verbose {
+        let spinner = if !self.verbose && !self.quiet {
             let sp = ProgressBar::new_spinner();
             sp.set_style(
                 ProgressStyle::default_spinner()
@@ -113,7 +173,7 @@ impl<C: GitHubClient> CacheUpdater<C> {
             sp.enable_steady_tick(std::time::Duration::from_millis(100));
             Some(sp)
         } else {
-            println!("{}", "Fetching Gist information from GitHub API...".cyan());
+            self.print("Fetching Gist information from GitHub API...".cyan());
             None
         };
 
@@ -124,18 +184,18 @@ impl<C: GitHubClient> CacheUpdater<C> {
         if let Some(sp) = spinner {
             sp.finish_with_message(format!("Fetched {} Gists", fetched_count));
         } else if self.verbose {
-            println!("{}", format!("Fetched Gists: {}", fetched_count).green());
+            self.print(format!("Fetched Gists: {}", fetched_count).green());
         }
 
         // Compare metadata and identify cache to be deleted
-        let mut deleted_cache_count = 0;
+        let mut invalidated = Vec::new();
         if let Some(ref old) = old_gists {
             // Convert old metadata to Map
             let old_map: HashMap<String, &GistInfo> =
                 old.iter().map(|g| (g.id.clone(), g)).collect();
 
             // Show progress bar if processing many gists and not in verbose mode
-            let progress = if !self.verbose && fetched_count > 10 {
+            let progress = if !self.verbose && !self.quiet && fetched_count > 10 {
                 let pb = ProgressBar::new(fetched_count as u64);
                 pb.set_style(
                     ProgressStyle::default_bar()
@@ -158,8 +218,7 @@ impl<C: GitHubClient> CacheUpdater<C> {
                     if old_gist.updated_at != new_gist.updated_at {
                         // Delete cache
                         if self.verbose {
-                            println!(
-                                "{}",
+                            self.print(
                                 format!(
                                     "Detected Gist update: {} ({})",
                                     new_gist.id,
@@ -168,39 +227,73 @@ impl<C: GitHubClient> CacheUpdater<C> {
                                         .as_ref()
                                         .unwrap_or(&"No description".to_string())
                                 )
-                                .yellow()
+                                .yellow(),
                             );
                         }
 
+                        // Only the cached files that actually changed are invalidated
                         // Self-healing principle: Log and continue even if error occurs
-                        match content_cache.delete_gist(&new_gist.id) {
-                            Ok(deleted) => {
-                                if deleted {
-                                    // Count only when actually deleted
-                                    deleted_cache_count += 1;
-                                    if self.verbose {
-                                        println!(
-                                            "{}",
-                                            format!("  → Deleted cache: {}", new_gist.id).green()
-                                        );
-                                    }
-                                } else if self.verbose {
-                                    // If it didn't exist (display only in verbose mode)
-                                    println!(
-                                        "{}",
-                                        format!("  → Cache did not exist: {}", new_gist.id).cyan()
-                                    );
-                                }
-                            }
-                            Err(e) => {
+                        let cached_files =
+                            content_cache.list_files(&new_gist.id).unwrap_or_else(|e| {
                                 eprintln!(
                                     "{}",
                                     format!(
-                                        "  Warning: Failed to delete cache: {} - {}",
+                                        "  Warning: Failed to list cache: {} - {}",
                                         new_gist.id, e
                                     )
                                     .yellow()
                                 );
+                                Vec::new()
+                            });
+
+                        if cached_files.is_empty() && self.verbose {
+                            // If it didn't exist (display only in verbose mode)
+                            self.print(format!("  → Cache did not exist: {}", new_gist.id).cyan());
+                        }
+
+                        for filename in cached_files {
+                            if !is_file_stale(old_gist, new_gist, &filename) {
+                                if self.verbose {
+                                    self.print(
+                                        format!(
+                                            "  → Kept cache (unchanged): {}/{}",
+                                            new_gist.id, filename
+                                        )
+                                        .cyan(),
+                                    );
+                                }
+                                continue;
+                            }
+
+                            match content_cache.delete_file(&new_gist.id, &filename) {
+                                Ok(deleted) => {
+                                    if deleted {
+                                        // Report only when actually deleted
+                                        invalidated.push(InvalidatedFile {
+                                            gist_id: new_gist.id.clone(),
+                                            filename: filename.clone(),
+                                        });
+                                        if self.verbose {
+                                            self.print(
+                                                format!(
+                                                    "  → Deleted cache: {}/{}",
+                                                    new_gist.id, filename
+                                                )
+                                                .green(),
+                                            );
+                                        }
+                                    }
+                                }
+                                Err(e) => {
+                                    eprintln!(
+                                        "{}",
+                                        format!(
+                                            "  Warning: Failed to delete cache: {}/{} - {}",
+                                            new_gist.id, filename, e
+                                        )
+                                        .yellow()
+                                    );
+                                }
                             }
                         }
                     }
@@ -219,7 +312,7 @@ impl<C: GitHubClient> CacheUpdater<C> {
         // Merge with existing cache if doing differential update
         let final_gists = if let Some(mut old) = old_gists {
             if fetched_count == 0 {
-                println!("{}", "No updates".green());
+                self.print("No updates".green());
                 old
             } else {
                 // Merge by ID, keeping the latest version
@@ -242,27 +335,23 @@ impl<C: GitHubClient> CacheUpdater<C> {
                 let new = total_count - old_count;
 
                 if self.verbose {
-                    println!(
-                        "{}",
+                    self.print(
                         format!(
                             "Differential merge completed: Existing {} + Diff {} → Total {}",
                             old_count, fetched_count, total_count
                         )
-                        .green()
+                        .green(),
                     );
                 }
 
-                println!("{}", format!("Updated: {} items", fetched_count).green());
+                self.print(format!("Updated: {} items", fetched_count).green());
                 if new > 0 && self.verbose {
-                    println!("{}", format!("New Gists: {} items", new).green());
+                    self.print(format!("New Gists: {} items", new).green());
                 }
 
-                // Report cache deletion
-                if deleted_cache_count > 0 {
-                    println!(
-                        "{}",
-                        format!("Cache deleted: {} items", deleted_cache_count).yellow()
-                    );
+                // Report cache invalidation
+                if !invalidated.is_empty() {
+                    self.print(format!("Cache invalidated: {} files", invalidated.len()).yellow());
                 }
 
                 merged
@@ -275,57 +364,210 @@ impl<C: GitHubClient> CacheUpdater<C> {
             gists.sort_by_key(|g| std::cmp::Reverse(g.updated_at));
 
             let count = gists.len();
-            println!("{}", format!("New/Updated: {} items", count).green());
+            self.print(format!("New/Updated: {} items", count).green());
             gists
         };
 
+        let (added, modified, removed) = diff_gists(&previous_gists, &final_gists);
+        let updated_at = Utc::now();
+
         // Create cache data
         let cache = GistCache {
             metadata: CacheMetadata {
-                last_updated: Utc::now(),
+                last_updated: updated_at,
                 total_count: final_gists.len(),
                 github_user,
             },
             gists: final_gists,
         };
 
-        // Save to file
-        self.save_cache(&cache)?;
+        // Save (a single transaction on the SQLite backend)
+        metadata_store.save(&cache)?;
+
+        // Tags are local extras, a failure to extract them only warns
+        if let Err(e) = metadata_store.sync_description_tags(&cache.gists) {
+            eprintln!(
+                "{}",
+                format!("Warning: Failed to update description tags: {}", e).yellow()
+            );
+        }
 
-        println!("{}", "Cache update completed".green().bold());
-        println!(
-            "{}",
+        self.print("Cache update completed".green().bold());
+        self.print(
             format!("Total Gists: {}", cache.metadata.total_count)
                 .cyan()
-                .bold()
+                .bold(),
         );
 
-        Ok(())
+        let report = UpdateReport {
+            mode: if force {
+                UpdateMode::Full
+            } else {
+                UpdateMode::Differential
+            },
+            github_user: cache.metadata.github_user,
+            since,
+            updated_at,
+            fetched: fetched_count,
+            added,
+            modified,
+            removed,
+            invalidated,
+            rate_limit_remaining: rate_limit,
+            total_gists: cache.metadata.total_count,
+        };
+
+        // The update itself succeeded, the journal is best-effort. Updates
+        // that changed nothing are recorded too, as evidence of when the
+        // cache was last known to match GitHub.
+        if let Err(e) = Journal::new(&self.config).append(JournalEvent::from(&report)) {
+            eprintln!(
+                "{}",
+                format!("Warning: Failed to write cache journal: {}", e).yellow()
+            );
+        }
+
+        Ok(report)
+    }
+
+    /// Fetch the files of the gists an update added or modified that are not
+    /// cached yet, so the next `run` needs no network access
+    ///
+    /// A file that cannot be fetched only prints a warning (see
+    /// `prefetch_files`).
+    ///
+    /// # Returns
+    /// Number of files written to the content cache
+    pub fn prefetch(&self, report: &UpdateReport) -> Result<usize> {
+        let content_store = open_content_store(&self.config)?;
+        let mut fetched = 0;
+
+        for change in report.added.iter().chain(&report.modified) {
+            fetched += prefetch_files(
+                &self.client,
+                content_store.as_ref(),
+                &change.id,
+                change.files.iter().map(String::as_str),
+            )?;
+        }
+
+        if self.verbose && fetched > 0 {
+            self.print(format!("Prefetched: {} files", fetched).green());
+        }
+
+        Ok(fetched)
+    }
+}
+
+/// Fetch the files of `gist_id` named in `filenames` that are not cached yet
+///
+/// A file that cannot be fetched only prints a warning; it is fetched on
+/// first use as usual.
+///
+/// # Returns
+/// Number of files written to the content cache
+///
+/// # Errors
+/// Returns an error only if a fetched file cannot be written to the cache
+pub fn prefetch_files<'a>(
+    client: &impl GitHubClient,
+    content_store: &dyn ContentStore,
+    gist_id: &str,
+    filenames: impl IntoIterator<Item = &'a str>,
+) -> Result<usize> {
+    let mut fetched = 0;
+    for filename in filenames {
+        if content_store.exists(gist_id, filename) {
+            continue;
+        }
+
+        match client.fetch_gist_content(gist_id, filename) {
+            Ok(content) => {
+                content_store.write(gist_id, filename, &content)?;
+                fetched += 1;
+            }
+            Err(e) => eprintln!(
+                "{}",
+                format!(
+                    "Warning: Failed to prefetch {}/{}: {}",
+                    gist_id, filename, e
+                )
+                .yellow()
+            ),
+        }
     }
+    Ok(fetched)
+}
 
-    fn load_cache(&self) -> Result<GistCache> {
-        let content = fs::read_to_string(&self.config.cache_file)?;
-        let cache: GistCache = serde_json::from_str(&content)?;
-        Ok(cache)
+/// Gists added, modified (new `updated_at`) and removed between two versions
+/// of the metadata cache
+fn diff_gists(
+    previous: &[GistInfo],
+    current: &[GistInfo],
+) -> (Vec<GistChange>, Vec<GistChange>, Vec<GistChange>) {
+    let previous_map: HashMap<&str, &GistInfo> =
+        previous.iter().map(|g| (g.id.as_str(), g)).collect();
+    let current_ids: HashSet<&str> = current.iter().map(|g| g.id.as_str()).collect();
+
+    let mut added = Vec::new();
+    let mut modified = Vec::new();
+    for gist in current {
+        match previous_map.get(gist.id.as_str()) {
+            None => added.push(GistChange::from(gist)),
+            Some(old) if old.updated_at != gist.updated_at => modified.push(GistChange::from(gist)),
+            Some(_) => {}
+        }
     }
+    let removed = previous
+        .iter()
+        .filter(|g| !current_ids.contains(g.id.as_str()))
+        .map(GistChange::from)
+        .collect();
+
+    (added, modified, removed)
+}
 
-    fn save_cache(&self, cache: &GistCache) -> Result<()> {
-        let json = serde_json::to_string_pretty(cache)?;
-        fs::write(&self.config.cache_file, json)?;
-        Ok(())
+/// Whether a cached file of an updated Gist has to be re-fetched
+///
+/// A file survives only if it is still part of the Gist with the same size
+/// and the same per-file revision as in the previous metadata. Metadata saved
+/// before revisions were recorded can't prove that, so those files are stale.
+fn is_file_stale(old_gist: &GistInfo, new_gist: &GitHubGist, filename: &str) -> bool {
+    let Some(new_file) = new_gist.files.values().find(|f| f.filename == filename) else {
+        return true;
+    };
+    let Some(old_file) = old_gist.files.iter().find(|f| f.filename == filename) else {
+        return true;
+    };
+
+    match (&old_file.revision, new_file.revision()) {
+        (Some(old_revision), Some(new_revision)) => {
+            *old_revision != new_revision || old_file.size != new_file.size
+        }
+        _ => true,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::ContentCache;
     use crate::cache::types::GitHubFile;
     use crate::github::MockGitHubClient;
-    use chrono::Utc;
     use std::collections::HashMap;
     use std::fs;
     use tempfile::TempDir;
 
+    impl<C: GitHubClient> CacheUpdater<C> {
+        fn load_cache(&self) -> Result<GistCache> {
+            open_metadata_store(&self.config)?.load()
+        }
+
+        fn save_cache(&self, cache: &GistCache) -> Result<()> {
+            open_metadata_store(&self.config)?.save(cache)
+        }
+    }
+
     fn create_test_config() -> Config {
         let temp_dir = TempDir::new().unwrap();
         let cache_dir = temp_dir.path().join("cache");
@@ -355,6 +597,7 @@ mod tests {
                     filename: "test.sh".to_string(),
                     language: Some("Shell".to_string()),
                     size: 100,
+                    revision: None,
                 }],
                 updated_at: Utc::now(),
                 public: true,
@@ -467,6 +710,7 @@ mod tests {
                             filename: "test.sh".to_string(),
                             language: Some("Shell".to_string()),
                             size: 50,
+                            raw_url: None,
                         },
                     )]),
                     updated_at: Utc::now(),
@@ -488,6 +732,175 @@ mod tests {
         assert_eq!(loaded.gists[0].id, "mock123");
     }
 
+    #[test]
+    fn test_update_extracts_description_hashtags() {
+        let config = create_test_config();
+        config.ensure_cache_dir().unwrap();
+
+        let mut mock = MockGitHubClient::new();
+        mock.expect_check_auth().returning(|| Ok(()));
+        mock.expect_check_rate_limit().returning(|| Ok(5000));
+        mock.expect_get_user()
+            .returning(|| Ok("mockuser".to_string()));
+        mock.expect_fetch_gists().returning(|_| {
+            Ok(vec![crate::cache::types::GitHubGist {
+                id: "mock123".to_string(),
+                description: Some("test.sh - Deploy helper #Deploy #ops".to_string()),
+                files: HashMap::from([(
+                    "test.sh".to_string(),
+                    GitHubFile {
+                        filename: "test.sh".to_string(),
+                        language: Some("Shell".to_string()),
+                        size: 50,
+                        raw_url: None,
+                    },
+                )]),
+                updated_at: Utc::now(),
+                public: true,
+                html_url: "https://gist.github.com/mock123".to_string(),
+            }])
+        });
+
+        CacheUpdater::new_with_client(config.clone(), false, mock)
+            .update(true)
+            .unwrap();
+
+        let tags = open_metadata_store(&config).unwrap().tags().unwrap();
+        assert_eq!(
+            tags["mock123"].all().into_iter().collect::<Vec<_>>(),
+            vec!["deploy", "ops"]
+        );
+    }
+
+    #[test]
+    fn test_update_report_lists_changes() {
+        use chrono::Timelike;
+
+        let config = create_test_config();
+        config.ensure_cache_dir().unwrap();
+
+        // test123 (updated below) and gone456 (deleted on GitHub)
+        let mut existing_cache = create_test_cache();
+        let mut gone = existing_cache.gists[0].clone();
+        gone.id = "gone456".to_string();
+        existing_cache.gists.push(gone);
+        let updater_temp = CacheUpdater::new(config.clone(), false);
+        updater_temp.save_cache(&existing_cache).unwrap();
+        ContentCache::new(config.contents_dir.clone())
+            .write("test123", "test.sh", "echo old")
+            .unwrap();
+
+        // Whole seconds, as returned by the GitHub API
+        let updated_at = (Utc::now() + chrono::Duration::minutes(1))
+            .with_nanosecond(0)
+            .unwrap();
+        let gh_gist = |id: &str| crate::cache::types::GitHubGist {
+            id: id.to_string(),
+            description: Some(format!("Gist {}", id)),
+            files: HashMap::from([(
+                "test.sh".to_string(),
+                GitHubFile {
+                    filename: "test.sh".to_string(),
+                    language: Some("Shell".to_string()),
+                    size: 120,
+                    raw_url: None,
+                },
+            )]),
+            updated_at,
+            public: true,
+            html_url: format!("https://gist.github.com/{}", id),
+        };
+        let fetched = vec![gh_gist("test123"), gh_gist("new789")];
+
+        let mut mock = MockGitHubClient::new();
+        mock.expect_check_auth().returning(|| Ok(()));
+        mock.expect_check_rate_limit().returning(|| Ok(4321));
+        mock.expect_get_user()
+            .returning(|| Ok("testuser".to_string()));
+        let differential = fetched.clone();
+        mock.expect_fetch_gists()
+            .withf(|since| since.is_some())
+            .returning(move |_| Ok(differential.clone()));
+        mock.expect_fetch_gists()
+            .withf(|since| since.is_none())
+            .returning(move |_| Ok(fetched.clone()));
+
+        let updater = CacheUpdater::new_with_client(config, false, mock).quiet(true);
+        let report = updater.update(false).unwrap();
+        assert_eq!(report.mode, UpdateMode::Differential);
+        assert_eq!(report.fetched, 2);
+        assert_eq!(report.rate_limit_remaining, 4321);
+        assert_eq!(report.added.len(), 1);
+        assert_eq!(report.added[0].id, "new789");
+        assert_eq!(report.modified.len(), 1);
+        assert_eq!(report.modified[0].id, "test123");
+        // Deletions are invisible to a differential update
+        assert!(report.removed.is_empty());
+        assert_eq!(
+            report.invalidated,
+            vec![InvalidatedFile {
+                gist_id: "test123".to_string(),
+                filename: "test.sh".to_string()
+            }]
+        );
+        assert_eq!(report.total_gists, 3);
+
+        let report = updater.update(true).unwrap();
+        assert_eq!(report.mode, UpdateMode::Full);
+        assert!(report.added.is_empty());
+        assert!(report.modified.is_empty());
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.removed[0].id, "gone456");
+        assert_eq!(report.total_gists, 2);
+    }
+
+    #[test]
+    fn test_prefetch_fetches_missing_files_of_changed_gists() {
+        let config = create_test_config();
+        config.ensure_cache_dir().unwrap();
+        let content_cache = ContentCache::new(config.contents_dir.clone());
+        content_cache
+            .write("mod1", "cached.sh", "echo cached")
+            .unwrap();
+
+        let change = |id: &str, files: &[&str]| GistChange {
+            id: id.to_string(),
+            description: None,
+            files: files.iter().map(|f| f.to_string()).collect(),
+            updated_at: Utc::now(),
+            html_url: format!("https://gist.github.com/{}", id),
+        };
+        let report = UpdateReport {
+            mode: UpdateMode::Differential,
+            github_user: "testuser".to_string(),
+            since: None,
+            updated_at: Utc::now(),
+            fetched: 2,
+            added: vec![change("new1", &["run.sh", "broken.sh"])],
+            modified: vec![change("mod1", &["cached.sh"])],
+            removed: vec![change("gone1", &["old.sh"])],
+            invalidated: vec![],
+            rate_limit_remaining: 5000,
+            total_gists: 2,
+        };
+
+        let mut mock = MockGitHubClient::new();
+        mock.expect_fetch_gist_content()
+            .withf(|id, filename| id == "new1" && filename == "run.sh")
+            .times(1)
+            .returning(|_, _| Ok("echo new".to_string()));
+        mock.expect_fetch_gist_content()
+            .withf(|id, filename| id == "new1" && filename == "broken.sh")
+            .times(1)
+            .returning(|_, _| Err(crate::error::GistCacheError::GitHubApi("gone".to_string())));
+
+        let updater = CacheUpdater::new_with_client(config, false, mock).quiet(true);
+        // Already cached and removed gists are not fetched; failures are skipped
+        assert_eq!(updater.prefetch(&report).unwrap(), 1);
+        assert_eq!(content_cache.read("new1", "run.sh").unwrap(), "echo new");
+        assert!(!content_cache.exists("new1", "broken.sh"));
+    }
+
     #[test]
     fn test_update_differential_with_mock() {
         let config = create_test_config();
@@ -520,6 +933,7 @@ mod tests {
                             filename: "new.py".to_string(),
                             language: Some("Python".to_string()),
                             size: 200,
+                            raw_url: None,
                         },
                     )]),
                     updated_at: Utc::now(),
@@ -567,6 +981,52 @@ mod tests {
         let loaded = updater.load_cache().unwrap();
         assert_eq!(loaded.gists.len(), 1); // 既存のまま
         assert_eq!(loaded.gists[0].id, "test123");
+
+        // 変更のない更新もジャーナルに記録する
+        let entries = Journal::new(&config).entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(!entries[0].involves("test123"));
+    }
+
+    #[test]
+    fn test_update_if_stale_respects_max_age() {
+        let config = create_test_config();
+        config.ensure_cache_dir().unwrap();
+
+        let mut cache = create_test_cache();
+        cache.metadata.last_updated = Utc::now() - Duration::hours(2);
+        let updater_temp = CacheUpdater::new(config.clone(), false);
+        updater_temp.save_cache(&cache).unwrap();
+
+        // Fresh enough: the API is never touched
+        let updater = CacheUpdater::new_with_client(config.clone(), false, MockGitHubClient::new());
+        assert!(
+            updater
+                .update_if_stale(Duration::hours(3))
+                .unwrap()
+                .is_none()
+        );
+
+        let mut mock = MockGitHubClient::new();
+        mock.expect_check_auth().times(1).returning(|| Ok(()));
+        mock.expect_check_rate_limit()
+            .times(1)
+            .returning(|| Ok(3000));
+        mock.expect_fetch_gists()
+            .times(1)
+            .withf(|since| since.is_some())
+            .returning(|_| Ok(vec![]));
+
+        let updater = CacheUpdater::new_with_client(config, false, mock);
+        assert!(
+            updater
+                .update_if_stale(Duration::hours(1))
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            Utc::now() - updater.load_cache().unwrap().metadata.last_updated < Duration::hours(1)
+        );
     }
 
     #[test]
@@ -633,6 +1093,7 @@ mod tests {
                     filename: "old.sh".to_string(),
                     language: Some("Shell".to_string()),
                     size: 100,
+                    revision: None,
                 }],
                 updated_at: old_time,
                 public: true,
@@ -672,6 +1133,7 @@ mod tests {
                         filename: "new.sh".to_string(),
                         language: Some("Shell".to_string()),
                         size: 120,
+                        raw_url: None,
                     },
                 )]),
                 updated_at: new_time,
@@ -786,6 +1248,7 @@ mod tests {
                     filename: "test.sh".to_string(),
                     language: Some("Shell".to_string()),
                     size: 100,
+                    revision: None,
                 }],
                 updated_at: old_time,
                 public: true,
@@ -822,6 +1285,7 @@ mod tests {
                         filename: "test.sh".to_string(),
                         language: Some("Shell".to_string()),
                         size: 150,
+                        raw_url: None,
                     },
                 )]),
                 updated_at: new_time,
@@ -864,6 +1328,7 @@ mod tests {
                         filename: "new.sh".to_string(),
                         language: Some("Shell".to_string()),
                         size: 100,
+                        raw_url: None,
                     },
                 )]),
                 updated_at: Utc::now(),
@@ -878,4 +1343,233 @@ mod tests {
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_save_cache_is_atomic() {
+        let config = create_test_config();
+        config.ensure_cache_dir().unwrap();
+        let updater = CacheUpdater::new(config.clone(), false);
+
+        updater.save_cache(&create_test_cache()).unwrap();
+
+        // No temp file is left behind after the rename
+        assert!(config.cache_file.exists());
+        assert!(
+            fs::read_dir(&config.cache_dir)
+                .unwrap()
+                .flatten()
+                .all(|e| !crate::cache::storage::is_temp_file(&e.file_name().to_string_lossy()))
+        );
+    }
+
+    #[test]
+    fn test_concurrent_updaters_do_not_lose_updates() {
+        let config = create_test_config();
+        config.ensure_cache_dir().unwrap();
+
+        CacheUpdater::new(config.clone(), false)
+            .save_cache(&create_test_cache())
+            .unwrap();
+
+        let handles: Vec<_> = (0..4)
+            .map(|n| {
+                let config = config.clone();
+                std::thread::spawn(move || {
+                    let mut mock = MockGitHubClient::new();
+                    mock.expect_check_auth().returning(|| Ok(()));
+                    mock.expect_check_rate_limit().returning(|| Ok(5000));
+                    mock.expect_fetch_gists().returning(move |_| {
+                        Ok(vec![crate::cache::types::GitHubGist {
+                            id: format!("concurrent{}", n),
+                            description: None,
+                            files: HashMap::new(),
+                            updated_at: Utc::now(),
+                            public: true,
+                            html_url: format!("https://gist.github.com/concurrent{}", n),
+                        }])
+                    });
+
+                    CacheUpdater::new_with_client(config, false, mock).update(false)
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert!(handle.join().unwrap().is_ok());
+        }
+
+        // Every differential update saw the previous one's result
+        let loaded = CacheUpdater::new(config, false).load_cache().unwrap();
+        assert_eq!(loaded.gists.len(), 5);
+    }
+
+    #[test]
+    fn test_update_fails_fast_when_locked() {
+        let mut config = create_test_config();
+        config.ensure_cache_dir().unwrap();
+        config.user_config.cache = Some(crate::config::CacheConfig {
+            lock_timeout: Some(0),
+            ..Default::default()
+        });
+
+        let _held = CacheLock::acquire(&config).unwrap();
+
+        let mock = MockGitHubClient::new();
+        let result = CacheUpdater::new_with_client(config, false, mock).update(false);
+        assert!(matches!(
+            result,
+            Err(crate::error::GistCacheError::CacheLocked(_))
+        ));
+    }
+
+    #[test]
+    fn test_update_with_sqlite_backend() {
+        let mut config = create_test_config();
+        config.ensure_cache_dir().unwrap();
+        config.user_config.cache = Some(crate::config::CacheConfig {
+            backend: Some(crate::config::StorageBackend::Sqlite),
+            ..Default::default()
+        });
+
+        let mut mock = MockGitHubClient::new();
+        mock.expect_check_auth().returning(|| Ok(()));
+        mock.expect_check_rate_limit().returning(|| Ok(5000));
+        mock.expect_get_user()
+            .returning(|| Ok("mockuser".to_string()));
+        mock.expect_fetch_gists().returning(|_| {
+            Ok(vec![crate::cache::types::GitHubGist {
+                id: "sqlite123".to_string(),
+                description: Some("Stored in SQLite".to_string()),
+                files: HashMap::from([(
+                    "run.py".to_string(),
+                    GitHubFile {
+                        filename: "run.py".to_string(),
+                        language: Some("Python".to_string()),
+                        size: 10,
+                        raw_url: None,
+                    },
+                )]),
+                updated_at: Utc::now(),
+                public: true,
+                html_url: "https://gist.github.com/sqlite123".to_string(),
+            }])
+        });
+
+        let updater = CacheUpdater::new_with_client(config.clone(), false, mock);
+        updater.update(true).unwrap();
+
+        // Metadata goes to cache.db only
+        assert!(config.cache_db_file().exists());
+        assert!(!config.cache_file.exists());
+
+        let store = open_metadata_store(&config).unwrap();
+        let gist = store.find_by_id("sqlite123").unwrap().unwrap();
+        assert_eq!(gist.description.as_deref(), Some("Stored in SQLite"));
+        assert_eq!(store.find_by_filename("RUN").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_update_invalidates_only_changed_files() {
+        let config = create_test_config();
+        config.ensure_cache_dir().unwrap();
+
+        let rev = |c: char| c.to_string().repeat(40);
+        let raw_url = |r: &str, name: &str| {
+            Some(format!(
+                "https://gist.githubusercontent.com/u/g1/raw/{r}/{name}"
+            ))
+        };
+        let file =
+            |name: &str, size: usize, revision: Option<String>| crate::cache::types::GistFile {
+                filename: name.to_string(),
+                language: None,
+                size,
+                revision,
+            };
+
+        let old_time = Utc::now() - chrono::Duration::hours(1);
+        let existing_cache = GistCache {
+            metadata: CacheMetadata {
+                last_updated: old_time,
+                total_count: 1,
+                github_user: "testuser".to_string(),
+            },
+            gists: vec![GistInfo {
+                id: "g1".to_string(),
+                description: None,
+                files: vec![
+                    file("data.csv", 8, Some(rev('a'))),
+                    file("run.sh", 8, Some(rev('b'))),
+                    file("legacy.txt", 6, None),
+                ],
+                updated_at: old_time,
+                public: true,
+                html_url: "https://gist.github.com/g1".to_string(),
+            }],
+        };
+        CacheUpdater::new(config.clone(), false)
+            .save_cache(&existing_cache)
+            .unwrap();
+
+        let content_cache = ContentCache::new(config.contents_dir.clone());
+        content_cache.write("g1", "data.csv", "a,b\n1,2\n").unwrap();
+        content_cache.write("g1", "run.sh", "echo old").unwrap();
+        content_cache.write("g1", "legacy.txt", "legacy").unwrap();
+
+        // Only run.sh changed; the revision of data.csv is the same
+        let new_files: Vec<GitHubFile> = vec![
+            GitHubFile {
+                filename: "data.csv".to_string(),
+                language: None,
+                size: 8,
+                raw_url: raw_url(&rev('a'), "data.csv"),
+            },
+            GitHubFile {
+                filename: "run.sh".to_string(),
+                language: None,
+                size: 8,
+                raw_url: raw_url(&rev('c'), "run.sh"),
+            },
+            GitHubFile {
+                filename: "legacy.txt".to_string(),
+                language: None,
+                size: 6,
+                raw_url: raw_url(&rev('d'), "legacy.txt"),
+            },
+        ];
+
+        let mut mock = MockGitHubClient::new();
+        mock.expect_check_auth().returning(|| Ok(()));
+        mock.expect_check_rate_limit().returning(|| Ok(5000));
+        mock.expect_fetch_gists().returning(move |_| {
+            Ok(vec![crate::cache::types::GitHubGist {
+                id: "g1".to_string(),
+                description: None,
+                files: new_files
+                    .iter()
+                    .map(|f| (f.filename.clone(), f.clone()))
+                    .collect(),
+                updated_at: Utc::now(),
+                public: true,
+                html_url: "https://gist.github.com/g1".to_string(),
+            }])
+        });
+
+        let updater = CacheUpdater::new_with_client(config.clone(), false, mock);
+        updater.update(false).unwrap();
+
+        assert_eq!(content_cache.read("g1", "data.csv").unwrap(), "a,b\n1,2\n");
+        assert!(!content_cache.exists("g1", "run.sh"));
+        // No recorded revision, so it can't be proven unchanged
+        assert!(!content_cache.exists("g1", "legacy.txt"));
+
+        // The new revisions are recorded for the next update
+        let loaded = updater.load_cache().unwrap();
+        let run_sh = loaded.gists[0]
+            .files
+            .iter()
+            .find(|f| f.filename == "run.sh")
+            .unwrap();
+        assert_eq!(run_sh.revision, Some(rev('c')));
+    }
 }
@@ -0,0 +1,429 @@
+//! Long-running cache refresh (`watch`)
+//!
+//! Runs a differential update every interval until SIGTERM or Ctrl-C.
+//! `watch.pid` stays locked while the daemon runs, so only one watcher works
+//! on a cache directory, and `watch.json` keeps the last cycle for `status`.
+
+use crate::cache::storage::write_atomic;
+use crate::cache::update::CacheUpdater;
+use crate::config::{Config, format_duration};
+use crate::error::{GistCacheError, Result};
+use crate::github::GitHubClient;
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use fs4::fs_std::FileExt;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+/// How `watch` refreshes the cache
+#[derive(Debug, Clone, Copy)]
+pub struct WatchOptions {
+    /// Time between the start of two cycles
+    pub interval: Duration,
+    /// Also fetch the files of added and modified gists
+    pub prefetch: bool,
+}
+
+/// Outcome of one refresh cycle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchCycle {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub added: usize,
+    pub modified: usize,
+    pub removed: usize,
+    pub invalidated: usize,
+    pub prefetched: usize,
+    /// Why the cycle failed; the daemon keeps running and retries next cycle
+    pub error: Option<String>,
+}
+
+impl fmt::Display for WatchCycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            Some(error) => write!(f, "failed: {}", error),
+            None => write!(
+                f,
+                "+{} ~{} -{}, {} files invalidated, {} files prefetched",
+                self.added, self.modified, self.removed, self.invalidated, self.prefetched
+            ),
+        }
+    }
+}
+
+/// State of the current (or last) `watch` process, stored in `watch.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchState {
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    /// Set on clean shutdown; a killed daemon leaves it empty
+    pub stopped_at: Option<DateTime<Utc>>,
+    pub interval_secs: u64,
+    pub prefetch: bool,
+    pub cycles: u64,
+    pub last_cycle: Option<WatchCycle>,
+}
+
+impl WatchState {
+    /// State left by the last `watch` process, if any
+    ///
+    /// # Self-healing Principle
+    /// An unreadable state file is treated as absent
+    pub fn load(config: &Config) -> Option<Self> {
+        let data = fs::read_to_string(config.watch_state_file()).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    fn save(&self, config: &Config) -> Result<()> {
+        write_atomic(
+            &config.watch_state_file(),
+            &serde_json::to_string_pretty(self)?,
+        )
+    }
+}
+
+/// `watch.pid`, locked for as long as the daemon runs
+///
+/// The lock (not the file's existence) tells whether a daemon is running, so
+/// a PID file left behind by a killed process never blocks a new one. The
+/// file is never removed: another daemon may already be waiting on it, and
+/// one created in its place would let a third daemon lock a different file.
+pub struct PidFile {
+    file: File,
+}
+
+impl PidFile {
+    /// Lock `watch.pid` and write the current PID into it
+    ///
+    /// # Errors
+    /// Returns `WatchRunning` if another daemon holds the lock
+    pub fn acquire(config: &Config) -> Result<Self> {
+        let path = config.watch_pid_file();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+
+        if !file.try_lock_exclusive()? {
+            let pid = WatchState::load(config)
+                .map(|state| state.pid.to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            return Err(GistCacheError::WatchRunning(pid));
+        }
+
+        file.set_len(0)?;
+        writeln!(file, "{}", std::process::id())?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for PidFile {
+    /// Clear the PID, still under the lock, which closing the file releases
+    fn drop(&mut self) {
+        let _ = self.file.set_len(0);
+    }
+}
+
+/// Whether a `watch` daemon currently holds the PID file lock
+pub fn is_running(config: &Config) -> bool {
+    let Ok(file) = OpenOptions::new().write(true).open(config.watch_pid_file()) else {
+        return false;
+    };
+    // Taking the lock means nobody held it; it is released when `file` drops
+    matches!(file.try_lock_exclusive(), Ok(false))
+}
+
+/// Run one differential update (plus prefetch) and summarize it
+///
+/// Errors are recorded in the cycle instead of being returned, so a network
+/// outage does not stop the daemon.
+pub fn run_cycle<C: GitHubClient>(updater: &CacheUpdater<C>, prefetch: bool) -> WatchCycle {
+    let started_at = Utc::now();
+    let mut cycle = WatchCycle {
+        started_at,
+        finished_at: started_at,
+        added: 0,
+        modified: 0,
+        removed: 0,
+        invalidated: 0,
+        prefetched: 0,
+        error: None,
+    };
+
+    let result = updater.update(false).and_then(|report| {
+        cycle.added = report.added.len();
+        cycle.modified = report.modified.len();
+        cycle.removed = report.removed.len();
+        cycle.invalidated = report.invalidated.len();
+        if prefetch {
+            cycle.prefetched = updater.prefetch(&report)?;
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        cycle.error = Some(e.to_string());
+    }
+
+    cycle.finished_at = Utc::now();
+    cycle
+}
+
+/// Refresh the cache every `options.interval` until SIGTERM or Ctrl-C
+///
+/// The first cycle runs immediately. A signal received during a cycle lets
+/// the cycle finish (it holds the cache lock) before the daemon exits.
+pub fn watch(config: Config, options: WatchOptions) -> Result<()> {
+    config.ensure_cache_dir()?;
+    let _pid_file = PidFile::acquire(&config)?;
+
+    let mut state = WatchState {
+        pid: std::process::id(),
+        started_at: Utc::now(),
+        stopped_at: None,
+        interval_secs: options.interval.as_secs(),
+        prefetch: options.prefetch,
+        cycles: 0,
+        last_cycle: None,
+    };
+    state.save(&config)?;
+
+    println!(
+        "{}",
+        format!(
+            "Watching for Gist updates every {} (PID {})",
+            format_duration(state.interval_secs),
+            state.pid
+        )
+        .cyan()
+        .bold()
+    );
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let cycle_config = config.clone();
+    let result = runtime.block_on(watch_loop(
+        &config,
+        options.interval,
+        &mut state,
+        shutdown_signal(),
+        move || {
+            let updater = CacheUpdater::new(cycle_config.clone(), false).quiet(true);
+            run_cycle(&updater, options.prefetch)
+        },
+    ));
+
+    state.stopped_at = Some(Utc::now());
+    state.save(&config)?;
+    println!("{}", "Watch stopped".green());
+
+    result
+}
+
+/// Run `cycle` on every tick until `shutdown` completes
+async fn watch_loop<F>(
+    config: &Config,
+    interval: Duration,
+    state: &mut WatchState,
+    shutdown: impl Future<Output = std::io::Result<()>>,
+    cycle: F,
+) -> Result<()>
+where
+    F: Fn() -> WatchCycle + Clone + Send + 'static,
+{
+    tokio::pin!(shutdown);
+    let mut ticker = tokio::time::interval(interval);
+    // A cycle longer than the interval postpones the next one instead of
+    // starting a burst of catch-up cycles
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            result = &mut shutdown => return result.map_err(Into::into),
+            _ = ticker.tick() => {}
+        }
+
+        // Updates block on `gh`, keep them off the runtime thread
+        let run = cycle.clone();
+        let result = tokio::task::spawn_blocking(run)
+            .await
+            .map_err(std::io::Error::other)?;
+
+        state.cycles += 1;
+        let line = format!(
+            "[{}] cycle {}: {}",
+            result.finished_at.format("%Y-%m-%d %H:%M:%S"),
+            state.cycles,
+            result
+        );
+        if result.error.is_some() {
+            eprintln!("{}", line.yellow());
+        } else {
+            println!("{}", line);
+        }
+
+        state.last_cycle = Some(result);
+        if let Err(e) = state.save(config) {
+            eprintln!(
+                "{}",
+                format!("Warning: Failed to write watch state: {}", e).yellow()
+            );
+        }
+    }
+}
+
+/// Completes on SIGTERM (Unix) or Ctrl-C
+async fn shutdown_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut sigterm = signal(SignalKind::terminate())?;
+        tokio::select! {
+            _ = sigterm.recv() => Ok(()),
+            result = tokio::signal::ctrl_c() => result,
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::types::GitHubGist;
+    use crate::github::MockGitHubClient;
+    use tempfile::TempDir;
+
+    fn test_state() -> WatchState {
+        WatchState {
+            pid: std::process::id(),
+            started_at: Utc::now(),
+            stopped_at: None,
+            interval_secs: 600,
+            prefetch: false,
+            cycles: 0,
+            last_cycle: None,
+        }
+    }
+
+    #[test]
+    fn test_pid_file_is_exclusive() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+        assert!(!is_running(&config));
+
+        test_state().save(&config).unwrap();
+        let pid_file = PidFile::acquire(&config).unwrap();
+        assert!(is_running(&config));
+        assert_eq!(
+            fs::read_to_string(config.watch_pid_file()).unwrap().trim(),
+            std::process::id().to_string()
+        );
+
+        let result = PidFile::acquire(&config);
+        assert!(
+            matches!(result, Err(GistCacheError::WatchRunning(pid)) if pid == std::process::id().to_string())
+        );
+
+        // Clean shutdown clears the PID but keeps the file; a stale one
+        // would not block either
+        drop(pid_file);
+        assert_eq!(fs::read_to_string(config.watch_pid_file()).unwrap(), "");
+        assert!(!is_running(&config));
+        fs::write(config.watch_pid_file(), "99999\n").unwrap();
+        assert!(!is_running(&config));
+        assert!(PidFile::acquire(&config).is_ok());
+    }
+
+    #[test]
+    fn test_run_cycle_records_changes_and_errors() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+
+        let gist: GitHubGist = serde_json::from_value(serde_json::json!({
+            "id": "new1",
+            "description": "New gist",
+            "files": {"run.sh": {"filename": "run.sh", "language": "Shell", "size": 8}},
+            "updated_at": "2026-01-01T00:00:00Z",
+            "public": true,
+            "html_url": "https://gist.github.com/new1"
+        }))
+        .unwrap();
+
+        let mut mock = MockGitHubClient::new();
+        mock.expect_check_auth().returning(|| Ok(()));
+        mock.expect_check_rate_limit().returning(|| Ok(5000));
+        mock.expect_get_user()
+            .returning(|| Ok("testuser".to_string()));
+        mock.expect_fetch_gists()
+            .returning(move |_| Ok(vec![gist.clone()]));
+        mock.expect_fetch_gist_content()
+            .returning(|_, _| Ok("echo new".to_string()));
+
+        let updater = CacheUpdater::new_with_client(config.clone(), false, mock).quiet(true);
+        let cycle = run_cycle(&updater, true);
+        assert_eq!(cycle.error, None);
+        assert_eq!(cycle.added, 1);
+        assert_eq!(cycle.prefetched, 1);
+        assert!(cycle.to_string().starts_with("+1 ~0 -0"));
+
+        let mut failing = MockGitHubClient::new();
+        failing
+            .expect_check_auth()
+            .returning(|| Err(GistCacheError::NotAuthenticated));
+        let updater = CacheUpdater::new_with_client(config, false, failing).quiet(true);
+        let cycle = run_cycle(&updater, true);
+        assert!(cycle.error.unwrap().contains("not authenticated"));
+    }
+
+    #[tokio::test]
+    async fn test_watch_loop_runs_cycles_until_shutdown() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+        let mut state = test_state();
+
+        let cycle = || {
+            let now = Utc::now();
+            WatchCycle {
+                started_at: now,
+                finished_at: now,
+                added: 0,
+                modified: 1,
+                removed: 0,
+                invalidated: 0,
+                prefetched: 0,
+                error: None,
+            }
+        };
+        let shutdown = async {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            Ok(())
+        };
+
+        watch_loop(
+            &config,
+            Duration::from_millis(100),
+            &mut state,
+            shutdown,
+            cycle,
+        )
+        .await
+        .unwrap();
+
+        // Immediately, then every 100ms until the shutdown after 250ms
+        assert!(state.cycles >= 2);
+        let saved = WatchState::load(&config).unwrap();
+        assert_eq!(saved.cycles, state.cycles);
+        assert_eq!(saved.last_cycle.unwrap().modified, 1);
+    }
+}
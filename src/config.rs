@@ -1,7 +1,7 @@
 use crate::error::{GistCacheError, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
 /// Supported file extensions for interpreter configuration
 pub const SUPPORTED_EXTENSIONS: &[&str] = &["py", "rb", "js", "ts", "sh", "php", "pl", "ps1", "*"];
@@ -57,6 +57,284 @@ pub struct ExecutionConfig {
 pub struct CacheConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub retention_days: Option<u32>,
+    /// Seconds to wait for another process holding the cache lock (0 = fail fast)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_timeout: Option<u64>,
+    /// Upper bound for cached contents in bytes (LRU eviction)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+    /// Where metadata and contents are stored (`json` or `sqlite`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<StorageBackend>,
+    /// Seconds after which `run` refreshes the metadata cache first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u64>,
+}
+
+/// Parse a byte size such as `1048576`, `512K`, `500MB` or `1.5G`
+/// (binary units, case-insensitive)
+pub fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1024,
+        "M" | "MB" | "MIB" => 1024 * 1024,
+        "G" | "GB" | "GIB" => 1024 * 1024 * 1024,
+        _ => {
+            return Err(GistCacheError::Config(format!(
+                "Invalid size value: {}",
+                value
+            )));
+        }
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| GistCacheError::Config(format!("Invalid size value: {}", value)))?;
+
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parse a duration such as `3600`, `90s`, `30m`, `6h`, `1d` or `2w` into seconds
+pub fn parse_duration(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(GistCacheError::Config(format!(
+                "Invalid duration value: {}",
+                value
+            )));
+        }
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| GistCacheError::Config(format!("Invalid duration value: {}", value)))?;
+
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| GistCacheError::Config(format!("Duration is too large: {}", value)))
+}
+
+/// Format seconds with the largest unit that divides them evenly (`6h`, `90m`)
+pub fn format_duration(secs: u64) -> String {
+    const UNITS: [(u64, &str); 4] = [
+        (7 * 24 * 60 * 60, "w"),
+        (24 * 60 * 60, "d"),
+        (60 * 60, "h"),
+        (60, "m"),
+    ];
+    UNITS
+        .iter()
+        .find(|(size, _)| secs > 0 && secs % size == 0)
+        .map(|(size, unit)| format!("{}{}", secs / size, unit))
+        .unwrap_or_else(|| format!("{}s", secs))
+}
+
+/// Storage backend for the metadata cache and cached file contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// `cache.json` plus one file per gist file under `contents/`
+    #[default]
+    Json,
+    /// A single `cache.db` SQLite database
+    Sqlite,
+}
+
+impl StorageBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageBackend::Json => "json",
+            StorageBackend::Sqlite => "sqlite",
+        }
+    }
+}
+
+impl std::str::FromStr for StorageBackend {
+    type Err = GistCacheError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(StorageBackend::Json),
+            "sqlite" => Ok(StorageBackend::Sqlite),
+            other => Err(GistCacheError::Config(format!(
+                "Invalid backend value: {} (expected 'json' or 'sqlite')",
+                other
+            ))),
+        }
+    }
+}
+
+/// Order of the interactive picker's list (`picker.sort`); `s` cycles it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PickerSort {
+    /// Most recently updated first
+    Updated,
+    /// Description (or filename), alphabetically
+    Name,
+    /// Primary language, alphabetically
+    Language,
+    /// Largest total file size first
+    Size,
+    /// Most runs first
+    Used,
+}
+
+impl PickerSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PickerSort::Updated => "updated",
+            PickerSort::Name => "name",
+            PickerSort::Language => "language",
+            PickerSort::Size => "size",
+            PickerSort::Used => "used",
+        }
+    }
+
+    /// Name shown in the picker footer
+    pub fn label(&self) -> &'static str {
+        match self {
+            PickerSort::Updated => "recently updated",
+            PickerSort::Name => "name",
+            PickerSort::Language => "language",
+            PickerSort::Size => "size",
+            PickerSort::Used => "most used",
+        }
+    }
+
+    /// The order after this one when cycling
+    pub fn next(self) -> Self {
+        match self {
+            PickerSort::Updated => PickerSort::Name,
+            PickerSort::Name => PickerSort::Language,
+            PickerSort::Language => PickerSort::Size,
+            PickerSort::Size => PickerSort::Used,
+            PickerSort::Used => PickerSort::Updated,
+        }
+    }
+}
+
+impl std::str::FromStr for PickerSort {
+    type Err = GistCacheError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "updated" => Ok(PickerSort::Updated),
+            "name" => Ok(PickerSort::Name),
+            "language" => Ok(PickerSort::Language),
+            "size" => Ok(PickerSort::Size),
+            "used" => Ok(PickerSort::Used),
+            other => Err(GistCacheError::Config(format!(
+                "Invalid sort value: {} (expected 'updated', 'name', 'language', 'size' or 'used')",
+                other
+            ))),
+        }
+    }
+}
+
+/// Where the picker shows the live preview of the highlighted gist
+/// (`picker.preview`); `p` cycles it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewLayout {
+    /// No live preview (Space still opens the full-screen one)
+    #[default]
+    Off,
+    /// Preview pane to the right of the list
+    Right,
+    /// Preview pane below the list
+    Bottom,
+}
+
+impl PreviewLayout {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PreviewLayout::Off => "off",
+            PreviewLayout::Right => "right",
+            PreviewLayout::Bottom => "bottom",
+        }
+    }
+
+    /// The layout after this one when cycling
+    pub fn next(self) -> Self {
+        match self {
+            PreviewLayout::Off => PreviewLayout::Right,
+            PreviewLayout::Right => PreviewLayout::Bottom,
+            PreviewLayout::Bottom => PreviewLayout::Off,
+        }
+    }
+}
+
+impl std::str::FromStr for PreviewLayout {
+    type Err = GistCacheError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "off" => Ok(PreviewLayout::Off),
+            "right" => Ok(PreviewLayout::Right),
+            "bottom" => Ok(PreviewLayout::Bottom),
+            other => Err(GistCacheError::Config(format!(
+                "Invalid preview value: {} (expected 'off', 'right' or 'bottom')",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PickerConfig {
+    /// List order (unset = search result order)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<PickerSort>,
+    /// Group the list under language headers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<bool>,
+    /// Live preview layout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<PreviewLayout>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SearchConfig {
+    /// Order search results by frecency (execution history); on by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frecency: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DisplayConfig {
+    /// Syntax highlighting theme: a bundled one or a `.tmTheme` file in the
+    /// `themes` directory next to the config file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+}
+
+/// A name that `run <name>` resolves straight to one gist, without a search
+/// or the picker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GistAlias {
+    pub gist: String,
+    /// Interpreter used unless the command line names one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interpreter: Option<String>,
+    /// Script arguments, passed before those given on the command line
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -67,6 +345,14 @@ pub struct UserConfig {
     pub execution: Option<ExecutionConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cache: Option<CacheConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub picker: Option<PickerConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<SearchConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplayConfig>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, GistAlias>,
 }
 
 #[derive(Clone)]
@@ -217,12 +503,83 @@ impl Config {
                         GistCacheError::Config(format!("Invalid number value: {}", value))
                     })?;
                     if self.user_config.cache.is_none() {
-                        self.user_config.cache = Some(CacheConfig {
-                            retention_days: None,
-                        });
+                        self.user_config.cache = Some(CacheConfig::default());
                     }
                     self.user_config.cache.as_mut().unwrap().retention_days = Some(days);
                 }
+                "cache.lock_timeout" => {
+                    let secs = value.parse::<u64>().map_err(|_| {
+                        GistCacheError::Config(format!("Invalid number value: {}", value))
+                    })?;
+                    if self.user_config.cache.is_none() {
+                        self.user_config.cache = Some(CacheConfig::default());
+                    }
+                    self.user_config.cache.as_mut().unwrap().lock_timeout = Some(secs);
+                }
+                "cache.max_size" => {
+                    let bytes = parse_size(value)?;
+                    if self.user_config.cache.is_none() {
+                        self.user_config.cache = Some(CacheConfig::default());
+                    }
+                    self.user_config.cache.as_mut().unwrap().max_size = Some(bytes);
+                }
+                "cache.backend" => {
+                    let backend = value.parse::<StorageBackend>()?;
+                    if self.user_config.cache.is_none() {
+                        self.user_config.cache = Some(CacheConfig::default());
+                    }
+                    self.user_config.cache.as_mut().unwrap().backend = Some(backend);
+                }
+                "cache.max_age" => {
+                    let secs = parse_duration(value)?;
+                    if self.user_config.cache.is_none() {
+                        self.user_config.cache = Some(CacheConfig::default());
+                    }
+                    self.user_config.cache.as_mut().unwrap().max_age = Some(secs);
+                }
+                "picker.sort" => {
+                    let sort = value.parse::<PickerSort>()?;
+                    self.user_config
+                        .picker
+                        .get_or_insert_with(PickerConfig::default)
+                        .sort = Some(sort);
+                }
+                "picker.group" => {
+                    let bool_value = value.parse::<bool>().map_err(|_| {
+                        GistCacheError::Config(format!("Invalid boolean value: {}", value))
+                    })?;
+                    self.user_config
+                        .picker
+                        .get_or_insert_with(PickerConfig::default)
+                        .group = Some(bool_value);
+                }
+                "search.frecency" => {
+                    let bool_value = value.parse::<bool>().map_err(|_| {
+                        GistCacheError::Config(format!("Invalid boolean value: {}", value))
+                    })?;
+                    self.user_config
+                        .search
+                        .get_or_insert_with(SearchConfig::default)
+                        .frecency = Some(bool_value);
+                }
+                "display.theme" => {
+                    if value.trim().is_empty() {
+                        return Err(GistCacheError::Config(
+                            "Theme name cannot be empty".to_string(),
+                        ));
+                    }
+                    self.user_config
+                        .display
+                        .get_or_insert_with(DisplayConfig::default)
+                        .theme = Some(value.to_string());
+                }
+                "picker.preview" => {
+                    let layout = value.parse::<PreviewLayout>()?;
+                    self.user_config
+                        .picker
+                        .get_or_insert_with(PickerConfig::default)
+                        .preview = Some(layout);
+                }
                 _ => {
                     return Err(GistCacheError::Config(format!(
                         "Unknown config key: {}",
@@ -271,6 +628,55 @@ impl Config {
                     .as_ref()?
                     .retention_days
                     .map(|v| v.to_string()),
+                "cache.lock_timeout" => self
+                    .user_config
+                    .cache
+                    .as_ref()?
+                    .lock_timeout
+                    .map(|v| v.to_string()),
+                "cache.max_size" => self
+                    .user_config
+                    .cache
+                    .as_ref()?
+                    .max_size
+                    .map(|v| v.to_string()),
+                "cache.backend" => self
+                    .user_config
+                    .cache
+                    .as_ref()?
+                    .backend
+                    .map(|v| v.as_str().to_string()),
+                "cache.max_age" => self
+                    .user_config
+                    .cache
+                    .as_ref()?
+                    .max_age
+                    .map(|v| v.to_string()),
+                "picker.sort" => self
+                    .user_config
+                    .picker
+                    .as_ref()?
+                    .sort
+                    .map(|v| v.as_str().to_string()),
+                "picker.group" => self
+                    .user_config
+                    .picker
+                    .as_ref()?
+                    .group
+                    .map(|v| v.to_string()),
+                "picker.preview" => self
+                    .user_config
+                    .picker
+                    .as_ref()?
+                    .preview
+                    .map(|v| v.as_str().to_string()),
+                "search.frecency" => self
+                    .user_config
+                    .search
+                    .as_ref()?
+                    .frecency
+                    .map(|v| v.to_string()),
+                "display.theme" => self.user_config.display.as_ref()?.theme.clone(),
                 _ => None,
             }
         }
@@ -301,8 +707,163 @@ impl Config {
         Ok(())
     }
 
+    /// Configured storage backend (`json` unless `cache.backend` says otherwise)
+    pub fn storage_backend(&self) -> StorageBackend {
+        self.user_config
+            .cache
+            .as_ref()
+            .and_then(|c| c.backend)
+            .unwrap_or_default()
+    }
+
+    /// Size limit for cached contents (`cache.max_size`), in bytes
+    pub fn cache_max_size(&self) -> Option<u64> {
+        self.user_config.cache.as_ref().and_then(|c| c.max_size)
+    }
+
+    /// How old the metadata cache may get before `run` refreshes it (`cache.max_age`)
+    pub fn cache_max_age(&self) -> Option<chrono::Duration> {
+        self.user_config
+            .cache
+            .as_ref()
+            .and_then(|c| c.max_age)
+            // Ages beyond what a TimeDelta holds mean "never refresh"
+            .map(|secs| {
+                i64::try_from(secs)
+                    .ok()
+                    .and_then(chrono::TimeDelta::try_seconds)
+                    .unwrap_or(chrono::TimeDelta::MAX)
+            })
+    }
+
+    /// Saved picker order (`picker.sort`; `None` keeps the search result order)
+    pub fn picker_sort(&self) -> Option<PickerSort> {
+        self.user_config.picker.as_ref().and_then(|p| p.sort)
+    }
+
+    /// Whether the picker groups the list by language (`picker.group`)
+    pub fn picker_group(&self) -> bool {
+        self.user_config
+            .picker
+            .as_ref()
+            .and_then(|p| p.group)
+            .unwrap_or(false)
+    }
+
+    /// Live preview layout of the picker (`picker.preview`, off by default)
+    pub fn picker_preview(&self) -> PreviewLayout {
+        self.user_config
+            .picker
+            .as_ref()
+            .and_then(|p| p.preview)
+            .unwrap_or_default()
+    }
+
+    /// Configured highlighting theme (`display.theme`; `None` uses the default)
+    pub fn display_theme(&self) -> Option<&str> {
+        self.user_config
+            .display
+            .as_ref()
+            .and_then(|d| d.theme.as_deref())
+    }
+
+    /// Directory of custom `.tmTheme` files, next to the config file
+    pub fn themes_dir(&self) -> PathBuf {
+        self.config_dir().join("themes")
+    }
+
+    /// Directory of custom `.sublime-syntax` files, next to the config file
+    pub fn syntaxes_dir(&self) -> PathBuf {
+        self.config_dir().join("syntaxes")
+    }
+
+    fn config_dir(&self) -> PathBuf {
+        self.config_file
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+
+    /// Whether search results are ordered by frecency (`search.frecency`)
+    pub fn frecency_enabled(&self) -> bool {
+        self.user_config
+            .search
+            .as_ref()
+            .and_then(|s| s.frecency)
+            .unwrap_or(true)
+    }
+
+    /// The alias `name`, if defined
+    pub fn alias(&self, name: &str) -> Option<&GistAlias> {
+        self.user_config.aliases.get(name)
+    }
+
+    /// All aliases, by name
+    pub fn aliases(&self) -> &BTreeMap<String, GistAlias> {
+        &self.user_config.aliases
+    }
+
+    /// Define (or replace) alias `name` and save the config
+    pub fn set_alias(&mut self, name: &str, alias: GistAlias) -> Result<()> {
+        let valid = name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if name.is_empty() || name.starts_with('-') || !valid {
+            return Err(GistCacheError::InvalidAlias(format!(
+                "'{}' (use letters, digits, '-', '_' and '.', not starting with '-')",
+                name
+            )));
+        }
+        self.user_config.aliases.insert(name.to_string(), alias);
+        self.save_user_config()
+    }
+
+    /// Remove alias `name` and save the config
+    pub fn remove_alias(&mut self, name: &str) -> Result<GistAlias> {
+        let alias = self
+            .user_config
+            .aliases
+            .remove(name)
+            .ok_or_else(|| GistCacheError::AliasNotFound(name.to_string()))?;
+        self.save_user_config()?;
+        Ok(alias)
+    }
+
+    /// Path of the execution history (frecency ranking)
+    pub fn history_file(&self) -> PathBuf {
+        self.cache_dir.join("history.jsonl")
+    }
+
+    /// Path of the files chosen to run of multi-file gists
+    pub fn main_files_file(&self) -> PathBuf {
+        self.cache_dir.join("main_files.json")
+    }
+
+    /// Path of the journal of cache changes (`cache log`)
+    pub fn journal_file(&self) -> PathBuf {
+        self.cache_dir.join("journal.jsonl")
+    }
+
+    /// Path of the PID file locked by a running `watch` daemon
+    pub fn watch_pid_file(&self) -> PathBuf {
+        self.cache_dir.join("watch.pid")
+    }
+
+    /// Path of the `watch` daemon's state (last cycle), shown by `status`
+    pub fn watch_state_file(&self) -> PathBuf {
+        self.cache_dir.join("watch.json")
+    }
+
+    /// Path of the SQLite database used by the `sqlite` backend
+    pub fn cache_db_file(&self) -> PathBuf {
+        self.cache_dir.join("cache.db")
+    }
+
     pub fn cache_exists(&self) -> bool {
-        self.cache_file.exists()
+        match self.storage_backend() {
+            StorageBackend::Json => self.cache_file.exists(),
+            StorageBackend::Sqlite => self.cache_db_file().exists(),
+        }
     }
 }
 
@@ -312,6 +873,22 @@ impl Default for Config {
     }
 }
 
+#[cfg(test)]
+impl Config {
+    /// Config keeping everything (cache, downloads, `config.toml`) under
+    /// `dir`, with default settings
+    pub(crate) fn for_test(dir: &Path) -> Self {
+        Self {
+            cache_dir: dir.to_path_buf(),
+            cache_file: dir.join("cache.json"),
+            contents_dir: dir.join("contents"),
+            download_dir: dir.join("downloads"),
+            config_file: dir.join("config.toml"),
+            user_config: UserConfig::default(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -414,6 +991,172 @@ mod tests {
         let _ = fs::remove_dir_all(&test_cache_dir);
     }
 
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1048576").unwrap(), 1_048_576);
+        assert_eq!(parse_size("512K").unwrap(), 512 * 1024);
+        assert_eq!(parse_size("500MB").unwrap(), 500 * 1024 * 1024);
+        assert_eq!(parse_size("1.5g").unwrap(), 1536 * 1024 * 1024);
+        assert_eq!(parse_size(" 10 kb ").unwrap(), 10 * 1024);
+        assert!(parse_size("").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+        assert!(parse_size("MB").is_err());
+    }
+
+    #[test]
+    fn test_parse_and_format_duration() {
+        assert_eq!(parse_duration("3600").unwrap(), 3600);
+        assert_eq!(parse_duration("90s").unwrap(), 90);
+        assert_eq!(parse_duration("30m").unwrap(), 30 * 60);
+        assert_eq!(parse_duration("6H").unwrap(), 6 * 60 * 60);
+        assert_eq!(parse_duration(" 1 d ").unwrap(), 24 * 60 * 60);
+        assert_eq!(parse_duration("2w").unwrap(), 14 * 24 * 60 * 60);
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("1.5h").is_err());
+        assert!(parse_duration("1y").is_err());
+        assert!(matches!(
+            parse_duration("3000000000000000w"),
+            Err(GistCacheError::Config(msg)) if msg.contains("too large")
+        ));
+
+        assert_eq!(format_duration(6 * 60 * 60), "6h");
+        assert_eq!(format_duration(90 * 60), "90m");
+        assert_eq!(format_duration(14 * 24 * 60 * 60), "2w");
+        assert_eq!(format_duration(90), "90s");
+        assert_eq!(format_duration(0), "0s");
+    }
+
+    #[test]
+    fn test_storage_backend_config() {
+        let temp_dir = std::env::temp_dir().join("test_storage_backend_config");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let mut config = Config::for_test(&temp_dir);
+        assert_eq!(config.storage_backend(), StorageBackend::Json);
+        assert_eq!(config.cache_max_size(), None);
+
+        config.set_config_value("cache.max_size", "2MB").unwrap();
+        assert_eq!(config.cache_max_size(), Some(2 * 1024 * 1024));
+        assert_eq!(
+            config.get_config_value("cache.max_size"),
+            Some("2097152".to_string())
+        );
+
+        assert_eq!(config.cache_max_age(), None);
+        config.set_config_value("cache.max_age", "6h").unwrap();
+        assert_eq!(config.cache_max_age(), Some(chrono::Duration::hours(6)));
+        assert_eq!(
+            config.get_config_value("cache.max_age"),
+            Some("21600".to_string())
+        );
+        // Too large for a TimeDelta: clamped instead of panicking
+        config
+            .set_config_value("cache.max_age", "10000000000000000")
+            .unwrap();
+        assert_eq!(config.cache_max_age(), Some(chrono::TimeDelta::MAX));
+
+        assert!(config.set_config_value("cache.backend", "redis").is_err());
+        config.set_config_value("cache.backend", "sqlite").unwrap();
+        assert_eq!(config.storage_backend(), StorageBackend::Sqlite);
+        assert_eq!(
+            config.get_config_value("cache.backend"),
+            Some("sqlite".to_string())
+        );
+
+        // cache.json alone no longer counts as an existing cache
+        fs::write(&config.cache_file, "{}").unwrap();
+        assert!(!config.cache_exists());
+        fs::write(config.cache_db_file(), "").unwrap();
+        assert!(config.cache_exists());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_picker_config() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::for_test(temp_dir.path());
+        assert_eq!(config.picker_sort(), None);
+        assert!(!config.picker_group());
+
+        assert!(config.set_config_value("picker.sort", "random").is_err());
+        config.set_config_value("picker.sort", "size").unwrap();
+        config.set_config_value("picker.group", "true").unwrap();
+        assert_eq!(config.picker_sort(), Some(PickerSort::Size));
+        assert!(config.picker_group());
+        assert_eq!(
+            config.get_config_value("picker.sort"),
+            Some("size".to_string())
+        );
+
+        // Persisted to the config file
+        let saved = Config::load_user_config(&config.config_file).unwrap();
+        assert_eq!(saved.picker.unwrap().sort, Some(PickerSort::Size));
+
+        assert_eq!(config.picker_preview(), PreviewLayout::Off);
+        assert!(config.set_config_value("picker.preview", "left").is_err());
+        config.set_config_value("picker.preview", "bottom").unwrap();
+        assert_eq!(config.picker_preview(), PreviewLayout::Bottom);
+        assert_eq!(PreviewLayout::Bottom.next(), PreviewLayout::Off);
+
+        assert!(config.frecency_enabled());
+        config.set_config_value("search.frecency", "false").unwrap();
+        assert!(!config.frecency_enabled());
+
+        assert_eq!(config.display_theme(), None);
+        assert!(config.set_config_value("display.theme", " ").is_err());
+        config.set_config_value("display.theme", "Nord").unwrap();
+        assert_eq!(config.display_theme(), Some("Nord"));
+        assert_eq!(
+            config.get_config_value("display.theme"),
+            Some("Nord".to_string())
+        );
+        assert_eq!(config.themes_dir(), temp_dir.path().join("themes"));
+        assert_eq!(config.syntaxes_dir(), temp_dir.path().join("syntaxes"));
+
+        // Cycling visits every order once
+        let mut sort = PickerSort::Updated;
+        for _ in 0..5 {
+            sort = sort.next();
+        }
+        assert_eq!(sort, PickerSort::Updated);
+    }
+
+    #[test]
+    fn test_aliases() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::for_test(temp_dir.path());
+        let deploy = GistAlias {
+            gist: "abc123".to_string(),
+            interpreter: Some("uv".to_string()),
+            args: vec!["--env".to_string(), "prod".to_string()],
+        };
+
+        config.set_alias("deploy", deploy.clone()).unwrap();
+        assert_eq!(config.alias("deploy"), Some(&deploy));
+        assert_eq!(config.alias("deplo"), None);
+        for name in ["", "-x", "two words", "a/b"] {
+            assert!(matches!(
+                config.set_alias(name, deploy.clone()),
+                Err(GistCacheError::InvalidAlias(_))
+            ));
+        }
+
+        // Persisted as [aliases.<name>] tables
+        let content = std::fs::read_to_string(&config.config_file).unwrap();
+        assert!(content.contains("[aliases.deploy]"));
+        let saved = Config::load_user_config(&config.config_file).unwrap();
+        assert_eq!(saved.aliases["deploy"], deploy);
+
+        assert_eq!(config.remove_alias("deploy").unwrap(), deploy);
+        assert!(config.aliases().is_empty());
+        assert!(matches!(
+            config.remove_alias("deploy"),
+            Err(GistCacheError::AliasNotFound(_))
+        ));
+    }
+
     #[test]
     fn test_set_nested_interpreter_config() {
         let temp_dir = std::env::temp_dir().join("test_nested_interpreter");
@@ -32,6 +32,9 @@ pub enum GistCacheError {
     #[error("Execution error: {0}")]
     Execution(String),
 
+    #[error("Execution error: Script exited with code: {0}")]
+    ScriptExited(i32),
+
     #[error("Invalid interpreter: {0}")]
     InvalidInterpreter(String),
 
@@ -49,6 +52,57 @@ pub enum GistCacheError {
 
     #[error("Cache directory error: {0}")]
     CacheDirectoryError(String),
+
+    #[error("Content integrity check failed: {0}")]
+    IntegrityCheckFailed(String),
+
+    #[error("Cache is locked by another process: {0}")]
+    CacheLocked(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("Invalid cache bundle: {0}")]
+    InvalidBundle(String),
+
+    #[error("Cache check found problems: {0}")]
+    CacheCheckFailed(String),
+
+    #[error("Watch daemon is already running (PID {0})")]
+    WatchRunning(String),
+
+    #[error("No run #{0} in the history (see 'gist-cache-rs history')")]
+    HistoryEntryNotFound(usize),
+
+    #[error("Unknown alias: {0} (see 'gist-cache-rs alias list')")]
+    AliasNotFound(String),
+
+    #[error("Invalid alias: {0}")]
+    InvalidAlias(String),
+
+    #[error("Invalid tag: '{0}' (use letters, digits, '-', '_', '.' and '/')")]
+    InvalidTag(String),
+
+    #[error("Invalid --select value: '{0}' (expected 'first', 'newest' or a number from 1)")]
+    InvalidSelectPolicy(String),
+
+    #[error("--select {0}: the query found only {1} Gists")]
+    SelectionOutOfRange(usize, usize),
+
+    #[error("'{0}' matches {1} Gists; choose one with --select first|newest|N")]
+    AmbiguousQuery(String, usize),
+
+    #[error("Unknown theme: {0} (see 'gist-cache-rs themes list')")]
+    UnknownTheme(String),
+
+    #[error("Failed to load highlighting definition: {0}")]
+    HighlightAsset(String),
+
+    #[error("No file of Gist {1} matches '{0}'")]
+    NoMatchingFile(String, String),
+
+    #[error("{1} files of Gist {0} could run; choose one with --file")]
+    AmbiguousMainFile(String, usize),
 }
 
 pub type Result<T> = std::result::Result<T, GistCacheError>;
@@ -109,6 +163,105 @@ mod tests {
 
         let error = GistCacheError::CacheDirectoryError("dir error".to_string());
         assert_eq!(error.to_string(), "Cache directory error: dir error");
+
+        let error = GistCacheError::IntegrityCheckFailed("abc/run.sh".to_string());
+        assert_eq!(
+            error.to_string(),
+            "Content integrity check failed: abc/run.sh"
+        );
+
+        let error = GistCacheError::CacheLocked("cache.lock".to_string());
+        assert_eq!(
+            error.to_string(),
+            "Cache is locked by another process: cache.lock"
+        );
+
+        let error = GistCacheError::Database(rusqlite::Error::QueryReturnedNoRows);
+        assert_eq!(error.to_string(), "Database error: Query returned no rows");
+
+        let error = GistCacheError::InvalidBundle("missing cache.json".to_string());
+        assert_eq!(
+            error.to_string(),
+            "Invalid cache bundle: missing cache.json"
+        );
+
+        let error = GistCacheError::CacheCheckFailed("3 problem(s)".to_string());
+        assert_eq!(
+            error.to_string(),
+            "Cache check found problems: 3 problem(s)"
+        );
+
+        let error = GistCacheError::ScriptExited(3);
+        assert_eq!(
+            error.to_string(),
+            "Execution error: Script exited with code: 3"
+        );
+
+        let error = GistCacheError::WatchRunning("4242".to_string());
+        assert_eq!(
+            error.to_string(),
+            "Watch daemon is already running (PID 4242)"
+        );
+
+        let error = GistCacheError::HistoryEntryNotFound(7);
+        assert_eq!(
+            error.to_string(),
+            "No run #7 in the history (see 'gist-cache-rs history')"
+        );
+
+        let error = GistCacheError::AliasNotFound("deploy".to_string());
+        assert_eq!(
+            error.to_string(),
+            "Unknown alias: deploy (see 'gist-cache-rs alias list')"
+        );
+
+        let error = GistCacheError::InvalidAlias("'a b'".to_string());
+        assert_eq!(error.to_string(), "Invalid alias: 'a b'");
+
+        let error = GistCacheError::InvalidTag("a b".to_string());
+        assert_eq!(
+            error.to_string(),
+            "Invalid tag: 'a b' (use letters, digits, '-', '_', '.' and '/')"
+        );
+
+        let error = GistCacheError::InvalidSelectPolicy("last".to_string());
+        assert_eq!(
+            error.to_string(),
+            "Invalid --select value: 'last' (expected 'first', 'newest' or a number from 1)"
+        );
+
+        let error = GistCacheError::SelectionOutOfRange(5, 3);
+        assert_eq!(
+            error.to_string(),
+            "--select 5: the query found only 3 Gists"
+        );
+
+        let error = GistCacheError::AmbiguousQuery("deploy".to_string(), 3);
+        assert_eq!(
+            error.to_string(),
+            "'deploy' matches 3 Gists; choose one with --select first|newest|N"
+        );
+
+        let error = GistCacheError::UnknownTheme("paper".to_string());
+        assert_eq!(
+            error.to_string(),
+            "Unknown theme: paper (see 'gist-cache-rs themes list')"
+        );
+
+        let error = GistCacheError::HighlightAsset("x.tmTheme: bad".to_string());
+        assert_eq!(
+            error.to_string(),
+            "Failed to load highlighting definition: x.tmTheme: bad"
+        );
+
+        let error = GistCacheError::NoMatchingFile("*.rb".to_string(), "abc".to_string());
+        assert_eq!(error.to_string(), "No file of Gist abc matches '*.rb'");
+
+        let error = GistCacheError::AmbiguousMainFile("abc".to_string(), 2);
+        assert_eq!(
+            error.to_string(),
+            "2 files of Gist abc could run; choose one with --file"
+        );
     }
 
     #[test]
@@ -0,0 +1,267 @@
+//! Picker actions besides running a gist: download, copy to the clipboard,
+//! open in the browser, edit and refresh
+
+use crate::cache::open_content_store;
+use crate::cache::types::GistInfo;
+use crate::config::Config;
+use crate::error::{GistCacheError, Result};
+use crate::github::GitHubApi;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Save every file of `gist` to `download_dir`, reading the content cache
+/// first and caching what had to be fetched
+///
+/// # Returns
+/// Paths of the written files
+pub fn download_gist(config: &Config, gist: &GistInfo) -> Result<Vec<PathBuf>> {
+    // Ensure download directory exists
+    config.ensure_download_dir()?;
+
+    let mut paths = Vec::new();
+    for (filename, content) in read_gist_files(config, gist)? {
+        // Save to download folder
+        let download_path = config.download_dir.join(&filename);
+        fs::write(&download_path, &content)?;
+        paths.push(download_path);
+    }
+
+    Ok(paths)
+}
+
+/// Every file of `gist` as (filename, content), reading the content cache
+/// first and caching what had to be fetched
+///
+/// # Errors
+/// Returns `IntegrityCheckFailed` if a cached file no longer matches its
+/// recorded hash and cannot be fetched again
+pub fn read_gist_files(config: &Config, gist: &GistInfo) -> Result<Vec<(String, String)>> {
+    let content_cache = open_content_store(config)?;
+    let mut files = Vec::new();
+
+    for file in &gist.files {
+        // Load from cache or fetch from API
+        let content = if content_cache.exists(&gist.id, &file.filename) {
+            // Same as a run: never hand out content that fails its hash check
+            match content_cache.read_verified(&gist.id, &file.filename) {
+                Ok(c) => c,
+                Err(e) => {
+                    // Fetch from API if cache read fails, but never fall back
+                    // to modified content
+                    let fetched = GitHubApi::new()
+                        .fetch_gist_content(&gist.id, &file.filename)
+                        .map_err(|fetch_err| match e {
+                            GistCacheError::IntegrityCheckFailed(_) => e,
+                            _ => fetch_err,
+                        })?;
+                    let _ = content_cache.write(&gist.id, &file.filename, &fetched);
+                    fetched
+                }
+            }
+        } else {
+            // Fetch from API
+            let fetched = GitHubApi::new().fetch_gist_content(&gist.id, &file.filename)?;
+
+            // Also create cache when fetching
+            let _ = content_cache.write(&gist.id, &file.filename, &fetched);
+
+            fetched
+        };
+        files.push((file.filename.clone(), content));
+    }
+
+    Ok(files)
+}
+
+/// Standard base64 (with padding), for OSC 52 payloads
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let group = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// OSC 52 escape sequence that sets the system clipboard to `text`
+///
+/// Inside tmux the sequence is wrapped in a DCS passthrough so it reaches
+/// the outer terminal.
+pub fn osc52_sequence(text: &str, in_tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", base64_encode(text.as_bytes()));
+    if in_tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+/// Copy `text` to the clipboard through the terminal (OSC 52)
+///
+/// Works over SSH without a clipboard tool, as long as the terminal
+/// supports OSC 52; terminals that do not silently ignore it.
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let in_tmux = std::env::var_os("TMUX").is_some();
+    let mut stdout = std::io::stdout();
+    stdout.write_all(osc52_sequence(text, in_tmux).as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Open `url` with the platform's default browser
+pub fn open_in_browser(url: &str) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let status = Command::new("open").arg(url).status();
+    #[cfg(target_os = "windows")]
+    let status = Command::new("cmd").args(["/C", "start", "", url]).status();
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let status = Command::new("xdg-open").arg(url).status();
+
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(GistCacheError::Execution(format!(
+            "Failed to open browser (exit code {})",
+            status.code().unwrap_or(-1)
+        ))),
+        Err(e) => Err(GistCacheError::Execution(format!(
+            "Failed to open browser: {}",
+            e
+        ))),
+    }
+}
+
+/// Edit `gist` on GitHub with `gh gist edit` (opens `$EDITOR`)
+///
+/// The gist's cached files are dropped afterwards, so the next run fetches
+/// the edited content.
+pub fn edit_gist(config: &Config, gist: &GistInfo) -> Result<()> {
+    let status = Command::new("gh")
+        .args(["gist", "edit", &gist.id])
+        .status()
+        .map_err(|_| GistCacheError::NotAuthenticated)?;
+
+    if !status.success() {
+        return Err(GistCacheError::GitHubApi(format!(
+            "gh gist edit exited with code: {}",
+            status.code().unwrap_or(-1)
+        )));
+    }
+
+    open_content_store(config)?.delete_gist(&gist.id)?;
+    Ok(())
+}
+
+/// Re-fetch every file of `gist` from GitHub into the content cache,
+/// replacing what was cached
+///
+/// # Returns
+/// Number of files fetched
+pub fn refresh_gist(config: &Config, gist: &GistInfo) -> Result<usize> {
+    let content_store = open_content_store(config)?;
+    let api = GitHubApi::new();
+
+    // Fetch everything first, so a failure leaves the cache untouched
+    let mut contents = Vec::new();
+    for file in &gist.files {
+        contents.push((
+            &file.filename,
+            api.fetch_gist_content(&gist.id, &file.filename)?,
+        ));
+    }
+
+    content_store.delete_gist(&gist.id)?;
+    for (filename, content) in &contents {
+        content_store.write(&gist.id, filename, content)?;
+    }
+
+    Ok(contents.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::ContentCache;
+    use crate::cache::types::GistFile;
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    fn test_gist(id: &str, filename: &str) -> GistInfo {
+        GistInfo {
+            id: id.to_string(),
+            description: None,
+            files: vec![GistFile {
+                filename: filename.to_string(),
+                language: None,
+                size: 0,
+                revision: None,
+            }],
+            updated_at: Utc::now(),
+            public: false,
+            html_url: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_read_gist_files_refuses_tampered_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+        let cache = ContentCache::new(config.contents_dir.clone());
+        let gist = test_gist("tampered000000", "run.sh");
+
+        cache.write(&gist.id, "run.sh", "echo safe").unwrap();
+        assert_eq!(
+            read_gist_files(&config, &gist).unwrap(),
+            vec![("run.sh".to_string(), "echo safe".to_string())]
+        );
+
+        // The Gist does not exist on GitHub, so the cached copy is all there is
+        fs::write(
+            config.contents_dir.join(&gist.id).join("run.sh"),
+            "rm -rf ~",
+        )
+        .unwrap();
+        assert!(matches!(
+            read_gist_files(&config, &gist),
+            Err(GistCacheError::IntegrityCheckFailed(_))
+        ));
+        assert!(matches!(
+            download_gist(&config, &gist),
+            Err(GistCacheError::IntegrityCheckFailed(_))
+        ));
+        assert!(!config.download_dir.join("run.sh").exists());
+    }
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode("ギスト".as_bytes()), "44Ku44K544OI");
+    }
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52_sequence("abc123", false), "\x1b]52;c;YWJjMTIz\x07");
+        assert_eq!(
+            osc52_sequence("abc123", true),
+            "\x1bPtmux;\x1b\x1b]52;c;YWJjMTIz\x07\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Whole-gist rendering for `show`: a self-contained HTML page, an SVG
+//! image, ANSI colored text or plain text, with the same
+//! `--- filename ---` sections as the picker's preview
+
+use crate::cache::types::GistInfo;
+use crate::execution::highlight::{
+    escape_html, highlight_content, highlight_html, highlight_svg, theme_css_colors,
+};
+use crate::search::interactive::DEFAULT_DESCRIPTION;
+use std::fmt::Write;
+
+/// Font size of SVG output, in pixels
+const SVG_FONT_SIZE: usize = 14;
+/// Distance between the baselines of two SVG lines, in pixels
+const SVG_LINE_HEIGHT: usize = 20;
+/// Advance of one monospace character at `SVG_FONT_SIZE` (0.6em), in
+/// tenths of a pixel
+const SVG_CHAR_WIDTH_TENTHS: usize = 84;
+/// Margin around the SVG text, in pixels
+const SVG_PADDING: usize = 16;
+
+/// Width of the line numbers of `content`, so they line up
+fn number_width(content: &str) -> usize {
+    content.lines().count().to_string().len()
+}
+
+/// `gist` as text: its description, then every file under a
+/// `--- filename ---` divider. With `ansi`, the description and dividers
+/// are colored and the content highlighted with the configured theme.
+///
+/// `files` holds (filename, content) in the gist's file order.
+pub fn render_text(
+    gist: &GistInfo,
+    files: &[(String, String)],
+    ansi: bool,
+    line_numbers: bool,
+) -> String {
+    let paint = |text: &str, code: &str| {
+        if ansi {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    };
+
+    let description = gist.description.as_deref().unwrap_or(DEFAULT_DESCRIPTION);
+    let mut out = format!("{}\n", paint(description, "1;36"));
+
+    for (filename, content) in files {
+        let _ = writeln!(out, "\n{}", paint(&format!("--- {} ---", filename), "1;33"));
+
+        let body = if ansi {
+            highlight_content(filename, content)
+        } else {
+            content.clone()
+        };
+        let width = number_width(content);
+        for (i, line) in body.lines().take(content.lines().count()).enumerate() {
+            if line_numbers {
+                // The reset ends the number's dimming; the highlighting
+                // sets the color again for every token
+                let _ = write!(out, "{}  ", paint(&format!("{:>width$}", i + 1), "2"));
+            }
+            let _ = writeln!(out, "{}", line);
+        }
+        if ansi {
+            out.push_str("\x1b[0m");
+        }
+    }
+    out
+}
+
+/// `gist` as a self-contained HTML page (inline styles only): its
+/// description as the title, then every file under a heading with its
+/// name, highlighted with the configured theme
+///
+/// `files` holds (filename, content) in the gist's file order.
+pub fn render_html(gist: &GistInfo, files: &[(String, String)], line_numbers: bool) -> String {
+    let description = escape_html(gist.description.as_deref().unwrap_or(DEFAULT_DESCRIPTION));
+    let (background, foreground) = theme_css_colors();
+
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{description}</title>
+<style>
+body {{ background: {background}; color: {foreground}; font-family: sans-serif; margin: 2em; }}
+a {{ color: inherit; }}
+h2 {{ font-family: monospace; font-size: 1em; margin: 2em 0 0.5em; }}
+pre {{ padding: 1em; overflow-x: auto; font-family: monospace; }}
+</style>
+</head>
+<body>
+<h1>{description}</h1>
+<p><a href=\"{url}\">{url}</a></p>
+",
+        url = escape_html(&gist.html_url),
+    );
+
+    for (filename, content) in files {
+        let _ = writeln!(out, "<h2>--- {} ---</h2>", escape_html(filename));
+        out.push_str(&highlight_html(filename, content, line_numbers));
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// `gist` as a standalone SVG image laid out like `render_text`: the
+/// description, then every file under a `--- filename ---` divider,
+/// highlighted with the configured theme on its background. Tabs are
+/// expanded to four spaces, since SVG text has no tab stops.
+///
+/// `files` holds (filename, content) in the gist's file order.
+pub fn render_svg(gist: &GistInfo, files: &[(String, String)], line_numbers: bool) -> String {
+    let description = gist.description.as_deref().unwrap_or(DEFAULT_DESCRIPTION);
+    let (background, foreground) = theme_css_colors();
+
+    // Each line is (text width in characters, SVG markup); `None` leaves
+    // the line blank
+    let mut lines: Vec<Option<(usize, String)>> = vec![Some((
+        description.chars().count(),
+        format!(
+            "<tspan font-weight=\"bold\">{}</tspan>",
+            escape_html(description)
+        ),
+    ))];
+    for (filename, content) in files {
+        let header = format!("--- {} ---", filename);
+        lines.push(None);
+        lines.push(Some((
+            header.chars().count(),
+            format!(
+                "<tspan font-weight=\"bold\">{}</tspan>",
+                escape_html(&header)
+            ),
+        )));
+
+        let content = content.replace('\t', "    ");
+        let width = number_width(&content);
+        for (i, (text, markup)) in content
+            .lines()
+            .zip(highlight_svg(filename, &content))
+            .enumerate()
+        {
+            let mut columns = text.chars().count();
+            let mut line = String::new();
+            if line_numbers {
+                columns += width + 2;
+                let _ = write!(
+                    line,
+                    "<tspan fill-opacity=\"0.6\">{:>width$}  </tspan>",
+                    i + 1
+                );
+            }
+            line.push_str(&markup);
+            lines.push(Some((columns, line)));
+        }
+    }
+
+    let columns = lines.iter().flatten().map(|(c, _)| *c).max().unwrap_or(0);
+    let width = 2 * SVG_PADDING + (columns * SVG_CHAR_WIDTH_TENTHS).div_ceil(10);
+    let height = 2 * SVG_PADDING + lines.len() * SVG_LINE_HEIGHT;
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\" font-family=\"monospace\" font-size=\"{SVG_FONT_SIZE}\">
+<title>{title}</title>
+<rect width=\"100%\" height=\"100%\" fill=\"{background}\"/>
+<g fill=\"{foreground}\" xml:space=\"preserve\">",
+        title = escape_html(description),
+    );
+    for (i, line) in lines.iter().enumerate() {
+        if let Some((_, markup)) = line {
+            // Baselines sit a font size below the top of each line
+            let y = SVG_PADDING + i * SVG_LINE_HEIGHT + SVG_FONT_SIZE;
+            let _ = writeln!(out, "<text x=\"{SVG_PADDING}\" y=\"{y}\">{markup}</text>");
+        }
+    }
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn gist(description: Option<&str>) -> GistInfo {
+        GistInfo {
+            id: "abc123".to_string(),
+            description: description.map(str::to_string),
+            files: vec![],
+            updated_at: Utc::now(),
+            public: true,
+            html_url: "https://gist.github.com/abc123".to_string(),
+        }
+    }
+
+    fn files() -> Vec<(String, String)> {
+        vec![
+            ("run.sh".to_string(), "echo <hi>\necho bye\n".to_string()),
+            (
+                "notes.txt".to_string(),
+                "a\nb\nc\nd\ne\nf\ng\nh\ni\nj".to_string(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_render_text_plain_and_ansi() {
+        let plain = render_text(&gist(Some("Deploy #bash")), &files(), false, false);
+        assert_eq!(
+            plain,
+            "Deploy #bash\n\n--- run.sh ---\necho <hi>\necho bye\n\n--- notes.txt ---\na\nb\nc\nd\ne\nf\ng\nh\ni\nj\n"
+        );
+        assert!(!plain.contains('\x1b'));
+
+        let numbered = render_text(&gist(None), &files(), false, true);
+        assert!(numbered.starts_with(&format!("{}\n", DEFAULT_DESCRIPTION)));
+        assert!(numbered.contains("1  echo <hi>\n2  echo bye\n"));
+        assert!(numbered.contains("\n 1  a\n"));
+        assert!(numbered.contains("\n10  j\n"));
+
+        let ansi = render_text(&gist(Some("Deploy")), &files(), true, true);
+        assert!(ansi.starts_with("\x1b[1;36mDeploy\x1b[0m\n"));
+        assert!(ansi.contains("\x1b[1;33m--- run.sh ---\x1b[0m"));
+        assert!(ansi.contains("\x1b[2m 1\x1b[0m  "));
+        assert!(ansi.ends_with("\x1b[0m"));
+    }
+
+    #[test]
+    fn test_render_html_is_a_self_contained_page() {
+        let gist = gist(Some("Deploy <prod> & \"staging\""));
+        let html = render_html(&gist, &files(), true);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Deploy &lt;prod&gt; &amp; &quot;staging&quot;</title>"));
+        assert!(html.contains("<h2>--- run.sh ---</h2>\n<pre style="));
+        assert!(html.contains("<h2>--- notes.txt ---</h2>"));
+        assert_eq!(html.matches("</pre>").count(), 2);
+        assert!(html.contains("user-select:none"));
+        // Nothing loaded from elsewhere
+        assert!(!html.contains("<link") && !html.contains("<script"));
+        assert!(html.ends_with("</html>\n"));
+    }
+
+    #[test]
+    fn test_render_svg_is_a_standalone_image() {
+        let gist = gist(Some("Deploy <prod> & co"));
+        let svg = render_svg(&gist, &files(), true);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains("<title>Deploy &lt;prod&gt; &amp; co</title>"));
+        assert!(svg.contains("<tspan font-weight=\"bold\">--- run.sh ---</tspan>"));
+        assert!(svg.contains("<tspan font-weight=\"bold\">--- notes.txt ---</tspan>"));
+        assert!(svg.contains("<tspan fill-opacity=\"0.6\"> 1  </tspan>"));
+        assert!(svg.contains("<tspan fill-opacity=\"0.6\">10  </tspan>"));
+        assert!(!svg.contains("<hi>"));
+        // Description, blank, header and 2 lines, blank, header and 10 lines
+        assert_eq!(svg.matches("<text ").count(), 15);
+        assert!(svg.contains("height=\"372\""));
+        assert!(svg.ends_with("</svg>\n"));
+        // Nothing loaded from elsewhere
+        assert!(!svg.contains("href") && !svg.contains("<script"));
+    }
+}
@@ -1,51 +1,336 @@
-use std::sync::LazyLock;
+use crate::config::Config;
+use crate::error::{GistCacheError, Result};
+use colored::Colorize;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, OnceLock};
 use syntect::easy::HighlightLines;
-use syntect::highlighting::{Theme, ThemeSet};
-use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::highlighting::{Color, FontStyle, Theme, ThemeSet};
+use syntect::html::{
+    IncludeBackground, append_highlighted_html_for_styled_line, start_highlighted_html_snippet,
+};
+use syntect::parsing::{SyntaxDefinition, SyntaxReference, SyntaxSet};
 use syntect::util::{LinesWithEndings, as_24_bit_terminal_escaped};
+use two_face::theme::{EmbeddedLazyThemeSet, EmbeddedThemeName};
+
+/// Theme used when `display.theme` is not set
+pub const DEFAULT_THEME: &str = "base16-ocean.dark";
+
+/// Bundled themes that store ANSI palette indices instead of colors, which
+/// the 24-bit escapes written here cannot render
+const PALETTE_THEMES: [EmbeddedThemeName; 3] = [
+    EmbeddedThemeName::Ansi,
+    EmbeddedThemeName::Base16,
+    EmbeddedThemeName::Base16_256,
+];
+
+/// Theme and extra syntaxes from the user's configuration, set once by
+/// `configure` before anything is highlighted
+struct Settings {
+    theme: Theme,
+    syntaxes: Vec<SyntaxDefinition>,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
 
 // syntect's bundled defaults are missing several languages this project
 // supports (TypeScript, TOML, ...); two-face ships bat's much larger
 // syntax collection instead.
-static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(two_face::syntax::extra_newlines);
+static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(|| {
+    let extra = SETTINGS.get().map_or(&[][..], |s| &s.syntaxes);
+    build_syntax_set(extra)
+});
+
+static BUNDLED_THEMES: LazyLock<EmbeddedLazyThemeSet> = LazyLock::new(two_face::theme::extra);
+
+/// Bundled syntaxes plus `extra`, which take precedence when they claim
+/// the same extension
+fn build_syntax_set(extra: &[SyntaxDefinition]) -> SyntaxSet {
+    let syntaxes = two_face::syntax::extra_newlines();
+    if extra.is_empty() {
+        return syntaxes;
+    }
+
+    let mut builder = syntaxes.into_builder();
+    for syntax in extra {
+        builder.add(syntax.clone());
+    }
+    builder.build()
+}
+
+fn theme() -> &'static Theme {
+    match SETTINGS.get() {
+        Some(settings) => &settings.theme,
+        None => BUNDLED_THEMES.get(EmbeddedThemeName::Base16OceanDark),
+    }
+}
+
+/// Load the theme and the extra syntaxes the configuration asks for
+///
+/// Problems are reported as warnings and the bundled defaults are used
+/// instead, so a broken theme file never stops a command (including the
+/// `config set` that would fix it). Only the first call has an effect.
+pub fn configure(config: &Config) {
+    if SETTINGS.get().is_some() {
+        return;
+    }
+
+    let warn = |e: GistCacheError| eprintln!("{}", format!("Warning: {}", e).yellow());
+
+    let theme = match config.display_theme() {
+        Some(name) => load_theme(config, name).unwrap_or_else(|e| {
+            warn(e);
+            theme().clone()
+        }),
+        None => theme().clone(),
+    };
+
+    let mut syntaxes = Vec::new();
+    for path in files_with_extension(&config.syntaxes_dir(), "sublime-syntax") {
+        match load_syntax(&path) {
+            Ok(syntax) => syntaxes.push(syntax),
+            Err(e) => warn(e),
+        }
+    }
 
-static THEME: LazyLock<Theme> = LazyLock::new(|| {
-    let mut themes = ThemeSet::load_defaults();
+    let _ = SETTINGS.set(Settings { theme, syntaxes });
+}
+
+/// A theme `display.theme` can name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeInfo {
+    pub name: String,
+    /// The `.tmTheme` file of a custom theme; `None` for bundled ones
+    pub path: Option<PathBuf>,
+}
+
+/// Bundled themes followed by the custom ones in `config.themes_dir()`
+///
+/// A custom theme with the name of a bundled one replaces it.
+pub fn available_themes(config: &Config) -> Vec<ThemeInfo> {
+    let custom: Vec<ThemeInfo> = files_with_extension(&config.themes_dir(), "tmTheme")
+        .into_iter()
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            Some(ThemeInfo {
+                name,
+                path: Some(path),
+            })
+        })
+        .collect();
+
+    let mut themes: Vec<ThemeInfo> = EmbeddedLazyThemeSet::theme_names()
+        .iter()
+        .filter(|name| !PALETTE_THEMES.contains(name))
+        .map(|name| name.as_name())
+        .filter(|name| !custom.iter().any(|c| c.name == *name))
+        .map(|name| ThemeInfo {
+            name: name.to_string(),
+            path: None,
+        })
+        .collect();
+    themes.extend(custom);
     themes
-        .themes
-        .remove("base16-ocean.dark")
-        .expect("bundled base16-ocean.dark theme should exist")
-});
+}
+
+/// The theme called `name`: `<name>.tmTheme` in `config.themes_dir()`, or
+/// else the bundled theme of that name
+///
+/// # Errors
+/// Returns `UnknownTheme` if there is neither, and `HighlightAsset` if the
+/// `.tmTheme` file cannot be read
+pub fn load_theme(config: &Config, name: &str) -> Result<Theme> {
+    let path = config.themes_dir().join(format!("{}.tmTheme", name));
+    if path.is_file() {
+        return ThemeSet::get_theme(&path)
+            .map_err(|e| GistCacheError::HighlightAsset(format!("{}: {}", path.display(), e)));
+    }
+
+    EmbeddedLazyThemeSet::theme_names()
+        .iter()
+        .find(|theme| theme.as_name() == name && !PALETTE_THEMES.contains(theme))
+        .map(|&theme| BUNDLED_THEMES.get(theme).clone())
+        .ok_or_else(|| GistCacheError::UnknownTheme(name.to_string()))
+}
+
+/// Parse one `.sublime-syntax` file
+///
+/// # Errors
+/// Returns `HighlightAsset` if the file cannot be read or parsed
+pub fn load_syntax(path: &Path) -> Result<SyntaxDefinition> {
+    let fallback_name = path.file_stem().and_then(|s| s.to_str());
+    std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| {
+            SyntaxDefinition::load_from_str(&content, true, fallback_name)
+                .map_err(|e| e.to_string())
+        })
+        .map_err(|e| GistCacheError::HighlightAsset(format!("{}: {}", path.display(), e)))
+}
+
+/// Files in `dir` with `extension`, sorted by name (none if `dir` is missing)
+fn files_with_extension(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|e| e == extension))
+        .collect();
+    files.sort();
+    files
+}
 
 /// Resolves a syntax by file extension, falling back to shebang-line
 /// detection (using in-memory `content`, not disk I/O) and finally to
 /// plain text when nothing matches.
-fn syntax_for(filename: &str, content: &str) -> &'static SyntaxReference {
-    let ext = std::path::Path::new(filename)
-        .extension()
-        .and_then(|e| e.to_str());
+fn syntax_for<'a>(syntaxes: &'a SyntaxSet, filename: &str, content: &str) -> &'a SyntaxReference {
+    let ext = Path::new(filename).extension().and_then(|e| e.to_str());
 
     if let Some(ext) = ext
-        && let Some(syntax) = SYNTAX_SET.find_syntax_by_extension(ext)
+        && let Some(syntax) = syntaxes.find_syntax_by_extension(ext)
     {
         return syntax;
     }
 
-    SYNTAX_SET
+    syntaxes
         .find_syntax_by_first_line(content)
-        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text())
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text())
 }
 
 /// Renders `content` as ANSI 24-bit colored text, using syntax rules
 /// inferred from `filename`'s extension. Falls back to the plain-text
 /// syntax (no highlighting) when the extension isn't recognized.
 pub fn highlight_content(filename: &str, content: &str) -> String {
-    let syntax = syntax_for(filename, content);
-    let mut highlighter = HighlightLines::new(syntax, &THEME);
+    highlight_with(&SYNTAX_SET, theme(), filename, content)
+}
+
+/// `highlight_content` with another theme than the configured one
+pub fn highlight_content_with_theme(filename: &str, content: &str, theme: &Theme) -> String {
+    highlight_with(&SYNTAX_SET, theme, filename, content)
+}
+
+/// Renders `content` as a `<pre>` block with inline styles from the
+/// configured theme. With `line_numbers`, every line starts with its
+/// number, which is left out when the block is copied.
+pub fn highlight_html(filename: &str, content: &str, line_numbers: bool) -> String {
+    html_with(&SYNTAX_SET, theme(), filename, content, line_numbers)
+}
+
+/// Renders `content` as SVG `<tspan>` runs colored with the configured
+/// theme, one string per line without its line break
+pub fn highlight_svg(filename: &str, content: &str) -> Vec<String> {
+    svg_with(&SYNTAX_SET, theme(), filename, content)
+}
+
+/// Background and foreground of the configured theme, as CSS colors
+pub fn theme_css_colors() -> (String, String) {
+    let settings = &theme().settings;
+    (
+        css_color(settings.background.unwrap_or(Color::WHITE)),
+        css_color(settings.foreground.unwrap_or(Color::BLACK)),
+    )
+}
+
+fn css_color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+/// `text` with the characters HTML gives a meaning escaped
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn html_with(
+    syntaxes: &SyntaxSet,
+    theme: &Theme,
+    filename: &str,
+    content: &str,
+    line_numbers: bool,
+) -> String {
+    let syntax = syntax_for(syntaxes, filename, content);
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let (mut out, background) = start_highlighted_html_snippet(theme);
+    let gutter = theme
+        .settings
+        .gutter_foreground
+        .or(theme.settings.foreground)
+        .map_or_else(|| "inherit".to_string(), css_color);
+    let width = content.lines().count().to_string().len();
+
+    for (i, line) in LinesWithEndings::from(content).enumerate() {
+        if line_numbers {
+            let _ = write!(
+                out,
+                "<span style=\"color:{};opacity:0.6;user-select:none;\">{:>width$}  </span>",
+                gutter,
+                i + 1
+            );
+        }
+        match highlighter.highlight_line(line, syntaxes) {
+            Ok(ranges) => {
+                // Writing to a String can't fail
+                let _ = append_highlighted_html_for_styled_line(
+                    &ranges[..],
+                    IncludeBackground::IfDifferent(background),
+                    &mut out,
+                );
+            }
+            Err(_) => out.push_str(&escape_html(line)),
+        }
+    }
+    out.push_str("</pre>\n");
+    out
+}
+
+fn svg_with(syntaxes: &SyntaxSet, theme: &Theme, filename: &str, content: &str) -> Vec<String> {
+    let syntax = syntax_for(syntaxes, filename, content);
+    let mut highlighter = HighlightLines::new(syntax, theme);
+
+    LinesWithEndings::from(content)
+        .map(|line| match highlighter.highlight_line(line, syntaxes) {
+            Ok(ranges) => {
+                let mut out = String::new();
+                for (style, text) in ranges {
+                    let text = text.trim_end_matches(['\n', '\r']);
+                    if text.is_empty() {
+                        continue;
+                    }
+                    let _ = write!(out, "<tspan fill=\"{}\"", css_color(style.foreground));
+                    if style.font_style.contains(FontStyle::BOLD) {
+                        out.push_str(" font-weight=\"bold\"");
+                    }
+                    if style.font_style.contains(FontStyle::ITALIC) {
+                        out.push_str(" font-style=\"italic\"");
+                    }
+                    let _ = write!(out, ">{}</tspan>", escape_html(text));
+                }
+                out
+            }
+            Err(_) => escape_html(line.trim_end_matches(['\n', '\r'])),
+        })
+        .collect()
+}
+
+fn highlight_with(syntaxes: &SyntaxSet, theme: &Theme, filename: &str, content: &str) -> String {
+    let syntax = syntax_for(syntaxes, filename, content);
+    let mut highlighter = HighlightLines::new(syntax, theme);
     let mut out = String::with_capacity(content.len() + 16);
 
     for line in LinesWithEndings::from(content) {
-        match highlighter.highlight_line(line, &SYNTAX_SET) {
+        match highlighter.highlight_line(line, syntaxes) {
             Ok(ranges) => out.push_str(&as_24_bit_terminal_escaped(&ranges[..], false)),
             Err(_) => out.push_str(line),
         }
@@ -57,6 +342,36 @@ pub fn highlight_content(filename: &str, content: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::TempDir;
+
+    const SYNTAX: &str = r#"%YAML 1.2
+---
+name: Gistfile
+file_extensions: [gistfile]
+scope: source.gistfile
+contexts:
+  main:
+    - match: '\brun\b'
+      scope: keyword.control.gistfile
+"#;
+
+    const THEME: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>name</key><string>Paper</string>
+  <key>settings</key>
+  <array>
+    <dict><key>settings</key><dict><key>foreground</key><string>#102030</string></dict></dict>
+    <dict><key>scope</key><string>keyword</string><key>settings</key><dict><key>foreground</key><string>#AA0000</string></dict></dict>
+  </array>
+</dict>
+</plist>
+"#;
+
+    fn load_syntax_str(content: &str) -> SyntaxDefinition {
+        SyntaxDefinition::load_from_str(content, true, None).unwrap()
+    }
 
     #[test]
     fn highlight_content_wraps_output_in_reset_code() {
@@ -80,15 +395,15 @@ mod tests {
 
     #[test]
     fn syntax_for_resolves_known_extensions() {
-        assert_eq!(syntax_for("main.rs", "").name, "Rust");
-        assert_eq!(syntax_for("script.py", "").name, "Python");
-        assert_eq!(syntax_for("script.ts", "").name, "TypeScript");
-        assert_eq!(syntax_for("Cargo.toml", "").name, "TOML");
+        assert_eq!(syntax_for(&SYNTAX_SET, "main.rs", "").name, "Rust");
+        assert_eq!(syntax_for(&SYNTAX_SET, "script.py", "").name, "Python");
+        assert_eq!(syntax_for(&SYNTAX_SET, "script.ts", "").name, "TypeScript");
+        assert_eq!(syntax_for(&SYNTAX_SET, "Cargo.toml", "").name, "TOML");
     }
 
     #[test]
     fn syntax_for_falls_back_to_shebang_when_extension_unknown() {
-        let syntax = syntax_for("hello", "#!/usr/bin/env python3\nprint(1)\n");
+        let syntax = syntax_for(&SYNTAX_SET, "hello", "#!/usr/bin/env python3\nprint(1)\n");
         assert_eq!(syntax.name, "Python");
     }
 
@@ -97,7 +412,119 @@ mod tests {
         // Regression test: find_syntax_for_file() opens the path from disk
         // to sniff the shebang, which fails for in-memory-only content.
         // syntax_for() must resolve purely from the filename + content.
-        let syntax = syntax_for("does-not-exist-on-disk.py", "print(1)\n");
+        let syntax = syntax_for(&SYNTAX_SET, "does-not-exist-on-disk.py", "print(1)\n");
         assert_eq!(syntax.name, "Python");
     }
+
+    #[test]
+    fn custom_themes_and_syntaxes_are_used() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+        std::fs::create_dir_all(config.themes_dir()).unwrap();
+        std::fs::create_dir_all(config.syntaxes_dir()).unwrap();
+        std::fs::write(config.themes_dir().join("Paper.tmTheme"), THEME).unwrap();
+        std::fs::write(
+            config.syntaxes_dir().join("gistfile.sublime-syntax"),
+            SYNTAX,
+        )
+        .unwrap();
+
+        let theme = load_theme(&config, "Paper").unwrap();
+        let syntaxes = build_syntax_set(&[load_syntax(
+            &config.syntaxes_dir().join("gistfile.sublime-syntax"),
+        )
+        .unwrap()]);
+        assert_eq!(
+            syntax_for(&syntaxes, "deploy.gistfile", "").name,
+            "Gistfile"
+        );
+
+        let result = highlight_with(&syntaxes, &theme, "deploy.gistfile", "run it\n");
+        assert!(result.contains("\x1b[38;2;170;0;0mrun"));
+    }
+
+    #[test]
+    fn available_themes_lists_bundled_and_custom_themes() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+        let names = |config: &Config| -> Vec<String> {
+            available_themes(config)
+                .into_iter()
+                .map(|t| t.name)
+                .collect()
+        };
+        let bundled = names(&config);
+        assert!(bundled.iter().any(|n| n == DEFAULT_THEME));
+        assert!(bundled.iter().any(|n| n == "Solarized (light)"));
+        // Palette themes can't be rendered with 24-bit escapes
+        assert!(!bundled.iter().any(|n| n == "ansi" || n == "base16"));
+        assert!(load_theme(&config, "ansi").is_err());
+
+        // A custom theme named like a bundled one replaces it
+        std::fs::create_dir_all(config.themes_dir()).unwrap();
+        std::fs::write(config.themes_dir().join("Nord.tmTheme"), THEME).unwrap();
+        std::fs::write(config.themes_dir().join("Paper.tmTheme"), THEME).unwrap();
+        let themes = available_themes(&config);
+        assert_eq!(themes.len(), bundled.len() + 1);
+        let nord: Vec<_> = themes.iter().filter(|t| t.name == "Nord").collect();
+        assert_eq!(nord.len(), 1);
+        assert!(nord[0].path.is_some());
+        assert_eq!(themes.last().unwrap().name, "Paper");
+
+        assert!(matches!(
+            load_theme(&config, "paper"),
+            Err(GistCacheError::UnknownTheme(_))
+        ));
+        std::fs::write(config.themes_dir().join("Broken.tmTheme"), "<plist>").unwrap();
+        assert!(matches!(
+            load_theme(&config, "Broken"),
+            Err(GistCacheError::HighlightAsset(_))
+        ));
+        let broken = temp_dir.path().join("broken.sublime-syntax");
+        std::fs::write(&broken, "contexts: [").unwrap();
+        assert!(matches!(
+            load_syntax(&broken),
+            Err(GistCacheError::HighlightAsset(_))
+        ));
+    }
+
+    #[test]
+    fn html_with_escapes_and_numbers_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+        std::fs::create_dir_all(config.themes_dir()).unwrap();
+        std::fs::write(config.themes_dir().join("Paper.tmTheme"), THEME).unwrap();
+        let theme = load_theme(&config, "Paper").unwrap();
+        let syntaxes = build_syntax_set(&[load_syntax_str(SYNTAX)]);
+
+        let html = html_with(&syntaxes, &theme, "a.gistfile", "run <a> & b\nx\n", false);
+        assert!(html.starts_with("<pre style=\"background-color:#ffffff;\">"));
+        assert!(html.contains("<span style=\"color:#aa0000;\">run</span>"));
+        assert!(html.contains("&lt;a&gt; &amp; b"));
+        assert!(html.ends_with("</pre>\n"));
+        assert!(!html.contains("user-select"));
+
+        let numbered: Vec<String> = (1..=10).map(|i| format!("line {i}\n")).collect();
+        let html = html_with(&syntaxes, &theme, "a.txt", &numbered.concat(), true);
+        assert!(html.contains("user-select:none;\"> 1  </span>"));
+        assert!(html.contains("user-select:none;\">10  </span>"));
+        assert_eq!(escape_html("'\"<&>"), "&#39;&quot;&lt;&amp;&gt;");
+    }
+
+    #[test]
+    fn svg_with_colors_and_escapes_each_line() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+        std::fs::create_dir_all(config.themes_dir()).unwrap();
+        std::fs::write(config.themes_dir().join("Paper.tmTheme"), THEME).unwrap();
+        let theme = load_theme(&config, "Paper").unwrap();
+        let syntaxes = build_syntax_set(&[load_syntax_str(SYNTAX)]);
+
+        let lines = svg_with(&syntaxes, &theme, "a.gistfile", "run <a> & b\n\nx\n");
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("<tspan fill=\"#aa0000\">run</tspan>"));
+        assert!(lines[0].contains("&lt;a&gt; &amp; b</tspan>"));
+        assert!(!lines[0].contains('\n'));
+        assert_eq!(lines[1], "");
+    }
 }
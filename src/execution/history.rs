@@ -0,0 +1,221 @@
+//! Execution history and the frecency ranking built on it
+//!
+//! `ScriptRunner::run` appends one JSON line per execution to
+//! `history.jsonl` in the cache directory, with everything `rerun` needs
+//! to replay it. Once the file passes `MAX_HISTORY_BYTES` it is rotated to
+//! `history.jsonl.1` (see `cache::jsonl`), so the history keeps roughly the
+//! last two files' worth of runs. Search results are ordered by frecency — how often and
+//! how recently a gist was run — unless `search.frecency` is `false`.
+
+use crate::cache::jsonl::JsonLines;
+use crate::config::Config;
+use crate::error::{GistCacheError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Days after which a run counts half as much toward a gist's frecency
+const HALF_LIFE_DAYS: f64 = 7.0;
+
+/// Size at which `history.jsonl` is rotated to `history.jsonl.1`
+const MAX_HISTORY_BYTES: u64 = 1024 * 1024;
+
+/// One execution of a gist: what ran, how, where, and how it ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the execution started
+    pub timestamp: DateTime<Utc>,
+    pub gist_id: String,
+    /// The file that was executed
+    #[serde(default)]
+    pub filename: String,
+    /// Resolved interpreter, as passed to `ScriptRunner::new`
+    pub interpreter: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_command: Option<String>,
+    #[serde(default)]
+    pub is_shell: bool,
+    /// Executed from a temporary file rather than through stdin
+    #[serde(default)]
+    pub file_based: bool,
+    #[serde(default)]
+    pub interactive: bool,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Working directory of the execution
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    /// Exit code of the script; `None` when it did not run to an exit
+    /// (e.g. the content could not be fetched or the interpreter not started)
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub duration_ms: u64,
+}
+
+/// Exit code to record for an execution that ended with `result`
+pub fn exit_code(result: &Result<()>) -> Option<i32> {
+    match result {
+        Ok(()) => Some(0),
+        Err(GistCacheError::ScriptExited(code)) => Some(*code),
+        Err(_) => None,
+    }
+}
+
+/// The execution history files of a cache directory
+pub struct History {
+    file: JsonLines,
+}
+
+impl History {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            file: JsonLines::new(config.history_file(), MAX_HISTORY_BYTES),
+        }
+    }
+
+    /// Append one execution, rotating the file first when it is full
+    pub fn record(&self, entry: &HistoryEntry) -> Result<()> {
+        self.file.append(entry)
+    }
+
+    /// All executions in the rotated and the current file, oldest first
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>> {
+        self.file.entries()
+    }
+}
+
+/// Frecency score of every gist in `entries`: each run counts 1 when it
+/// just happened and half as much every `HALF_LIFE_DAYS` after, so a gist
+/// run daily outranks one run often a while ago, which in turn outranks one
+/// run once recently
+pub fn frecency_scores(entries: &[HistoryEntry], now: DateTime<Utc>) -> HashMap<String, f64> {
+    let mut scores = HashMap::new();
+    for entry in entries {
+        // Entries from a clock running ahead count as just now
+        let age_days = (now - entry.timestamp).num_seconds().max(0) as f64 / 86_400.0;
+        *scores.entry(entry.gist_id.clone()).or_insert(0.0) +=
+            0.5f64.powf(age_days / HALF_LIFE_DAYS);
+    }
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::fs;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn entry(gist_id: &str, days_ago: i64, now: DateTime<Utc>) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: now - Duration::days(days_ago),
+            gist_id: gist_id.to_string(),
+            filename: "run.sh".to_string(),
+            interpreter: "bash".to_string(),
+            run_command: None,
+            is_shell: true,
+            file_based: false,
+            interactive: false,
+            args: vec![],
+            cwd: None,
+            exit_code: Some(0),
+            duration_ms: 10,
+        }
+    }
+
+    #[test]
+    fn test_history_record_and_read() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+        let history = History::new(&config);
+        assert!(history.entries().unwrap().is_empty());
+
+        let now = Utc::now();
+        let mut failed = entry("failed", 0, now);
+        failed.interpreter = "python3".to_string();
+        failed.run_command = Some("uv run".to_string());
+        failed.args = vec!["--name".to_string(), "two words".to_string()];
+        failed.cwd = Some(PathBuf::from("/tmp/project"));
+        failed.exit_code = exit_code(&Err(GistCacheError::ScriptExited(3)));
+        let mut offline = entry("offline", 0, now);
+        offline.exit_code = exit_code(&Err(GistCacheError::NotAuthenticated));
+
+        history.record(&entry("ok", 0, now)).unwrap();
+        history.record(&failed).unwrap();
+        history.record(&offline).unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(config.history_file())
+            .unwrap()
+            .write_all(b"{\"timestamp\":")
+            .unwrap();
+
+        let entries = history.entries().unwrap();
+        let codes: Vec<_> = entries.iter().map(|e| e.exit_code).collect();
+        assert_eq!(codes, vec![Some(0), Some(3), None]);
+        assert_eq!(entries[1], failed);
+    }
+
+    #[test]
+    fn test_history_reads_entries_without_the_replay_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+        fs::write(
+            config.history_file(),
+            "{\"timestamp\":\"2026-01-01T00:00:00Z\",\"gist_id\":\"abc\",\"interpreter\":\"bash\",\"exit_code\":0}\n",
+        )
+        .unwrap();
+
+        let entries = History::new(&config).entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].gist_id, "abc");
+        assert!(entries[0].args.is_empty());
+        assert_eq!(entries[0].cwd, None);
+    }
+
+    #[test]
+    fn test_history_rotates_by_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::for_test(temp_dir.path());
+        let history = History {
+            file: JsonLines::new(config.history_file(), 1),
+        };
+        let now = Utc::now();
+
+        // Every record finds a full file and rotates it first, so only the
+        // last two entries survive, still in order
+        for id in ["first", "second", "third"] {
+            history.record(&entry(id, 0, now)).unwrap();
+        }
+        assert!(config.history_file().with_extension("jsonl.1").exists());
+        let ids: Vec<String> = history
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.gist_id)
+            .collect();
+        assert_eq!(ids, vec!["second", "third"]);
+    }
+
+    #[test]
+    fn test_frecency_blends_frequency_and_recency() {
+        let now = Utc::now();
+        let mut entries = vec![entry("once-today", 0, now)];
+        // Run every day of the last week
+        entries.extend((0..7).map(|day| entry("daily", day, now)));
+        // Run often, but two months ago
+        entries.extend((0..10).map(|_| entry("stale", 60, now)));
+
+        let scores = frecency_scores(&entries, now);
+        assert!((scores["once-today"] - 1.0).abs() < 1e-9);
+        assert!(scores["daily"] > scores["once-today"]);
+        assert!(scores["once-today"] > scores["stale"]);
+
+        // A week halves a run's weight; future timestamps count as now
+        let scores = frecency_scores(&[entry("a", 7, now), entry("b", -1, now)], now);
+        assert!((scores["a"] - 0.5).abs() < 1e-9);
+        assert!((scores["b"] - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,366 @@
+//! Which file of a multi-file gist `run` executes
+//!
+//! In order: the files `run --file` names (a filename or a glob); the only
+//! file; the file a `[main: <filename>]` marker in the description names;
+//! the only `main.*` file; the file chosen for the gist before, remembered
+//! in `main_files.json` in the cache directory; and last a guess from the
+//! file extensions, the interpreter's if it has one. When several files fit
+//! the guess, `run` asks which one and remembers the answer.
+
+use crate::cache::storage::write_atomic;
+use crate::cache::types::{GistFile, GistInfo};
+use crate::config::{Config, SUPPORTED_EXTENSIONS};
+use crate::error::{GistCacheError, Result};
+use globset::Glob;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// The file to run, or the files to choose from
+#[derive(Debug)]
+pub enum MainFile<'a> {
+    Chosen(&'a GistFile),
+    /// Several files fit; the first one is the guess
+    Ambiguous(Vec<&'a GistFile>),
+}
+
+impl<'a> MainFile<'a> {
+    /// The chosen file, or the guess among several
+    pub fn file(&self) -> &'a GistFile {
+        match self {
+            Self::Chosen(file) => file,
+            Self::Ambiguous(files) => files[0],
+        }
+    }
+}
+
+/// Extension of the scripts `interpreter` runs
+fn interpreter_extension(interpreter: &str) -> Option<&'static str> {
+    let extension = match interpreter {
+        "bash" | "sh" | "zsh" => "sh",
+        "python" | "python3" | "uv" => "py",
+        "ruby" => "rb",
+        "node" => "js",
+        "perl" => "pl",
+        "php" => "php",
+        "pwsh" | "powershell" => "ps1",
+        "ts-node" | "deno" | "bun" => "ts",
+        _ => return None,
+    };
+    Some(extension)
+}
+
+/// Whether the guess considers files with `ext` scripts: the supported
+/// extensions, apart from the `*` wildcard
+fn is_script_extension(ext: &str) -> bool {
+    ext != "*" && SUPPORTED_EXTENSIONS.contains(&ext)
+}
+
+fn extension(filename: &str) -> Option<&str> {
+    filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext)
+        .filter(|ext| !ext.is_empty())
+}
+
+/// The files of `gist` that `pattern` names: the file called `pattern`,
+/// otherwise the files matching it as a glob (`*.py`, `deploy-*`)
+///
+/// # Errors
+/// Returns `NoMatchingFile` if there are none, `Config` if `pattern` is not
+/// a valid glob
+pub fn matching_files<'a>(gist: &'a GistInfo, pattern: &str) -> Result<Vec<&'a GistFile>> {
+    if let Some(file) = gist.files.iter().find(|f| f.filename == pattern) {
+        return Ok(vec![file]);
+    }
+
+    let matcher = Glob::new(pattern)
+        .map_err(|e| GistCacheError::Config(format!("Invalid --file pattern: {}", e)))?
+        .compile_matcher();
+    let files: Vec<&GistFile> = gist
+        .files
+        .iter()
+        .filter(|f| matcher.is_match(&f.filename))
+        .collect();
+    if files.is_empty() {
+        return Err(GistCacheError::NoMatchingFile(
+            pattern.to_string(),
+            gist.id.clone(),
+        ));
+    }
+    Ok(files)
+}
+
+/// The entry point `gist` declares: the file a `[main: <filename>]` marker
+/// in its description names, otherwise its only `main.*` file
+pub fn declared_file(gist: &GistInfo) -> Option<&GistFile> {
+    let description = gist.description.as_deref().unwrap_or_default();
+    let marked = description.match_indices('[').find_map(|(start, _)| {
+        let rest = &description[start + 1..];
+        let (key, value) = rest[..rest.find(']')?].split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case("main")
+            .then(|| value.trim())
+    });
+    if let Some(file) = marked.and_then(|name| gist.files.iter().find(|f| f.filename == name)) {
+        return Some(file);
+    }
+
+    let mut mains = gist.files.iter().filter(|f| {
+        f.filename
+            .rsplit_once('.')
+            .is_some_and(|(stem, _)| stem == "main")
+    });
+    match (mains.next(), mains.next()) {
+        (Some(file), None) => Some(file),
+        _ => None,
+    }
+}
+
+/// Guess the file of `gist` to run from the extensions: the files
+/// `interpreter` runs if there are any, otherwise every script. Without
+/// scripts, the first file.
+fn guess<'a>(gist: &'a GistInfo, interpreter: Option<&str>) -> MainFile<'a> {
+    let with_extension = |wanted: &dyn Fn(&str) -> bool| -> Vec<&'a GistFile> {
+        gist.files
+            .iter()
+            .filter(|f| extension(&f.filename).is_some_and(wanted))
+            .collect()
+    };
+
+    let mut candidates = interpreter
+        .and_then(interpreter_extension)
+        .map(|wanted| with_extension(&|ext| ext == wanted))
+        .unwrap_or_default();
+    if candidates.is_empty() {
+        candidates = with_extension(&is_script_extension);
+    }
+    match candidates.len() {
+        0 => MainFile::Chosen(&gist.files[0]),
+        1 => MainFile::Chosen(candidates[0]),
+        _ => MainFile::Ambiguous(candidates),
+    }
+}
+
+/// The file of `gist` to run, as described in the module documentation.
+/// `pattern` is `run --file`, `remembered` the file chosen before.
+///
+/// # Errors
+/// Returns `NoMatchingFile` if `pattern` names no file of the gist, and
+/// `Execution` if the gist has no files
+pub fn resolve<'a>(
+    gist: &'a GistInfo,
+    pattern: Option<&str>,
+    interpreter: Option<&str>,
+    remembered: Option<&str>,
+) -> Result<MainFile<'a>> {
+    if let Some(pattern) = pattern {
+        let mut files = matching_files(gist, pattern)?;
+        return Ok(if files.len() == 1 {
+            MainFile::Chosen(files.remove(0))
+        } else {
+            MainFile::Ambiguous(files)
+        });
+    }
+
+    match gist.files.as_slice() {
+        [] => Err(GistCacheError::Execution(format!(
+            "Gist {} has no files",
+            gist.id
+        ))),
+        [file] => Ok(MainFile::Chosen(file)),
+        _ => {
+            if let Some(file) = declared_file(gist) {
+                return Ok(MainFile::Chosen(file));
+            }
+            if let Some(file) =
+                remembered.and_then(|name| gist.files.iter().find(|f| f.filename == name))
+            {
+                return Ok(MainFile::Chosen(file));
+            }
+            Ok(guess(gist, interpreter))
+        }
+    }
+}
+
+/// The file `run` executes for `gist` with `interpreter` (`--interpreter`
+/// or an alias's) when no `--file` is given, taking the remembered choice
+/// into account; the guess when several files fit and nothing was chosen
+///
+/// For listings (`tag list`, `alias list`), so they show the file `run`
+/// executes. `None` if the gist has no files.
+pub fn file_to_run<'a>(
+    gist: &'a GistInfo,
+    interpreter: Option<&str>,
+    remembered: &RememberedFiles,
+) -> Option<&'a GistFile> {
+    // A listing never fails over a choice file that can't be read
+    let remembered = remembered.get(&gist.id).ok().flatten();
+    resolve(gist, None, interpreter, remembered.as_deref())
+        .ok()
+        .map(|main_file| main_file.file())
+}
+
+/// The file chosen for each gist when its guess was ambiguous, in
+/// `main_files.json` in the cache directory
+pub struct RememberedFiles {
+    path: PathBuf,
+}
+
+impl RememberedFiles {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            path: config.main_files_file(),
+        }
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, String>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// The file chosen for `gist_id`, if any
+    pub fn get(&self, gist_id: &str) -> Result<Option<String>> {
+        Ok(self.load()?.remove(gist_id))
+    }
+
+    /// Remember `filename` as the file to run of `gist_id`
+    pub fn set(&self, gist_id: &str, filename: &str) -> Result<()> {
+        let mut files = self.load()?;
+        files.insert(gist_id.to_string(), filename.to_string());
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(&self.path, &serde_json::to_string_pretty(&files)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn gist(description: &str, filenames: &[&str]) -> GistInfo {
+        GistInfo {
+            id: "abc".to_string(),
+            description: Some(description.to_string()),
+            files: filenames
+                .iter()
+                .map(|name| GistFile {
+                    filename: name.to_string(),
+                    language: None,
+                    size: 10,
+                    revision: None,
+                })
+                .collect(),
+            updated_at: Utc::now(),
+            public: true,
+            html_url: String::new(),
+        }
+    }
+
+    fn chosen(main_file: Result<MainFile>) -> String {
+        match main_file.unwrap() {
+            MainFile::Chosen(file) => file.filename.clone(),
+            other => panic!("expected one file, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_resolve_order() {
+        let files = ["util.py", "app.py", "setup.sh", "config.json"];
+        let plain = gist("Tool", &files);
+
+        // Several scripts are ambiguous; the interpreter narrows them
+        let main_file = resolve(&plain, None, None, None).unwrap();
+        assert!(matches!(&main_file, MainFile::Ambiguous(f) if f.len() == 3));
+        assert_eq!(main_file.file().filename, "util.py");
+        assert_eq!(
+            chosen(resolve(&plain, None, Some("bash"), None)),
+            "setup.sh"
+        );
+        let main_file = resolve(&plain, None, Some("python3"), None).unwrap();
+        assert!(matches!(main_file, MainFile::Ambiguous(f) if f.len() == 2));
+
+        // A remembered choice settles it, unless the file is gone
+        assert_eq!(
+            chosen(resolve(&plain, None, None, Some("app.py"))),
+            "app.py"
+        );
+        let main_file = resolve(&plain, None, None, Some("gone.py")).unwrap();
+        assert!(matches!(main_file, MainFile::Ambiguous(_)));
+
+        // Declarations win over remembered choices
+        let marked = gist("Tool [main: app.py] #python", &files);
+        assert_eq!(
+            chosen(resolve(&marked, None, None, Some("util.py"))),
+            "app.py"
+        );
+        let conventional = gist("Tool", &["util.py", "main.py", "README.md"]);
+        assert_eq!(chosen(resolve(&conventional, None, None, None)), "main.py");
+        let two_mains = gist("Tool", &["main.py", "main.sh"]);
+        assert!(declared_file(&two_mains).is_none());
+
+        // --file wins over everything
+        assert_eq!(
+            chosen(resolve(&marked, Some("setup.sh"), None, None)),
+            "setup.sh"
+        );
+        let main_file = resolve(&marked, Some("*.py"), None, None).unwrap();
+        assert!(matches!(main_file, MainFile::Ambiguous(f) if f.len() == 2));
+        assert!(matches!(
+            resolve(&marked, Some("*.rb"), None, None),
+            Err(GistCacheError::NoMatchingFile(pattern, id)) if pattern == "*.rb" && id == "abc"
+        ));
+
+        // Without scripts, the first file
+        let data = gist("Data", &["a.csv", "b.json"]);
+        assert_eq!(chosen(resolve(&data, None, None, None)), "a.csv");
+    }
+
+    #[test]
+    fn test_remembered_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let remembered = RememberedFiles {
+            path: temp_dir.path().join("cache").join("main_files.json"),
+        };
+        assert_eq!(remembered.get("abc").unwrap(), None);
+        remembered.set("abc", "app.py").unwrap();
+        remembered.set("def", "run.sh").unwrap();
+        remembered.set("abc", "util.py").unwrap();
+        assert_eq!(remembered.get("abc").unwrap().as_deref(), Some("util.py"));
+        assert_eq!(remembered.get("def").unwrap().as_deref(), Some("run.sh"));
+    }
+
+    #[test]
+    fn test_file_to_run() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let remembered = RememberedFiles {
+            path: temp_dir.path().join("main_files.json"),
+        };
+        let plain = gist("Tool", &["util.py", "app.py", "setup.sh"]);
+
+        // Same file as `run`: the guess, narrowed by the interpreter
+        assert_eq!(
+            file_to_run(&plain, None, &remembered).unwrap().filename,
+            "util.py"
+        );
+        assert_eq!(
+            file_to_run(&plain, Some("bash"), &remembered)
+                .unwrap()
+                .filename,
+            "setup.sh"
+        );
+
+        // ...or the file chosen before
+        remembered.set("abc", "app.py").unwrap();
+        assert_eq!(
+            file_to_run(&plain, None, &remembered).unwrap().filename,
+            "app.py"
+        );
+        assert!(file_to_run(&gist("Empty", &[]), None, &remembered).is_none());
+    }
+}
@@ -1,4 +1,8 @@
+pub mod actions;
+pub mod export;
 pub mod highlight;
+pub mod history;
+pub mod main_file;
 pub mod runner;
 
 pub use runner::{RunOptions, ScriptRunner};
@@ -1,15 +1,24 @@
-use crate::cache::ContentCache;
 use crate::cache::types::GistInfo;
+use crate::cache::{ContentStore, open_content_store};
 use crate::config::Config;
 use crate::error::{GistCacheError, Result};
+use crate::execution::actions::download_gist;
 use crate::execution::highlight::highlight_content;
+use crate::execution::history::{self, History, HistoryEntry};
+use crate::execution::main_file;
 use crate::github::GitHubApi;
 use colored::Colorize;
 use std::fs;
 use std::io::Write;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
 use std::process::{Command, Stdio};
+use tempfile::TempDir;
+
+/// Environment variable holding the per-run working directory (every file of
+/// the Gist) for scripts run from one
+pub const WORKDIR_ENV: &str = "GIST_CACHE_WORKDIR";
 
 /// Options for script execution
 pub struct RunOptions {
@@ -17,6 +26,8 @@ pub struct RunOptions {
     pub preview: bool,
     pub download: bool,
     pub force_file_based: bool,
+    /// Leave the per-run working directory in place after the run
+    pub keep_workdir: bool,
 }
 
 pub struct ScriptRunner {
@@ -27,6 +38,8 @@ pub struct ScriptRunner {
     options: RunOptions,
     args: Vec<String>,
     config: Config,
+    /// The file to execute; guessed from the interpreter when unset
+    main_file: Option<String>,
 }
 
 impl ScriptRunner {
@@ -47,9 +60,16 @@ impl ScriptRunner {
             options,
             args,
             config,
+            main_file: None,
         }
     }
 
+    /// Execute `filename` instead of guessing the file from the interpreter
+    pub fn with_main_file(mut self, filename: String) -> Self {
+        self.main_file = Some(filename);
+        self
+    }
+
     pub fn run(&self) -> Result<()> {
         // Display gist info
         self.display_info();
@@ -62,7 +82,10 @@ impl ScriptRunner {
             return Ok(());
         }
 
+        let started_at = chrono::Utc::now();
+        let started = std::time::Instant::now();
         let result = self.execute();
+        self.record_execution(&result, started_at, started.elapsed());
 
         // If download option is specified, download regardless of execution result
         if self.options.download {
@@ -72,6 +95,39 @@ impl ScriptRunner {
         result
     }
 
+    /// Append this execution to the history (`history`, `rerun`, frecency
+    /// ranking); a failure to do so only warns
+    fn record_execution(
+        &self,
+        result: &Result<()>,
+        started_at: chrono::DateTime<chrono::Utc>,
+        duration: std::time::Duration,
+    ) {
+        let entry = HistoryEntry {
+            timestamp: started_at,
+            gist_id: self.gist.id.clone(),
+            filename: self
+                .select_main_file()
+                .map(|f| f.filename.clone())
+                .unwrap_or_default(),
+            interpreter: self.interpreter.clone(),
+            run_command: self.run_command.clone(),
+            is_shell: self.is_shell,
+            file_based: self.options.force_file_based,
+            interactive: self.options.interactive,
+            args: self.args.clone(),
+            cwd: std::env::current_dir().ok(),
+            exit_code: history::exit_code(result),
+            duration_ms: duration.as_millis().min(u64::MAX as u128) as u64,
+        };
+        if let Err(e) = History::new(&self.config).record(&entry) {
+            eprintln!(
+                "{}",
+                format!("Warning: Failed to record execution history: {}", e).yellow()
+            );
+        }
+    }
+
     fn display_info(&self) {
         println!();
         println!(
@@ -99,26 +155,13 @@ impl ScriptRunner {
         println!("{}", "=== Gist Content ===".cyan().bold());
 
         let colorize = console::Term::stdout().features().colors_supported();
+        let content_cache = open_content_store(&self.config)?;
 
         for file in &self.gist.files {
             println!("\n{}", format!("--- {} ---", file.filename).yellow().bold());
 
-            // Check cache
-            let content_cache = ContentCache::new(self.config.contents_dir.clone());
-
-            let content = if content_cache.exists(&self.gist.id, &file.filename) {
-                // Load from cache
-                match content_cache.read(&self.gist.id, &file.filename) {
-                    Ok(c) => c,
-                    Err(_) => {
-                        // Fetch from API if cache read fails
-                        GitHubApi::new().fetch_gist_content(&self.gist.id, &file.filename)?
-                    }
-                }
-            } else {
-                // Fetch from API
-                GitHubApi::new().fetch_gist_content(&self.gist.id, &file.filename)?
-            };
+            // Same as a run: never show content that fails its hash check
+            let content = self.load_content(content_cache.as_ref(), &file.filename)?;
 
             if colorize {
                 println!("{}", highlight_content(&file.filename, &content));
@@ -140,128 +183,173 @@ impl ScriptRunner {
         );
 
         // Check cache and fetch content
-        let content_cache = ContentCache::new(self.config.contents_dir.clone());
-
-        let content = if content_cache.exists(&self.gist.id, &main_file.filename) {
-            // Load from cache
-            match content_cache.read(&self.gist.id, &main_file.filename) {
-                Ok(c) => {
-                    if std::env::var("GIST_CACHE_VERBOSE").is_ok() {
-                        println!("{}", "  → Loaded from cache".green());
-                    }
-                    c
-                }
-                Err(e) => {
-                    // Self-healing principle: Fetch from API if cache read fails
-                    eprintln!(
-                        "{}",
-                        format!("  Warning: Cache read failed, fetching from API: {}", e).yellow()
-                    );
-                    let fetched =
-                        GitHubApi::new().fetch_gist_content(&self.gist.id, &main_file.filename)?;
-
-                    // Try to save to cache if fetch succeeds
-                    let _ = content_cache.write(&self.gist.id, &main_file.filename, &fetched);
+        let content_cache = open_content_store(&self.config)?;
+        let content = self.load_content(content_cache.as_ref(), &main_file.filename)?;
 
-                    fetched
+        // Ensure consistency in interactive mode:
+        // Whether loading from cache or fetching from API,
+        // always execute via a working directory to unify behavior.
+        // A multi-file gist always does, so its main file can import or
+        // read the other files.
+        let mut files = vec![(main_file.filename.clone(), content)];
+        let execution_result = if self.options.force_file_based
+            || self.options.interactive
+            || self.is_shell
+            || self.gist.files.len() > 1
+        {
+            for file in &self.gist.files {
+                if file.filename != main_file.filename {
+                    let content = self.load_content(content_cache.as_ref(), &file.filename)?;
+                    files.push((file.filename.clone(), content));
                 }
             }
+            self.execute_in_workdir(&files)
         } else {
-            // Fetch from API
-            println!(
-                "{}",
-                "  Info: Cache does not exist, fetching from GitHub API...".yellow()
-            );
-            GitHubApi::new().fetch_gist_content(&self.gist.id, &main_file.filename)?
+            self.execute_direct(&files[0].1)
         };
 
-        // Ensure consistency in interactive mode:
-        // Whether loading from cache or fetching from API,
-        // always execute via temporary file to unify behavior
-        let execution_result =
-            if self.options.force_file_based || self.options.interactive || self.is_shell {
-                self.execute_via_temp_file(&content, &main_file.filename)
-            } else {
-                self.execute_direct(&content)
-            };
-
         // Save to cache only if execution succeeds
         if execution_result.is_ok() {
-            // Save only if cache doesn't exist (don't overwrite existing cache)
-            if !content_cache.exists(&self.gist.id, &main_file.filename) {
-                match content_cache.write(&self.gist.id, &main_file.filename, &content) {
-                    Ok(_) => {
-                        if std::env::var("GIST_CACHE_VERBOSE").is_ok() {
-                            println!(
-                                "{}",
-                                format!(
-                                    "  → Saved to cache: {}",
-                                    self.config
-                                        .contents_dir
-                                        .join(&self.gist.id)
-                                        .join(&main_file.filename)
-                                        .display()
-                                )
-                                .green()
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        // Cache save failure is warning only (execution succeeded)
-                        eprintln!(
-                            "{}",
-                            format!("  Warning: Failed to save cache: {}", e).yellow()
-                        );
-                    }
-                }
+            for (filename, content) in &files {
+                self.save_content(content_cache.as_ref(), filename, content);
             }
         }
 
         execution_result
     }
 
-    fn select_main_file(&self) -> Result<&crate::cache::types::GistFile> {
-        if self.gist.files.len() == 1 {
-            return Ok(&self.gist.files[0]);
+    /// Content of `filename`, from the cache when it is there and intact,
+    /// otherwise from GitHub
+    fn load_content(&self, content_cache: &dyn ContentStore, filename: &str) -> Result<String> {
+        // Status lines go to stderr, so `run --preview > file` captures only
+        // the content
+        if !content_cache.exists(&self.gist.id, filename) {
+            // Fetch from API
+            eprintln!(
+                "{}",
+                "  Info: Cache does not exist, fetching from GitHub API...".yellow()
+            );
+            return GitHubApi::new().fetch_gist_content(&self.gist.id, filename);
         }
 
-        // Try to find a matching file based on interpreter
-        let preferred_extension = match self.interpreter.as_str() {
-            "bash" | "sh" => ".sh",
-            "python" | "python3" => ".py",
-            "ruby" => ".rb",
-            "node" => ".js",
-            "perl" => ".pl",
-            "php" => ".php",
-            "pwsh" | "powershell" => ".ps1",
-            "ts-node" | "deno" | "bun" => ".ts",
-            _ => "",
-        };
+        // Load from cache, refusing content that no longer matches its recorded hash
+        match content_cache.read_verified(&self.gist.id, filename) {
+            Ok(c) => {
+                if std::env::var("GIST_CACHE_VERBOSE").is_ok() {
+                    eprintln!("{}", "  → Loaded from cache".green());
+                }
+                Ok(c)
+            }
+            Err(e) => {
+                // Self-healing principle: Fetch from API if cache read fails
+                eprintln!(
+                    "{}",
+                    format!("  Warning: Cache read failed, fetching from API: {}", e).yellow()
+                );
+                // Never fall back to running modified content
+                let fetched = GitHubApi::new()
+                    .fetch_gist_content(&self.gist.id, filename)
+                    .map_err(|fetch_err| match e {
+                        GistCacheError::IntegrityCheckFailed(_) => e,
+                        _ => fetch_err,
+                    })?;
+
+                // Try to save to cache if fetch succeeds
+                let _ = content_cache.write(&self.gist.id, filename, &fetched);
+
+                Ok(fetched)
+            }
+        }
+    }
+
+    /// Cache `content` of `filename` unless it is cached already (don't
+    /// overwrite existing cache); a failure only warns
+    fn save_content(&self, content_cache: &dyn ContentStore, filename: &str, content: &str) {
+        if content_cache.exists(&self.gist.id, filename) {
+            return;
+        }
+        match content_cache.write(&self.gist.id, filename, content) {
+            Ok(_) => {
+                if std::env::var("GIST_CACHE_VERBOSE").is_ok() {
+                    println!(
+                        "{}",
+                        format!("  → Saved to cache: {}/{}", self.gist.id, filename).green()
+                    );
+                }
+            }
+            Err(e) => {
+                // Cache save failure is warning only (execution succeeded)
+                eprintln!(
+                    "{}",
+                    format!("  Warning: Failed to save cache: {}", e).yellow()
+                );
+            }
+        }
+    }
 
-        if !preferred_extension.is_empty()
-            && let Some(file) = self
+    fn select_main_file(&self) -> Result<&crate::cache::types::GistFile> {
+        if let Some(filename) = &self.main_file {
+            return self
                 .gist
                 .files
                 .iter()
-                .find(|f| f.filename.ends_with(preferred_extension))
-        {
-            return Ok(file);
+                .find(|f| &f.filename == filename)
+                .ok_or_else(|| {
+                    GistCacheError::NoMatchingFile(filename.clone(), self.gist.id.clone())
+                });
         }
 
-        // Default to first file
-        Ok(&self.gist.files[0])
+        let main_file = main_file::resolve(&self.gist, None, Some(&self.interpreter), None)?;
+        Ok(main_file.file())
     }
 
-    /// Execute via temporary file (interactive mode, shell scripts, file-based interpreters)
+    /// Execute the main file, `files[0]`, from a new working directory
+    /// holding every file of `files` (interactive mode, shell scripts,
+    /// file-based interpreters, multi-file gists). The directory is removed
+    /// afterwards unless `keep_workdir` is set.
     ///
     /// Important: Using this function ensures consistent behavior for
     /// interactive scripts regardless of whether cache exists or not
-    fn execute_via_temp_file(&self, content: &str, filename: &str) -> Result<()> {
-        // Create temporary file
-        let temp_dir = std::env::temp_dir();
-        let temp_file = temp_dir.join(filename);
+    fn execute_in_workdir(&self, files: &[(String, String)]) -> Result<()> {
+        let workdir = self.create_workdir()?;
+        let result = self.run_in_workdir(workdir.path(), files);
+
+        // Otherwise the directory is removed when `workdir` is dropped
+        if self.options.keep_workdir {
+            let kept = workdir.keep();
+            println!(
+                "{}",
+                format!("Kept working directory: {}", kept.display()).cyan()
+            );
+        }
 
-        fs::write(&temp_file, content)?;
+        result
+    }
+
+    /// Create a new directory in the system temporary directory that only
+    /// the current user can access, for this run's files
+    fn create_workdir(&self) -> Result<TempDir> {
+        let prefix = format!("gist-cache-rs-{}-", self.gist.id);
+        let mut builder = tempfile::Builder::new();
+        builder.prefix(&prefix);
+        #[cfg(unix)]
+        builder.permissions(fs::Permissions::from_mode(0o700));
+        Ok(builder.tempdir()?)
+    }
+
+    fn run_in_workdir(&self, workdir: &Path, files: &[(String, String)]) -> Result<()> {
+        for (filename, content) in files {
+            // Gist filenames have no directories; refuse anything that
+            // would land outside the working directory
+            if Path::new(filename).file_name() != Some(filename.as_ref()) {
+                return Err(GistCacheError::Execution(format!(
+                    "Refusing to write '{}' outside the working directory",
+                    filename
+                )));
+            }
+            fs::write(workdir.join(filename), content)?;
+        }
+        let temp_file = workdir.join(&files[0].0);
 
         // Make executable for shell scripts (Unix only)
         #[cfg(unix)]
@@ -308,6 +396,11 @@ impl ScriptRunner {
             cmd.arg(arg);
         }
 
+        // The script keeps the caller's directory, so relative paths in its
+        // arguments resolve as typed; it finds its other files through
+        // `GIST_CACHE_WORKDIR` (or next to its own path)
+        cmd.env(WORKDIR_ENV, workdir);
+
         // Run with inherited stdio for interactive mode
         // Inherit stdin in interactive mode so commands like `read` work properly
         let status = cmd
@@ -316,14 +409,8 @@ impl ScriptRunner {
             .stderr(Stdio::inherit())
             .status()?;
 
-        // Clean up
-        let _ = fs::remove_file(&temp_file);
-
         if !status.success() {
-            return Err(GistCacheError::Execution(format!(
-                "Script exited with code: {}",
-                status.code().unwrap_or(-1)
-            )));
+            return Err(GistCacheError::ScriptExited(status.code().unwrap_or(-1)));
         }
 
         Ok(())
@@ -396,10 +483,7 @@ impl ScriptRunner {
         let status = child.wait()?;
 
         if !status.success() {
-            return Err(GistCacheError::Execution(format!(
-                "Script exited with code: {}",
-                status.code().unwrap_or(-1)
-            )));
+            return Err(GistCacheError::ScriptExited(status.code().unwrap_or(-1)));
         }
 
         Ok(())
@@ -409,35 +493,7 @@ impl ScriptRunner {
         println!();
         println!("{}", "=== Downloading Files ===".cyan().bold());
 
-        // Ensure download directory exists
-        self.config.ensure_download_dir()?;
-
-        let content_cache = ContentCache::new(self.config.contents_dir.clone());
-
-        for file in &self.gist.files {
-            // Load from cache or fetch from API
-            let content = if content_cache.exists(&self.gist.id, &file.filename) {
-                match content_cache.read(&self.gist.id, &file.filename) {
-                    Ok(c) => c,
-                    Err(_) => {
-                        // Fetch from API if cache read fails
-                        GitHubApi::new().fetch_gist_content(&self.gist.id, &file.filename)?
-                    }
-                }
-            } else {
-                // Fetch from API
-                let fetched = GitHubApi::new().fetch_gist_content(&self.gist.id, &file.filename)?;
-
-                // Also create cache when downloading
-                let _ = content_cache.write(&self.gist.id, &file.filename, &fetched);
-
-                fetched
-            };
-
-            // Save to download folder
-            let download_path = self.config.download_dir.join(&file.filename);
-            fs::write(&download_path, &content)?;
-
+        for download_path in download_gist(&self.config, &self.gist)? {
             println!(
                 "{}",
                 format!("  ✓ Download complete: {}", download_path.display()).green()
@@ -476,11 +532,13 @@ mod tests {
                     filename: "test.sh".to_string(),
                     language: Some("Shell".to_string()),
                     size: 100,
+                    revision: None,
                 },
                 GistFile {
                     filename: "test.py".to_string(),
                     language: Some("Python".to_string()),
                     size: 200,
+                    revision: None,
                 },
             ],
             updated_at: Utc::now(),
@@ -516,6 +574,7 @@ mod tests {
                 preview: false,
                 download: false,
                 force_file_based: false,
+                keep_workdir: false,
             },
             vec![],
             config,
@@ -532,6 +591,7 @@ mod tests {
             preview: false,
             download: true,
             force_file_based: false,
+            keep_workdir: false,
         };
 
         assert!(options.interactive);
@@ -548,6 +608,7 @@ mod tests {
             filename: "single.sh".to_string(),
             language: Some("Shell".to_string()),
             size: 100,
+            revision: None,
         }];
 
         let runner = ScriptRunner::new(
@@ -560,6 +621,7 @@ mod tests {
                 preview: false,
                 download: false,
                 force_file_based: false,
+                keep_workdir: false,
             },
             vec![],
             config,
@@ -584,6 +646,7 @@ mod tests {
                 preview: false,
                 download: false,
                 force_file_based: false,
+                keep_workdir: false,
             },
             vec![],
             config,
@@ -609,6 +672,7 @@ mod tests {
                 preview: false,
                 download: false,
                 force_file_based: false,
+                keep_workdir: false,
             },
             vec![],
             config,
@@ -633,6 +697,7 @@ mod tests {
                 preview: false,
                 download: false,
                 force_file_based: false,
+                keep_workdir: false,
             },
             vec![],
             config,
@@ -658,6 +723,7 @@ mod tests {
                 preview: false,
                 download: false,
                 force_file_based: false,
+                keep_workdir: false,
             },
             vec![],
             config.clone(),
@@ -676,6 +742,7 @@ mod tests {
                 preview: false,
                 download: false,
                 force_file_based: false,
+                keep_workdir: false,
             },
             vec![],
             config.clone(),
@@ -693,6 +760,7 @@ mod tests {
                 preview: false,
                 download: false,
                 force_file_based: false,
+                keep_workdir: false,
             },
             vec![],
             config.clone(),
@@ -707,6 +775,7 @@ mod tests {
             preview: true,
             download: true,
             force_file_based: true,
+            keep_workdir: false,
         };
 
         assert!(options.interactive);
@@ -732,6 +801,7 @@ mod tests {
                 preview: false,
                 download: false,
                 force_file_based: false,
+                keep_workdir: false,
             },
             vec![],
             config,
@@ -743,6 +813,34 @@ mod tests {
         assert_eq!(main_file.filename, "test.sh");
     }
 
+    #[test]
+    fn test_select_main_file_with_main_file() {
+        let runner = ScriptRunner::new(
+            create_test_gist(),
+            "bash".to_string(),
+            None,
+            true,
+            RunOptions {
+                interactive: false,
+                preview: false,
+                download: false,
+                force_file_based: false,
+                keep_workdir: false,
+            },
+            vec![],
+            create_test_config(),
+        );
+
+        // The caller's choice wins over the interpreter's extension
+        let runner = runner.with_main_file("test.py".to_string());
+        assert_eq!(runner.select_main_file().unwrap().filename, "test.py");
+        let runner = runner.with_main_file("gone.sh".to_string());
+        assert!(matches!(
+            runner.select_main_file(),
+            Err(GistCacheError::NoMatchingFile(name, _)) if name == "gone.sh"
+        ));
+    }
+
     #[test]
     fn test_run_options_preview_mode() {
         let options = RunOptions {
@@ -750,6 +848,7 @@ mod tests {
             preview: true,
             download: false,
             force_file_based: false,
+            keep_workdir: false,
         };
 
         assert!(options.preview);
@@ -763,6 +862,7 @@ mod tests {
             preview: false,
             download: true,
             force_file_based: false,
+            keep_workdir: false,
         };
 
         assert!(options.download);
@@ -71,6 +71,7 @@ mod tests {
                     filename: "test.rs".to_string(),
                     language: Some("Rust".to_string()),
                     size: 100,
+                    raw_url: None,
                 },
             )]),
             updated_at: Utc::now(),
@@ -12,4 +12,4 @@ pub use config::Config;
 pub use error::{GistCacheError, Result};
 pub use execution::{RunOptions, ScriptRunner};
 pub use github::GitHubApi;
-pub use search::{SearchMode, SearchQuery, select_from_results};
+pub use search::{SearchMode, SearchQuery, select_from_results, select_many_from_results};
@@ -1,13 +1,15 @@
-use crate::cache::ContentCache;
 use crate::cache::types::GistInfo;
+use crate::cache::{ContentStore, GistTags, UsageStats};
+use crate::config::{PickerSort, PreviewLayout};
 use crate::error::{GistCacheError, Result};
 use crate::execution::highlight::highlight_content;
 use crate::github::GitHubApi;
 use console::{Key, Term, style};
-use std::collections::HashMap;
-use std::path::Path;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
 
-const DEFAULT_DESCRIPTION: &str = "No description";
+pub(crate) const DEFAULT_DESCRIPTION: &str = "No description";
 // Reserve space for the "❯ "/"  " cursor prefix and a trailing column so a
 // truncated line never wraps, even on narrow terminals.
 const RESERVED_WIDTH: usize = 3;
@@ -58,8 +60,10 @@ impl Matcher {
 
 /// Builds the label shown for a gist. Gist descriptions conventionally
 /// already start with the primary filename (e.g. "hello.py - a script"), so
-/// filenames already present in the description are not repeated.
-fn build_item_text(gist: &GistInfo) -> String {
+/// filenames already present in the description are not repeated. Favorites
+/// get a star, and tags added locally follow as `#tag` (so the filter
+/// matches them too); hashtags of the description are in it already.
+fn build_item_text(gist: &GistInfo, tags: Option<&GistTags>) -> String {
     let desc = gist.description.as_deref().unwrap_or(DEFAULT_DESCRIPTION);
     let desc_lower = desc.to_lowercase();
 
@@ -70,11 +74,131 @@ fn build_item_text(gist: &GistInfo) -> String {
         .filter(|f| !desc_lower.contains(&f.to_lowercase()))
         .collect();
 
-    if extra_files.is_empty() {
+    let mut text = if extra_files.is_empty() {
         desc.to_string()
     } else {
         format!("{} - {}", desc, extra_files.join(", "))
+    };
+
+    if let Some(tags) = tags {
+        for tag in tags.tags.difference(&tags.description_tags) {
+            text.push_str(" #");
+            text.push_str(tag);
+        }
+        if tags.favorite {
+            text.insert_str(0, "★ ");
+        }
+    }
+    text
+}
+
+/// How the picker lays out its list: `s` cycles `sort`, `g` toggles
+/// grouping by language and `p` cycles the live preview while picking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ListView {
+    /// `None` keeps the search result order
+    pub sort: Option<PickerSort>,
+    pub group: bool,
+    pub preview: PreviewLayout,
+}
+
+/// Header label of the gists without a detected language (grouped view)
+const NO_LANGUAGE: &str = "Other";
+
+/// Primary language of a gist: the first file with a detected one
+fn primary_language(gist: &GistInfo) -> Option<&str> {
+    gist.files.iter().find_map(|f| f.language.as_deref())
+}
+
+/// Sort key putting languages in alphabetical order, gists without one last
+fn language_key(gist: &GistInfo) -> (bool, String) {
+    match primary_language(gist) {
+        Some(language) => (false, language.to_lowercase()),
+        None => (true, String::new()),
+    }
+}
+
+/// Result indices in display order. Sorting is stable, so ties keep the
+/// search result order; grouping orders by language first and keeps the
+/// sort within each group. Favorites (`tags`) come first, within their
+/// group when grouping.
+fn display_order(
+    results: &[&GistInfo],
+    items: &[String],
+    view: ListView,
+    usage: &HashMap<String, UsageStats>,
+    tags: &HashMap<String, GistTags>,
+) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..results.len()).collect();
+    match view.sort {
+        None => {}
+        Some(PickerSort::Updated) => order.sort_by_key(|&i| Reverse(results[i].updated_at)),
+        Some(PickerSort::Name) => order.sort_by_cached_key(|&i| items[i].to_lowercase()),
+        Some(PickerSort::Language) => order.sort_by_cached_key(|&i| language_key(results[i])),
+        Some(PickerSort::Size) => {
+            order.sort_by_key(|&i| Reverse(results[i].files.iter().map(|f| f.size).sum::<usize>()))
+        }
+        Some(PickerSort::Used) => order.sort_by_key(|&i| {
+            Reverse(
+                usage
+                    .get(&results[i].id)
+                    .map(|stats| (stats.run_count, stats.last_used)),
+            )
+        }),
+    }
+    let favorite = |i: usize| tags.get(&results[i].id).is_some_and(|t| t.favorite);
+    if view.group {
+        order.sort_by_cached_key(|&i| (language_key(results[i]), !favorite(i)));
+    } else {
+        order.sort_by_key(|&i| !favorite(i));
+    }
+    order
+}
+
+/// One row of the list
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Row {
+    /// Language header of the grouped view, e.g. "Python (3)"
+    Header(String),
+    /// Position in the visible items
+    Item(usize),
+}
+
+/// Rows for the visible items (result indices, in display order), with a
+/// header in front of each language when grouping
+fn list_rows(visible: &[usize], results: &[&GistInfo], group: bool) -> Vec<Row> {
+    if !group {
+        return (0..visible.len()).map(Row::Item).collect();
     }
+
+    let mut rows = Vec::new();
+    let mut start = 0;
+    while start < visible.len() {
+        let language = primary_language(results[visible[start]]);
+        let end = visible[start..]
+            .iter()
+            .position(|&i| primary_language(results[i]) != language)
+            .map_or(visible.len(), |len| start + len);
+        rows.push(Row::Header(format!(
+            "{} ({})",
+            language.unwrap_or(NO_LANGUAGE),
+            end - start
+        )));
+        rows.extend((start..end).map(Row::Item));
+        start = end;
+    }
+    rows
+}
+
+/// Renders a language header of the grouped view, truncated like a list row
+fn render_header(label: &str, width: usize) -> String {
+    let available = width.min(MAX_ITEM_WIDTH).saturating_sub(RESERVED_WIDTH);
+    let label = if available == 0 {
+        label.to_string()
+    } else {
+        console::truncate_str(label, available, "...").to_string()
+    };
+    format!("  {}", style(label).yellow().bold())
 }
 
 /// Number of terminal rows `text` occupies when wrapped at column `width`.
@@ -87,19 +211,29 @@ fn visual_row_count(text: &str, width: usize) -> usize {
 
 /// Renders one list row, truncating to fit `width` unless `full` is set.
 /// Truncation happens on the plain body text before styling is applied, so
-/// ANSI codes are never cut mid-sequence.
-fn render_line(text: &str, is_selected: bool, width: usize, full: bool) -> String {
-    let available = width.min(MAX_ITEM_WIDTH).saturating_sub(RESERVED_WIDTH);
+/// ANSI codes are never cut mid-sequence. Marked rows (multi-select) get a
+/// `✓ ` in front of the body, counted against the width like the body.
+fn render_line(text: &str, is_selected: bool, is_marked: bool, width: usize, full: bool) -> String {
+    let mark = if is_marked { "✓ " } else { "" };
+    let available = width
+        .min(MAX_ITEM_WIDTH)
+        .saturating_sub(RESERVED_WIDTH + console::measure_text_width(mark));
     let body = if full || available == 0 {
         text.to_string()
     } else {
         console::truncate_str(text, available, "...").to_string()
     };
+    let mark = style(mark).green().bold();
 
     if is_selected {
-        format!("{} {}", style("❯").cyan().bold(), style(body).cyan())
+        format!(
+            "{} {}{}",
+            style("❯").cyan().bold(),
+            mark,
+            style(body).cyan()
+        )
     } else {
-        format!("  {}", body)
+        format!("  {}{}", mark, body)
     }
 }
 
@@ -117,20 +251,28 @@ fn page_capacity(term_rows: usize) -> usize {
 /// `row_budget`. Grows outward from `selected` — mostly downward, filling
 /// upward with whatever budget remains — so item-count-based assumptions
 /// from short mode don't silently break once wrapped lines are wider than
-/// one row.
+/// one row. `is_marked` tells which rows carry a multi-select mark, since
+/// the mark widens a full-mode line. In the grouped view, header rows are
+/// passed as their label.
 fn visible_window(
     items: &[String],
     selected: usize,
     full: bool,
     width: usize,
     row_budget: usize,
+    is_marked: impl Fn(usize) -> bool,
 ) -> std::ops::Range<usize> {
     if items.is_empty() {
         return 0..0;
     }
     let row_budget = row_budget.max(1);
 
-    let row_cost = |i: usize| visual_row_count(&render_line(&items[i], false, width, full), width);
+    let row_cost = |i: usize| {
+        visual_row_count(
+            &render_line(&items[i], false, is_marked(i), width, full),
+            width,
+        )
+    };
 
     let mut start = selected;
     let mut end = selected + 1;
@@ -166,30 +308,29 @@ fn visible_window(
 }
 
 /// Fetches one file's content, checking an in-session cache first, then the
-/// on-disk content cache, then falling back to the GitHub API — same
+/// content store, then falling back to the GitHub API — same
 /// fallback chain as `ScriptRunner::preview_content`. Successful API fetches
-/// are written back to the disk cache (best-effort) and the session cache,
+/// are written back to the content store (best-effort) and the session cache,
 /// so re-opening the same preview during this picker session never refetches.
 fn fetch_file_content(
-    contents_dir: &Path,
+    content_store: &dyn ContentStore,
     gist_id: &str,
     filename: &str,
-    session_cache: &mut HashMap<(String, String), String>,
+    session_cache: &mut HashMap<FileKey, String>,
 ) -> Result<String> {
     let key = (gist_id.to_string(), filename.to_string());
     if let Some(cached) = session_cache.get(&key) {
         return Ok(cached.clone());
     }
 
-    let content_cache = ContentCache::new(contents_dir.to_path_buf());
-    let content = if content_cache.exists(gist_id, filename) {
-        match content_cache.read(gist_id, filename) {
+    let content = if content_store.exists(gist_id, filename) {
+        match content_store.read(gist_id, filename) {
             Ok(c) => c,
             Err(_) => GitHubApi::new().fetch_gist_content(gist_id, filename)?,
         }
     } else {
         let fetched = GitHubApi::new().fetch_gist_content(gist_id, filename)?;
-        let _ = content_cache.write(gist_id, filename, &fetched);
+        let _ = content_store.write(gist_id, filename, &fetched);
         fetched
     };
 
@@ -197,6 +338,114 @@ fn fetch_file_content(
     Ok(content)
 }
 
+/// A gist file, as keyed in the session cache: (gist ID, filename)
+type FileKey = (String, String);
+
+/// Fetches gist files from GitHub on a background thread, so the live
+/// preview can have the items around the cursor ready before the cursor
+/// gets there instead of stalling on a `gh` call for each one. Finished
+/// fetches are handed back over a channel and only ever touched by the
+/// picker's thread, which moves them into the session cache (and the
+/// content store). The thread exits once the prefetcher is dropped and
+/// its current fetch is done.
+struct Prefetcher {
+    requests: Sender<FileKey>,
+    fetched: Receiver<(FileKey, Option<String>)>,
+    pending: HashSet<FileKey>,
+}
+
+impl Prefetcher {
+    fn spawn() -> Self {
+        let (requests, request_rx) = mpsc::channel::<FileKey>();
+        let (fetched_tx, fetched) = mpsc::channel();
+        std::thread::spawn(move || {
+            let api = GitHubApi::new();
+            for key in request_rx {
+                let content = api.fetch_gist_content(&key.0, &key.1).ok();
+                if fetched_tx.send((key, content)).is_err() {
+                    break;
+                }
+            }
+        });
+        Prefetcher {
+            requests,
+            fetched,
+            pending: HashSet::new(),
+        }
+    }
+
+    /// Queues a fetch of every file of `gist` that is neither in the session
+    /// cache nor in the content store (nor queued already)
+    fn request(
+        &mut self,
+        gist: &GistInfo,
+        content_store: &dyn ContentStore,
+        session_cache: &HashMap<FileKey, String>,
+    ) {
+        for file in &gist.files {
+            let key = (gist.id.clone(), file.filename.clone());
+            if session_cache.contains_key(&key)
+                || self.pending.contains(&key)
+                || content_store.exists(&gist.id, &file.filename)
+            {
+                continue;
+            }
+            if self.requests.send(key.clone()).is_ok() {
+                self.pending.insert(key);
+            }
+        }
+    }
+
+    /// Moves finished fetches into the session cache and, best-effort, the
+    /// content store, like `fetch_file_content` does with its own fetches.
+    /// With `wait_for`, first blocks until those files' fetches are done, so
+    /// a file already being fetched is never fetched twice. Failed fetches
+    /// are dropped; `fetch_file_content` retries them and shows the error.
+    fn collect(
+        &mut self,
+        content_store: &dyn ContentStore,
+        session_cache: &mut HashMap<FileKey, String>,
+        wait_for: &[FileKey],
+    ) {
+        let mut store = |pending: &mut HashSet<FileKey>,
+                         (key, content): (FileKey, Option<String>)| {
+            pending.remove(&key);
+            if let Some(content) = content {
+                let _ = content_store.write(&key.0, &key.1, &content);
+                session_cache.insert(key, content);
+            }
+        };
+
+        while wait_for.iter().any(|key| self.pending.contains(key)) {
+            match self.fetched.recv() {
+                Ok(done) => store(&mut self.pending, done),
+                Err(_) => {
+                    self.pending.clear();
+                    break;
+                }
+            }
+        }
+        while let Ok(done) = self.fetched.try_recv() {
+            store(&mut self.pending, done);
+        }
+    }
+}
+
+/// Session cache keys of every file of `gist`
+fn file_keys(gist: &GistInfo) -> Vec<FileKey> {
+    gist.files
+        .iter()
+        .map(|f| (gist.id.clone(), f.filename.clone()))
+        .collect()
+}
+
+/// How many items above and below the cursor the live preview prefetches
+const PREFETCH_RADIUS: usize = 2;
+
+/// Narrowest terminal that still gets the side-by-side preview layout;
+/// narrower ones show the preview below the list instead
+const MIN_SPLIT_WIDTH: usize = 60;
+
 /// A gist's preview, flattened to individual display lines plus the
 /// absolute line index of each file's "--- filename ---" divider, so the
 /// viewer can pin the divider of whichever file is currently on screen.
@@ -214,8 +463,8 @@ struct PreviewContent {
 /// otherwise bleed into whatever is drawn after it.
 fn build_preview_lines(
     gist: &GistInfo,
-    contents_dir: &Path,
-    session_cache: &mut HashMap<(String, String), String>,
+    content_store: &dyn ContentStore,
+    session_cache: &mut HashMap<FileKey, String>,
 ) -> PreviewContent {
     let desc = gist.description.as_deref().unwrap_or(DEFAULT_DESCRIPTION);
     let mut lines = vec![style(desc).cyan().bold().to_string(), String::new()];
@@ -230,7 +479,7 @@ fn build_preview_lines(
                 .to_string(),
         );
 
-        match fetch_file_content(contents_dir, &gist.id, &file.filename, session_cache) {
+        match fetch_file_content(content_store, &gist.id, &file.filename, session_cache) {
             Ok(content) => {
                 let highlighted = highlight_content(&file.filename, &content);
                 lines.extend(highlighted.split('\n').map(|line| format!("{line}\x1b[0m")));
@@ -315,6 +564,33 @@ fn render_preview_line(
     format!("{marker} {line_no:>gutter_width$} {body}")
 }
 
+/// The first `rows` lines of a preview, numbered and truncated to `width`
+/// like the full-screen viewer's, for the live preview pane
+fn live_preview_rows(preview: &PreviewContent, width: usize, rows: usize) -> Vec<String> {
+    let gutter_width = preview.lines.len().max(1).to_string().len();
+    preview
+        .lines
+        .iter()
+        .take(rows)
+        .enumerate()
+        .map(|(i, line)| render_preview_line(i + 1, line, false, gutter_width, width))
+        .collect()
+}
+
+/// Joins list rows and preview rows side by side: each list row padded
+/// to `list_width`, a `│` separator, then the preview row. Produces as
+/// many rows as the longer of the two sides.
+fn side_by_side(list: &[String], preview: &[String], list_width: usize) -> Vec<String> {
+    (0..list.len().max(preview.len()))
+        .map(|i| {
+            let left = list.get(i).map(String::as_str).unwrap_or("");
+            let left = console::pad_str(left, list_width, console::Alignment::Left, None);
+            let right = preview.get(i).map(String::as_str).unwrap_or("");
+            format!("{} {} {}", left, style("│").dim(), right)
+        })
+        .collect()
+}
+
 /// Finds the next (or, going backward, previous) line whose plain text
 /// (ANSI codes stripped, since `lines` holds highlighted content) matches
 /// `matcher`, starting just past `from` and wrapping around the whole
@@ -357,10 +633,10 @@ fn find_next_match(
 fn show_preview(
     term: &Term,
     gist: &GistInfo,
-    contents_dir: &Path,
-    session_cache: &mut HashMap<(String, String), String>,
+    content_store: &dyn ContentStore,
+    session_cache: &mut HashMap<FileKey, String>,
 ) -> Result<()> {
-    let preview = build_preview_lines(gist, contents_dir, session_cache);
+    let preview = build_preview_lines(gist, content_store, session_cache);
     let lines = &preview.lines;
     let gutter_width = lines.len().max(1).to_string().len();
     // Description + blank line are always pinned; the active file divider
@@ -505,24 +781,125 @@ fn show_preview(
     Ok(())
 }
 
+/// What to do with the gists picked in `select`: Enter picks `Run`, the
+/// other actions have their own key in the list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickerAction {
+    /// Enter: whatever the command was asked to do (run, preview, ...)
+    Run,
+    /// `d`: save the files to `download_dir`
+    Download,
+    /// `y`: copy the gist ID to the clipboard
+    CopyId,
+    /// `Y`: copy the gist URL to the clipboard
+    CopyUrl,
+    /// `o`: open the gist page in the browser
+    Open,
+    /// `e`: edit the gist on GitHub
+    Edit,
+    /// `r`: re-fetch the gist's files into the content cache
+    Refresh,
+    /// `i`: show the gist's metadata
+    Info,
+}
+
+impl PickerAction {
+    /// Action bound to `key` in the list (not while typing a filter)
+    fn from_key(key: char) -> Option<Self> {
+        match key {
+            'd' => Some(PickerAction::Download),
+            'y' => Some(PickerAction::CopyId),
+            'Y' => Some(PickerAction::CopyUrl),
+            'o' => Some(PickerAction::Open),
+            'e' => Some(PickerAction::Edit),
+            'r' => Some(PickerAction::Refresh),
+            'i' => Some(PickerAction::Info),
+            _ => None,
+        }
+    }
+
+    /// Title printed after the picker closes
+    fn title(&self) -> &'static str {
+        match self {
+            PickerAction::Run => "Select",
+            PickerAction::Download => "Download",
+            PickerAction::CopyId => "Copy the ID of",
+            PickerAction::CopyUrl => "Copy the URL of",
+            PickerAction::Open => "Open",
+            PickerAction::Edit => "Edit",
+            PickerAction::Refresh => "Refresh",
+            PickerAction::Info => "Show info for",
+        }
+    }
+}
+
+/// Key hints for the actions, shown in the footer
+const ACTION_HINT: &str = "d download · y/Y copy ID/URL · o open · e edit · r refresh · i info";
+
+/// Result of `select`: the action and the picked result indices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selection {
+    pub action: PickerAction,
+    pub indices: Vec<usize>,
+}
+
+/// What Enter picks: every marked item (in result order, including marked
+/// items the current filter hides), or the selected visible item when
+/// nothing is marked.
+fn picked(visible: &[usize], selected: usize, marked: &BTreeSet<usize>) -> Option<Vec<usize>> {
+    if marked.is_empty() {
+        visible.get(selected).map(|&i| vec![i])
+    } else {
+        Some(marked.iter().copied().collect())
+    }
+}
+
 /// Interactive picker for search results, styled like dialoguer's Select but
-/// with a Tab toggle between truncated and full item text (long
+/// with a Tab (←/→ when multi-selecting) toggle between truncated and full
+/// item text (long
 /// descriptions/filenames are abbreviated by default so the list stays
 /// scannable), a Space toggle to preview the selected gist's full,
 /// highlighted content without leaving the picker, and a `/` filter (regex
 /// supported, falls back to a literal substring match) to narrow the
-/// results further. Scrolls to keep the selection visible when there are
-/// more results than fit on screen. Runs inside the terminal's alternate
-/// screen buffer, so the picker (and any preview) never leaves artifacts in
-/// the shell's scrollback. Returns `Ok(None)` when the user cancels
-/// (Esc/Ctrl+C, or Esc a second time once an active filter has already been
-/// cleared).
-pub fn select(results: &[&GistInfo], contents_dir: &Path) -> Result<Option<usize>> {
+/// results further. With `multi`, Tab marks/unmarks the selected gist and
+/// moves on (marks survive filter changes), so several gists can be picked
+/// at once. Scrolls to keep the selection visible when there are more
+/// results than fit on screen. Runs inside the terminal's alternate screen
+/// buffer, so the picker (and any preview) never leaves artifacts in the
+/// shell's scrollback.
+///
+/// `s` cycles the sort order of `view` (recently updated, name, language,
+/// size, most used — by `usage`) and `g` toggles grouping under language
+/// headers; `view` is left as the user last set it, also on cancel, so the
+/// caller can remember it.
+///
+/// Enter picks with `PickerAction::Run`; with `actions`, the action keys
+/// (`d`, `y`/`Y`, `o`, `e`, `r`, `i`) pick with their action instead
+/// (without, they are neither shown nor accepted). Returns the indices of
+/// the marked gists in result order — or just the selected one when nothing
+/// is marked — and `Ok(None)` when the user cancels (Esc/Ctrl+C, or Esc a
+/// second time once an active filter has already been cleared).
+pub fn select(
+    results: &[&GistInfo],
+    content_store: &dyn ContentStore,
+    multi: bool,
+    actions: bool,
+    view: &mut ListView,
+    usage: &HashMap<String, UsageStats>,
+    tags: &HashMap<String, GistTags>,
+) -> Result<Option<Selection>> {
     let term = Term::stdout();
-    let items: Vec<String> = results.iter().map(|g| build_item_text(g)).collect();
+    let items: Vec<String> = results
+        .iter()
+        .map(|g| build_item_text(g, tags.get(&g.id)))
+        .collect();
     let debug = std::env::var("GIST_CACHE_VERBOSE").is_ok();
     let mut key_log: Vec<String> = Vec::new();
-    let mut content_cache: HashMap<(String, String), String> = HashMap::new();
+    let mut content_cache: HashMap<FileKey, String> = HashMap::new();
+    // Started on first use of the live preview
+    let mut prefetcher: Option<Prefetcher> = None;
+    // Live preview of the gist (by ID) it was last built for
+    let mut live: Option<(String, PreviewContent)> = None;
 
     term.write_str(ENTER_ALT_SCREEN)
         .map_err(GistCacheError::Io)?;
@@ -532,12 +909,19 @@ pub fn select(results: &[&GistInfo], contents_dir: &Path) -> Result<Option<usize
     let mut full = false;
     let mut filter_input = String::new();
     let mut editing_filter = false;
+    let mut marked: BTreeSet<usize> = BTreeSet::new();
+    // Result index to keep selected across a change of order
+    let mut reselect: Option<usize> = None;
 
     let outcome = loop {
         let matcher = Matcher::new(&filter_input);
-        let visible: Vec<usize> = (0..items.len())
+        let visible: Vec<usize> = display_order(results, &items, *view, usage, tags)
+            .into_iter()
             .filter(|&i| matcher.is_match(&items[i]))
             .collect();
+        if let Some(index) = reselect.take() {
+            selected = visible.iter().position(|&i| i == index).unwrap_or(0);
+        }
         selected = if visible.is_empty() {
             0
         } else {
@@ -552,11 +936,15 @@ pub fn select(results: &[&GistInfo], contents_dir: &Path) -> Result<Option<usize
 
         term.write_str(CLEAR_AND_HOME).map_err(GistCacheError::Io)?;
 
+        let hint = if multi {
+            "(↑/↓ move, Tab mark, Space preview, ←/→ short/full, / filter, Enter select, Esc cancel)"
+        } else {
+            "(↑/↓ move, Space preview, Tab full/short, / filter, Enter select, Esc cancel)"
+        };
         term.write_line(&format!(
             "{} Select a Gist {}",
             style("?").cyan(),
-            style("(↑/↓ move, Space preview, Tab full/short, / filter, Enter select, Esc cancel)")
-                .dim()
+            style(hint).dim()
         ))
         .map_err(GistCacheError::Io)?;
 
@@ -574,27 +962,131 @@ pub fn select(results: &[&GistInfo], contents_dir: &Path) -> Result<Option<usize
         term.write_line(&style(filter_status).dim().to_string())
             .map_err(GistCacheError::Io)?;
 
+        // The side-by-side layout keeps the list in short mode, since a
+        // wrapped list row would push the panes out of line
+        let layout = match view.preview {
+            PreviewLayout::Right if width < MIN_SPLIT_WIDTH => PreviewLayout::Bottom,
+            layout => layout,
+        };
+        let (list_budget, list_width, list_full) = match layout {
+            PreviewLayout::Off => (row_budget, width, full),
+            PreviewLayout::Right => (row_budget, width * 2 / 5, false),
+            PreviewLayout::Bottom => ((row_budget / 2).max(1), width, full),
+        };
+
+        let mut list_lines = Vec::new();
         if visible.is_empty() {
-            term.write_line(&style("  -- No matches --").red().to_string())
-                .map_err(GistCacheError::Io)?;
+            list_lines.push(style("  -- No matches --").red().to_string());
         } else {
-            let filtered_items: Vec<String> = visible.iter().map(|&i| items[i].clone()).collect();
-            let window = visible_window(&filtered_items, selected, full, width, row_budget);
-            for i in window {
-                term.write_line(&render_line(&filtered_items[i], i == selected, width, full))
-                    .map_err(GistCacheError::Io)?;
+            let rows = list_rows(&visible, results, view.group);
+            let lines: Vec<String> = rows
+                .iter()
+                .map(|row| match row {
+                    Row::Header(label) => label.clone(),
+                    Row::Item(pos) => items[visible[*pos]].clone(),
+                })
+                .collect();
+            let selected_row = rows
+                .iter()
+                .position(|row| *row == Row::Item(selected))
+                .unwrap_or(0);
+            let is_marked =
+                |row: usize| matches!(rows[row], Row::Item(pos) if marked.contains(&visible[pos]));
+            let window = visible_window(
+                &lines,
+                selected_row,
+                list_full,
+                list_width,
+                list_budget,
+                is_marked,
+            );
+            for row in window {
+                list_lines.push(match rows[row] {
+                    Row::Header(ref label) => render_header(label, list_width),
+                    Row::Item(pos) => render_line(
+                        &lines[row],
+                        pos == selected,
+                        is_marked(row),
+                        list_width,
+                        list_full,
+                    ),
+                });
+            }
+        }
+
+        let mut preview_rows = Vec::new();
+        if layout != PreviewLayout::Off && !visible.is_empty() {
+            let gist = results[visible[selected]];
+            let prefetcher = prefetcher.get_or_insert_with(Prefetcher::spawn);
+
+            // Queue the neighbours first, so they are fetched while the
+            // highlighted gist is being loaded
+            let from = selected.saturating_sub(PREFETCH_RADIUS);
+            let to = (selected + PREFETCH_RADIUS).min(visible.len() - 1);
+            for &i in &visible[from..=to] {
+                if i != visible[selected] {
+                    prefetcher.request(results[i], content_store, &content_cache);
+                }
+            }
+            prefetcher.collect(content_store, &mut content_cache, &file_keys(gist));
+
+            if live.as_ref().is_none_or(|(id, _)| *id != gist.id) {
+                let preview = build_preview_lines(gist, content_store, &mut content_cache);
+                live = Some((gist.id.clone(), preview));
+            }
+            if let Some((_, preview)) = &live {
+                preview_rows = match layout {
+                    PreviewLayout::Right => {
+                        live_preview_rows(preview, width.saturating_sub(list_width + 3), row_budget)
+                    }
+                    _ => {
+                        // Full-mode list rows may wrap, so count terminal rows
+                        let used: usize = list_lines
+                            .iter()
+                            .map(|line| visual_row_count(line, width))
+                            .sum();
+                        live_preview_rows(preview, width, row_budget.saturating_sub(used + 1))
+                    }
+                };
             }
         }
 
+        let body = match layout {
+            PreviewLayout::Right => side_by_side(&list_lines, &preview_rows, list_width),
+            PreviewLayout::Bottom => {
+                let mut body = list_lines;
+                body.push(style("─".repeat(width.saturating_sub(1))).dim().to_string());
+                body.extend(preview_rows);
+                body
+            }
+            PreviewLayout::Off => list_lines,
+        };
+        for line in body {
+            term.write_line(&line).map_err(GistCacheError::Io)?;
+        }
+
+        let marked_status = if marked.is_empty() {
+            String::new()
+        } else {
+            format!(" {} marked", marked.len())
+        };
+        let footer = format!(
+            "  [{}/{}]{} ({}: {}) (s: {}, g: {}, p: {})  {}",
+            if visible.is_empty() { 0 } else { selected + 1 },
+            visible.len(),
+            marked_status,
+            if multi { "←/→" } else { "Tab" },
+            if full { "full" } else { "short" },
+            view.sort.map_or("search order", |sort| sort.label()),
+            if view.group { "by language" } else { "off" },
+            view.preview.as_str(),
+            if actions { ACTION_HINT } else { "" }
+        );
+        // Truncated like the preview footer, so it never wraps
         term.write_line(
-            &style(format!(
-                "  [{}/{}] (Tab: {})",
-                if visible.is_empty() { 0 } else { selected + 1 },
-                visible.len(),
-                if full { "full" } else { "short" }
-            ))
-            .dim()
-            .to_string(),
+            &style(console::truncate_str(&footer, width, "").into_owned())
+                .dim()
+                .to_string(),
         )
         .map_err(GistCacheError::Io)?;
 
@@ -611,11 +1103,22 @@ pub fn select(results: &[&GistInfo], contents_dir: &Path) -> Result<Option<usize
             key_log.push(format!("{:?}", key));
         }
 
+        // Tab marks the selected gist and moves to the next one, in both
+        // modes (it never types anything into the filter)
+        if multi && key == Key::Tab && !visible.is_empty() {
+            let index = visible[selected];
+            if !marked.remove(&index) {
+                marked.insert(index);
+            }
+            selected = (selected + 1) % visible.len();
+            continue;
+        }
+
         if editing_filter {
             match key {
                 Key::Enter => {
-                    if let Some(&i) = visible.get(selected) {
-                        break Some(i);
+                    if let Some(indices) = picked(&visible, selected, &marked) {
+                        break Some((PickerAction::Run, indices));
                     }
                 }
                 Key::Escape => editing_filter = false,
@@ -632,7 +1135,10 @@ pub fn select(results: &[&GistInfo], contents_dir: &Path) -> Result<Option<usize
                 Key::ArrowDown if !visible.is_empty() => {
                     selected = (selected + 1) % visible.len();
                 }
-                Key::Tab => full = !full,
+                // Tab is the mark key in multi-select mode
+                Key::Tab if !multi => full = !full,
+                Key::ArrowLeft => full = false,
+                Key::ArrowRight => full = true,
                 Key::CtrlC => break None,
                 Key::Char(c) => filter_input.push(c),
                 _ => {}
@@ -650,7 +1156,19 @@ pub fn select(results: &[&GistInfo], contents_dir: &Path) -> Result<Option<usize
                 Key::ArrowDown if !visible.is_empty() => {
                     selected = (selected + 1) % visible.len();
                 }
-                Key::Tab => full = !full,
+                // Tab is the mark key in multi-select mode
+                Key::Tab if !multi => full = !full,
+                Key::ArrowLeft => full = false,
+                Key::ArrowRight => full = true,
+                Key::Char('s') => {
+                    view.sort = Some(view.sort.map_or(PickerSort::Updated, PickerSort::next));
+                    reselect = visible.get(selected).copied();
+                }
+                Key::Char('g') => {
+                    view.group = !view.group;
+                    reselect = visible.get(selected).copied();
+                }
+                Key::Char('p') => view.preview = view.preview.next(),
                 // '\u{3000}' (IDEOGRAPHIC SPACE) is what some Japanese IMEs
                 // send for the space bar even outside of text conversion, so
                 // both are accepted as the preview trigger.
@@ -658,16 +1176,22 @@ pub fn select(results: &[&GistInfo], contents_dir: &Path) -> Result<Option<usize
                     // Preview errors (e.g. a failed API fetch) are shown
                     // inline by show_preview itself; nothing more to do
                     // here either way.
-                    let _ = show_preview(
-                        &term,
-                        results[visible[selected]],
-                        contents_dir,
-                        &mut content_cache,
-                    );
+                    let gist = results[visible[selected]];
+                    if let Some(prefetcher) = prefetcher.as_mut() {
+                        prefetcher.collect(content_store, &mut content_cache, &file_keys(gist));
+                    }
+                    let _ = show_preview(&term, gist, content_store, &mut content_cache);
                 }
                 Key::Enter => {
-                    if let Some(&i) = visible.get(selected) {
-                        break Some(i);
+                    if let Some(indices) = picked(&visible, selected, &marked) {
+                        break Some((PickerAction::Run, indices));
+                    }
+                }
+                Key::Char(c) if actions => {
+                    if let Some(action) = PickerAction::from_key(c)
+                        && let Some(indices) = picked(&visible, selected, &marked)
+                    {
+                        break Some((action, indices));
                     }
                 }
                 Key::Escape => {
@@ -690,16 +1214,32 @@ pub fn select(results: &[&GistInfo], contents_dir: &Path) -> Result<Option<usize
         eprintln!("[gist-cache-rs] keys received: {}", key_log.join(", "));
     }
 
-    if let Some(index) = outcome {
-        println!(
-            "{} Select a Gist {} {}",
+    let Some((action, indices)) = outcome else {
+        return Ok(None);
+    };
+    match indices.as_slice() {
+        [index] => println!(
+            "{} {} a Gist {} {}",
             style("✔").green(),
+            action.title(),
             style("·").dim(),
-            items[index]
-        );
+            items[*index]
+        ),
+        _ => {
+            println!(
+                "{} {} Gists {} {} selected",
+                style("✔").green(),
+                action.title(),
+                style("·").dim(),
+                indices.len()
+            );
+            for &index in &indices {
+                println!("  {} {}", style("✓").green(), items[index]);
+            }
+        }
     }
 
-    Ok(outcome)
+    Ok(Some(Selection { action, indices }))
 }
 
 #[cfg(test)]
@@ -718,6 +1258,7 @@ mod tests {
                     filename: name.to_string(),
                     language: None,
                     size: 100,
+                    revision: None,
                 })
                 .collect(),
             updated_at: Utc::now(),
@@ -794,7 +1335,7 @@ mod tests {
             vec!["hello_args.py"],
         );
         assert_eq!(
-            build_item_text(&g),
+            build_item_text(&g, None),
             "hello_args.py - Python引数テストスクリプト #python #test"
         );
     }
@@ -802,19 +1343,22 @@ mod tests {
     #[test]
     fn build_item_text_appends_filenames_not_in_description() {
         let g = gist(Some("A helper script"), vec!["helper.sh"]);
-        assert_eq!(build_item_text(&g), "A helper script - helper.sh");
+        assert_eq!(build_item_text(&g, None), "A helper script - helper.sh");
     }
 
     #[test]
     fn build_item_text_appends_only_missing_files_from_multiple() {
         let g = gist(Some("main.py - entry point"), vec!["main.py", "utils.py"]);
-        assert_eq!(build_item_text(&g), "main.py - entry point - utils.py");
+        assert_eq!(
+            build_item_text(&g, None),
+            "main.py - entry point - utils.py"
+        );
     }
 
     #[test]
     fn build_item_text_uses_default_when_no_description() {
         let g = gist(None, vec!["script.sh"]);
-        assert_eq!(build_item_text(&g), "No description - script.sh");
+        assert_eq!(build_item_text(&g, None), "No description - script.sh");
     }
 
     #[test]
@@ -832,7 +1376,7 @@ mod tests {
     #[test]
     fn render_line_truncates_when_not_full() {
         let text = "a".repeat(100);
-        let line = render_line(&text, false, 20, false);
+        let line = render_line(&text, false, false, 20, false);
         assert!(console::measure_text_width(&line) <= 20);
         assert!(line.contains("..."));
     }
@@ -843,7 +1387,7 @@ mod tests {
         // enough to fit it without wrapping, so short mode caps at
         // MAX_ITEM_WIDTH regardless of the actual terminal width.
         let text = "a".repeat(200);
-        let line = render_line(&text, false, 300, false);
+        let line = render_line(&text, false, false, 300, false);
         assert!(console::measure_text_width(&line) <= MAX_ITEM_WIDTH);
         assert!(line.contains("..."));
     }
@@ -851,17 +1395,248 @@ mod tests {
     #[test]
     fn render_line_shows_everything_when_full() {
         let text = "a".repeat(100);
-        let line = render_line(&text, false, 20, true);
+        let line = render_line(&text, false, false, 20, true);
         assert!(!line.contains("..."));
         assert!(line.ends_with(&text));
     }
 
     #[test]
     fn render_line_marks_selected_item() {
-        let line = render_line("item", true, 80, false);
+        let line = render_line("item", true, false, 80, false);
         assert!(line.contains('❯'));
     }
 
+    #[test]
+    fn render_line_shows_mark_within_width() {
+        let text = "a".repeat(100);
+        let line = render_line(&text, false, true, 20, false);
+        assert!(line.contains('✓'));
+        assert!(console::measure_text_width(&line) <= 20);
+        assert!(!render_line("item", false, false, 80, false).contains('✓'));
+    }
+
+    #[test]
+    fn picked_prefers_marks_over_the_selection() {
+        let visible = [0usize, 2, 4];
+        let mut marked = BTreeSet::new();
+        assert_eq!(picked(&visible, 1, &marked), Some(vec![2]));
+        assert_eq!(picked(&[], 0, &marked), None);
+
+        // Marks hidden by the current filter are still picked, in result order
+        marked.insert(3);
+        marked.insert(0);
+        assert_eq!(picked(&visible, 1, &marked), Some(vec![0, 3]));
+    }
+
+    /// Gists for the ordering tests: (id, description, language, size, age in days)
+    fn sortable_gists() -> Vec<GistInfo> {
+        [
+            ("a", "beta", Some("Python"), 300, 3),
+            ("b", "Alpha", None, 100, 1),
+            ("c", "gamma", Some("Bash"), 200, 2),
+            ("d", "delta", Some("Python"), 400, 0),
+        ]
+        .into_iter()
+        .map(|(id, desc, language, size, days)| {
+            let mut g = gist(Some(desc), vec!["run"]);
+            g.id = id.to_string();
+            g.files[0].language = language.map(str::to_string);
+            g.files[0].size = size;
+            g.updated_at = Utc::now() - chrono::Duration::days(days);
+            g
+        })
+        .collect()
+    }
+
+    #[test]
+    fn display_order_sorts_by_each_key() {
+        let gists = sortable_gists();
+        let results: Vec<&GistInfo> = gists.iter().collect();
+        let items: Vec<String> = results.iter().map(|g| build_item_text(g, None)).collect();
+        let mut usage = HashMap::new();
+        usage.insert(
+            "c".to_string(),
+            UsageStats {
+                run_count: 5,
+                last_used: Utc::now(),
+            },
+        );
+        usage.insert(
+            "a".to_string(),
+            UsageStats {
+                run_count: 2,
+                last_used: Utc::now(),
+            },
+        );
+
+        let order = |sort| {
+            let view = ListView {
+                sort,
+                ..ListView::default()
+            };
+            display_order(&results, &items, view, &usage, &HashMap::new())
+        };
+        assert_eq!(order(None), vec![0, 1, 2, 3]);
+        assert_eq!(order(Some(PickerSort::Updated)), vec![3, 1, 2, 0]);
+        assert_eq!(order(Some(PickerSort::Name)), vec![1, 0, 3, 2]);
+        // Ties keep the result order; gists without a language go last
+        assert_eq!(order(Some(PickerSort::Language)), vec![2, 0, 3, 1]);
+        assert_eq!(order(Some(PickerSort::Size)), vec![3, 0, 2, 1]);
+        assert_eq!(order(Some(PickerSort::Used)), vec![2, 0, 1, 3]);
+    }
+
+    #[test]
+    fn grouping_keeps_the_sort_within_each_language() {
+        let gists = sortable_gists();
+        let results: Vec<&GistInfo> = gists.iter().collect();
+        let items: Vec<String> = results.iter().map(|g| build_item_text(g, None)).collect();
+        let view = ListView {
+            sort: Some(PickerSort::Size),
+            group: true,
+            ..ListView::default()
+        };
+
+        let order = display_order(&results, &items, view, &HashMap::new(), &HashMap::new());
+        assert_eq!(order, vec![2, 3, 0, 1]);
+
+        assert_eq!(
+            list_rows(&order, &results, true),
+            vec![
+                Row::Header("Bash (1)".to_string()),
+                Row::Item(0),
+                Row::Header("Python (2)".to_string()),
+                Row::Item(1),
+                Row::Item(2),
+                Row::Header("Other (1)".to_string()),
+                Row::Item(3),
+            ]
+        );
+        assert_eq!(
+            list_rows(&order, &results, false),
+            (0..4).map(Row::Item).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn favorites_are_pinned_first() {
+        let gists = sortable_gists();
+        let results: Vec<&GistInfo> = gists.iter().collect();
+        let items: Vec<String> = results.iter().map(|g| build_item_text(g, None)).collect();
+        let mut tags = HashMap::new();
+        for id in ["b", "d"] {
+            let favorite = GistTags {
+                favorite: true,
+                ..GistTags::default()
+            };
+            tags.insert(id.to_string(), favorite);
+        }
+
+        let view = ListView {
+            sort: Some(PickerSort::Size),
+            ..ListView::default()
+        };
+        assert_eq!(
+            display_order(&results, &items, view, &HashMap::new(), &tags),
+            vec![3, 1, 0, 2]
+        );
+
+        // Within their language when grouping
+        let view = ListView {
+            group: true,
+            ..view
+        };
+        assert_eq!(
+            display_order(&results, &items, view, &HashMap::new(), &tags),
+            vec![2, 3, 0, 1]
+        );
+    }
+
+    #[test]
+    fn build_item_text_shows_favorites_and_added_tags() {
+        let g = gist(Some("deploy.sh - ship it #ops"), vec!["deploy.sh"]);
+        let mut tags = GistTags {
+            favorite: true,
+            ..GistTags::default()
+        };
+        tags.tags.insert("prod".to_string());
+        tags.tags.insert("ops".to_string());
+        tags.description_tags.insert("ops".to_string());
+        assert_eq!(
+            build_item_text(&g, Some(&tags)),
+            "★ deploy.sh - ship it #ops #prod"
+        );
+    }
+
+    #[test]
+    fn render_header_truncates_to_fit() {
+        let rendered = console::strip_ansi_codes(&render_header("Python (12)", 10)).to_string();
+        assert_eq!(console::measure_text_width(&rendered), 9);
+        assert!(rendered.starts_with("  Pyt"));
+        assert!(rendered.ends_with("..."));
+    }
+
+    #[test]
+    fn side_by_side_pads_the_list_column() {
+        let list = vec!["a".to_string(), "bb".to_string()];
+        let preview = vec!["x".to_string()];
+        let rows: Vec<String> = side_by_side(&list, &preview, 4)
+            .iter()
+            .map(|row| console::strip_ansi_codes(row).to_string())
+            .collect();
+        assert_eq!(rows, vec!["a    │ x", "bb   │ "]);
+
+        // The longer side decides the row count
+        assert_eq!(side_by_side(&[], &preview, 4).len(), 1);
+    }
+
+    #[test]
+    fn live_preview_rows_are_numbered_and_limited() {
+        let preview = PreviewContent {
+            lines: (1..=12).map(|i| format!("line {i}")).collect(),
+            dividers: vec![],
+        };
+        let rows = live_preview_rows(&preview, 80, 3);
+        assert_eq!(rows.len(), 3);
+        let first = console::strip_ansi_codes(&rows[0]).to_string();
+        assert_eq!(first, "   1 line 1");
+
+        // Truncated to the pane width
+        let narrow = live_preview_rows(&preview, 8, 1);
+        assert!(console::measure_text_width(&narrow[0]) <= 8);
+    }
+
+    #[test]
+    fn prefetcher_skips_files_already_available() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let content_store = crate::cache::ContentCache::new(temp_dir.path().to_path_buf());
+        let g = gist(Some("Two files"), vec!["a.sh", "b.sh"]);
+        content_store.write(&g.id, "a.sh", "echo a").unwrap();
+        let mut session_cache = HashMap::new();
+        session_cache.insert((g.id.clone(), "b.sh".to_string()), "echo b".to_string());
+
+        // Nothing to fetch, so no `gh` call is queued and collect returns
+        // without waiting
+        let mut prefetcher = Prefetcher::spawn();
+        prefetcher.request(&g, &content_store, &session_cache);
+        assert!(prefetcher.pending.is_empty());
+        prefetcher.collect(&content_store, &mut session_cache, &file_keys(&g));
+        assert_eq!(session_cache.len(), 1);
+    }
+
+    #[test]
+    fn picker_action_keys() {
+        assert_eq!(PickerAction::from_key('d'), Some(PickerAction::Download));
+        assert_eq!(PickerAction::from_key('y'), Some(PickerAction::CopyId));
+        assert_eq!(PickerAction::from_key('Y'), Some(PickerAction::CopyUrl));
+        assert_eq!(PickerAction::from_key('o'), Some(PickerAction::Open));
+        assert_eq!(PickerAction::from_key('e'), Some(PickerAction::Edit));
+        assert_eq!(PickerAction::from_key('r'), Some(PickerAction::Refresh));
+        assert_eq!(PickerAction::from_key('i'), Some(PickerAction::Info));
+        // Keys used for navigation in the preview are not actions
+        assert_eq!(PickerAction::from_key('n'), None);
+        assert_eq!(PickerAction::from_key('/'), None);
+    }
+
     #[test]
     fn page_capacity_reserves_header_footer_and_margin() {
         assert_eq!(page_capacity(24), 20);
@@ -966,14 +1741,14 @@ mod tests {
         width: usize,
     ) -> usize {
         window
-            .map(|i| visual_row_count(&render_line(&items[i], false, width, full), width))
+            .map(|i| visual_row_count(&render_line(&items[i], false, false, width, full), width))
             .sum()
     }
 
     #[test]
     fn visible_window_includes_selection_and_respects_row_budget() {
         let items = make_items(10);
-        let window = visible_window(&items, 5, false, 80, 3);
+        let window = visible_window(&items, 5, false, 80, 3, |_| false);
         assert!(window.contains(&5));
         assert!(window_row_cost(&items, window, false, 80) <= 3);
     }
@@ -981,28 +1756,28 @@ mod tests {
     #[test]
     fn visible_window_does_not_run_past_start_of_list() {
         let items = make_items(10);
-        let window = visible_window(&items, 0, false, 80, 3);
+        let window = visible_window(&items, 0, false, 80, 3, |_| false);
         assert_eq!(window.start, 0);
     }
 
     #[test]
     fn visible_window_does_not_run_past_end_of_list() {
         let items = make_items(10);
-        let window = visible_window(&items, 9, false, 80, 3);
+        let window = visible_window(&items, 9, false, 80, 3, |_| false);
         assert_eq!(window.end, 10);
     }
 
     #[test]
     fn visible_window_shows_everything_when_list_fits_budget() {
         let items = make_items(5);
-        let window = visible_window(&items, 2, false, 80, 10);
+        let window = visible_window(&items, 2, false, 80, 10, |_| false);
         assert_eq!(window, 0..5);
     }
 
     #[test]
     fn visible_window_handles_empty_list() {
         let items: Vec<String> = Vec::new();
-        assert_eq!(visible_window(&items, 0, false, 80, 10), 0..0);
+        assert_eq!(visible_window(&items, 0, false, 80, 10, |_| false), 0..0);
     }
 
     #[test]
@@ -1012,7 +1787,7 @@ mod tests {
         // budget; the window must shrink to compensate.
         let items: Vec<String> = (0..10).map(|_| "a".repeat(50)).collect();
         let width = 20;
-        let window = visible_window(&items, 5, true, width, 6);
+        let window = visible_window(&items, 5, true, width, 6, |_| false);
         assert!(window.contains(&5));
         assert!(window_row_cost(&items, window, true, width) <= 6);
     }
@@ -1,4 +1,8 @@
 pub mod interactive;
 pub mod query;
 
-pub use query::{SearchMode, SearchQuery, select_from_results};
+pub use interactive::{ListView, PickerAction, Selection};
+pub use query::{
+    SearchMode, SearchQuery, SelectPolicy, rank_by_frecency, select_from_results,
+    select_many_from_results,
+};
@@ -1,6 +1,8 @@
 use crate::cache::types::GistInfo;
+use crate::cache::{ContentStore, GistTags, UsageStats};
 use crate::error::{GistCacheError, Result};
-use std::path::Path;
+use crate::search::interactive::{ListView, PickerAction};
+use std::collections::{BTreeSet, HashMap};
 
 #[derive(Debug, Clone)]
 pub enum SearchMode {
@@ -14,20 +16,56 @@ pub enum SearchMode {
 pub struct SearchQuery {
     query: String,
     mode: SearchMode,
+    /// Every local tag of each gist, by gist ID
+    tags: HashMap<String, BTreeSet<String>>,
 }
 
 impl SearchQuery {
     pub fn new(query: String, mode: SearchMode) -> Self {
-        Self { query, mode }
+        Self {
+            query,
+            mode,
+            tags: HashMap::new(),
+        }
     }
 
-    pub fn search<'a>(&self, gists: &'a [GistInfo]) -> Result<Vec<&'a GistInfo>> {
-        let mode = match &self.mode {
+    /// Also match local tags when searching descriptions and filenames
+    /// together: `#tag` matches gists tagged exactly `tag`, any other query
+    /// gists with a tag containing it
+    pub fn with_tags(mut self, tags: &HashMap<String, GistTags>) -> Self {
+        self.tags = tags
+            .iter()
+            .map(|(id, t)| {
+                (
+                    id.clone(),
+                    t.all().into_iter().map(str::to_string).collect(),
+                )
+            })
+            .collect();
+        self
+    }
+
+    /// Whether a local tag of `gist` matches the query
+    fn tag_match(&self, gist: &GistInfo, query_lower: &str) -> bool {
+        let Some(tags) = self.tags.get(&gist.id) else {
+            return false;
+        };
+        match query_lower.strip_prefix('#') {
+            Some(tag) => tags.contains(tag),
+            None => tags.iter().any(|t| t.contains(query_lower)),
+        }
+    }
+
+    /// The mode actually used by `search` (`Auto` resolved from the query)
+    pub fn effective_mode(&self) -> SearchMode {
+        match &self.mode {
             SearchMode::Auto => self.detect_mode(),
             other => other.clone(),
-        };
+        }
+    }
 
-        match mode {
+    pub fn search<'a>(&self, gists: &'a [GistInfo]) -> Result<Vec<&'a GistInfo>> {
+        match self.effective_mode() {
             SearchMode::Id => self.search_by_id(gists),
             SearchMode::Filename => self.search_by_filename(gists),
             SearchMode::Description => self.search_by_description(gists),
@@ -92,29 +130,137 @@ impl SearchQuery {
                     .iter()
                     .any(|f| f.filename.to_lowercase().contains(&query_lower));
 
-                desc_match || file_match
+                desc_match || file_match || self.tag_match(g, &query_lower)
             })
             .collect();
         Ok(results)
     }
 }
 
+/// Orders `results` by frecency score (`frecency_scores`), highest first;
+/// the sort is stable, so gists never run keep their order after the others
+pub fn rank_by_frecency(results: &mut [&GistInfo], scores: &HashMap<String, f64>) {
+    let score = |gist: &GistInfo| scores.get(&gist.id).copied().unwrap_or(0.0);
+    results.sort_by(|a, b| score(b).total_cmp(&score(a)));
+}
+
+/// How to pick one gist among several search results without the picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectPolicy {
+    /// The first result (the most run one, unless `search.frecency` is off)
+    First,
+    /// The most recently updated result
+    Newest,
+    /// The Nth result, counting from 1
+    Nth(usize),
+}
+
+impl std::str::FromStr for SelectPolicy {
+    type Err = GistCacheError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "first" => Ok(Self::First),
+            "newest" => Ok(Self::Newest),
+            other => match other.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(Self::Nth(n)),
+                _ => Err(GistCacheError::InvalidSelectPolicy(s.to_string())),
+            },
+        }
+    }
+}
+
+impl SelectPolicy {
+    /// The result this policy picks
+    ///
+    /// # Errors
+    /// Returns `SelectionOutOfRange` if `Nth` counts past the results
+    pub fn pick<'a>(self, results: &[&'a GistInfo]) -> Result<&'a GistInfo> {
+        let picked = match self {
+            Self::First => results.first().copied(),
+            // The earliest of equally new results
+            Self::Newest => results.iter().copied().reduce(|newest, g| {
+                if g.updated_at > newest.updated_at {
+                    g
+                } else {
+                    newest
+                }
+            }),
+            Self::Nth(n) => {
+                return results
+                    .get(n - 1)
+                    .copied()
+                    .ok_or(GistCacheError::SelectionOutOfRange(n, results.len()));
+            }
+        };
+        picked.ok_or_else(|| GistCacheError::NoSearchResults("".to_string()))
+    }
+}
+
+/// Pick one gist; the picker offers no action keys
 pub fn select_from_results<'a>(
     results: &[&'a GistInfo],
-    contents_dir: &Path,
+    content_store: &dyn ContentStore,
 ) -> Result<&'a GistInfo> {
+    pick_from_results(
+        results,
+        content_store,
+        false,
+        false,
+        &mut ListView::default(),
+        &HashMap::new(),
+        &HashMap::new(),
+    )
+    .map(|(_, selected)| selected[0])
+}
+
+/// Like `select_from_results`, but the picker lets the user mark several
+/// gists (Tab); returns them in result order with the picker action chosen
+/// (`Run` when there was nothing to choose from). The list starts in `view`
+/// (`usage` backs the "most used" order, `tags` pins favorites and labels
+/// local tags), and `view` holds the order the user left the picker in.
+pub fn select_many_from_results<'a>(
+    results: &[&'a GistInfo],
+    content_store: &dyn ContentStore,
+    view: &mut ListView,
+    usage: &HashMap<String, UsageStats>,
+    tags: &HashMap<String, GistTags>,
+) -> Result<(PickerAction, Vec<&'a GistInfo>)> {
+    pick_from_results(results, content_store, true, true, view, usage, tags)
+}
+
+fn pick_from_results<'a>(
+    results: &[&'a GistInfo],
+    content_store: &dyn ContentStore,
+    multi: bool,
+    actions: bool,
+    view: &mut ListView,
+    usage: &HashMap<String, UsageStats>,
+    tags: &HashMap<String, GistTags>,
+) -> Result<(PickerAction, Vec<&'a GistInfo>)> {
     if results.is_empty() {
         return Err(GistCacheError::NoSearchResults("".to_string()));
     }
 
     if results.len() == 1 {
-        return Ok(results[0]);
+        return Ok((PickerAction::Run, vec![results[0]]));
     }
 
     println!("\nMultiple Gists found:\n");
 
-    match crate::search::interactive::select(results, contents_dir)? {
-        Some(index) => Ok(results[index]),
+    match crate::search::interactive::select(
+        results,
+        content_store,
+        multi,
+        actions,
+        view,
+        usage,
+        tags,
+    )? {
+        Some(selection) => Ok((
+            selection.action,
+            selection.indices.into_iter().map(|i| results[i]).collect(),
+        )),
         None => Err(GistCacheError::InvalidSelection),
     }
 }
@@ -122,7 +268,9 @@ pub fn select_from_results<'a>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::ContentCache;
     use chrono::Utc;
+    use std::path::PathBuf;
 
     fn create_test_gist(id: &str, desc: Option<&str>, filenames: Vec<&str>) -> GistInfo {
         GistInfo {
@@ -134,6 +282,7 @@ mod tests {
                     filename: name.to_string(),
                     language: None,
                     size: 100,
+                    revision: None,
                 })
                 .collect(),
             updated_at: Utc::now(),
@@ -252,16 +401,85 @@ mod tests {
     #[test]
     fn test_select_from_empty_results() {
         let results: Vec<&GistInfo> = vec![];
-        let error = select_from_results(&results, Path::new("/tmp/contents")).unwrap_err();
+        let error =
+            select_from_results(&results, &ContentCache::new(PathBuf::from("/tmp/contents")))
+                .unwrap_err();
         assert!(matches!(error, GistCacheError::NoSearchResults(_)));
     }
 
+    #[test]
+    fn test_rank_by_frecency() {
+        let gists: Vec<GistInfo> = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|id| create_test_gist(id, Some("Test"), vec!["file.rs"]))
+            .collect();
+        let mut results: Vec<&GistInfo> = gists.iter().collect();
+        let scores = HashMap::from([("c".to_string(), 2.5), ("b".to_string(), 0.25)]);
+
+        rank_by_frecency(&mut results, &scores);
+        let ids: Vec<&str> = results.iter().map(|g| g.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "b", "a", "d"]);
+    }
+
+    #[test]
+    fn test_select_policy() {
+        assert_eq!(
+            "first".parse::<SelectPolicy>().unwrap(),
+            SelectPolicy::First
+        );
+        assert_eq!(
+            "Newest".parse::<SelectPolicy>().unwrap(),
+            SelectPolicy::Newest
+        );
+        assert_eq!("2".parse::<SelectPolicy>().unwrap(), SelectPolicy::Nth(2));
+        for invalid in ["0", "-1", "last", ""] {
+            assert!(matches!(
+                invalid.parse::<SelectPolicy>(),
+                Err(GistCacheError::InvalidSelectPolicy(_))
+            ));
+        }
+
+        let mut gists: Vec<GistInfo> = ["a", "b", "c"]
+            .into_iter()
+            .map(|id| create_test_gist(id, Some("Test"), vec!["file.rs"]))
+            .collect();
+        let now = Utc::now();
+        gists[0].updated_at = now - chrono::Duration::days(2);
+        gists[1].updated_at = now;
+        gists[2].updated_at = now;
+        let results: Vec<&GistInfo> = gists.iter().collect();
+
+        assert_eq!(SelectPolicy::First.pick(&results).unwrap().id, "a");
+        assert_eq!(SelectPolicy::Newest.pick(&results).unwrap().id, "b");
+        assert_eq!(SelectPolicy::Nth(3).pick(&results).unwrap().id, "c");
+        assert!(matches!(
+            SelectPolicy::Nth(4).pick(&results),
+            Err(GistCacheError::SelectionOutOfRange(4, 3))
+        ));
+        assert!(SelectPolicy::First.pick(&[]).is_err());
+    }
+
     #[test]
     fn test_select_from_single_result() {
         let gist = create_test_gist("abc123", Some("Test"), vec!["file.rs"]);
         let results = vec![&gist];
-        let selected = select_from_results(&results, Path::new("/tmp/contents")).unwrap();
+        let selected =
+            select_from_results(&results, &ContentCache::new(PathBuf::from("/tmp/contents")))
+                .unwrap();
         assert_eq!(selected.id, "abc123");
+
+        let mut view = ListView::default();
+        let (action, selected) = select_many_from_results(
+            &results,
+            &ContentCache::new(PathBuf::from("/tmp/contents")),
+            &mut view,
+            &HashMap::new(),
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert_eq!(action, PickerAction::Run);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].id, "abc123");
     }
 
     #[test]
@@ -364,6 +582,44 @@ mod tests {
         assert_eq!(results.len(), 2);
     }
 
+    #[test]
+    fn test_search_matches_local_tags() {
+        let gists = vec![
+            create_test_gist("abc123", Some("deploy"), vec!["deploy.sh"]),
+            create_test_gist("def456", Some("#backend notes"), vec!["notes.md"]),
+            create_test_gist("ghi789", Some("misc"), vec!["misc.py"]),
+        ];
+        let mut tags = HashMap::new();
+        let mut backend = GistTags::default();
+        backend.tags.insert("backend".to_string());
+        tags.insert("abc123".to_string(), backend);
+        let mut description = GistTags::default();
+        description
+            .description_tags
+            .insert("backend-ops".to_string());
+        tags.insert("ghi789".to_string(), description);
+
+        let ids = |query: &str| -> Vec<String> {
+            SearchQuery::new(query.to_string(), SearchMode::Auto)
+                .with_tags(&tags)
+                .search(&gists)
+                .unwrap()
+                .into_iter()
+                .map(|g| g.id.clone())
+                .collect()
+        };
+        // Exact tag, plus the description text as before
+        assert_eq!(ids("#backend"), vec!["abc123", "def456"]);
+        assert_eq!(ids("#BACKEND-OPS"), vec!["ghi789"]);
+        // Plain queries match inside tags too
+        assert_eq!(ids("backend"), vec!["abc123", "def456", "ghi789"]);
+
+        // Explicit description searches don't look at tags
+        let query =
+            SearchQuery::new("backend".to_string(), SearchMode::Description).with_tags(&tags);
+        assert_eq!(query.search(&gists).unwrap().len(), 1);
+    }
+
     #[test]
     fn test_search_empty_gist_list() {
         let gists: Vec<GistInfo> = vec![];
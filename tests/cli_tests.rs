@@ -648,3 +648,264 @@ fn test_config_show_with_content() {
         .stdout(predicate::str::contains("interpreter = fish"))
         .stdout(predicate::str::contains("No configuration settings found.").not());
 }
+
+#[test]
+fn test_run_ambiguous_query_without_terminal() {
+    let temp = setup_test_env();
+    let cache_dir = temp.path().join("gist-cache");
+    fs::create_dir_all(&cache_dir).unwrap();
+    fs::write(
+        cache_dir.join("cache.json"),
+        r#"{
+        "metadata": {
+            "last_updated": "2024-01-01T12:00:00Z",
+            "total_count": 2,
+            "github_user": "testuser"
+        },
+        "gists": [
+            {
+                "id": "old111",
+                "description": "Deploy staging",
+                "files": [{"filename": "old.sh", "language": null, "size": 10}],
+                "updated_at": "2024-01-01T12:00:00Z",
+                "public": true,
+                "html_url": "https://gist.github.com/old111"
+            },
+            {
+                "id": "new222",
+                "description": "Deploy production",
+                "files": [{"filename": "new.sh", "language": null, "size": 10}],
+                "updated_at": "2024-03-01T12:00:00Z",
+                "public": true,
+                "html_url": "https://gist.github.com/new222"
+            }
+        ]
+    }"#,
+    )
+    .unwrap();
+    let contents = gist_cache_rs::cache::ContentCache::new(cache_dir.join("contents"));
+    for (id, file, content) in [
+        ("old111", "old.sh", "echo old-deploy"),
+        ("new222", "new.sh", "echo new-deploy"),
+    ] {
+        contents.write(id, file, content).unwrap();
+    }
+
+    let run = |extra: &[&str]| {
+        Command::cargo_bin("gist-cache-rs")
+            .unwrap()
+            .env("GIST_CACHE_DIR", temp.path())
+            .args(["run", "--preview", "--description", "deploy"])
+            .args(extra)
+            .assert()
+    };
+
+    // No terminal: the candidates are listed instead of opening the picker
+    run(&[])
+        .failure()
+        .stdout(predicate::str::contains("\"index\": 2"))
+        .stdout(predicate::str::contains("new222"))
+        .stderr(predicate::str::contains("matches 2 Gists"));
+
+    run(&["--select", "newest"])
+        .success()
+        .stdout(predicate::str::contains("new-deploy"));
+    run(&["--select", "1"])
+        .success()
+        .stdout(predicate::str::contains("old-deploy"));
+    run(&["--select", "3"])
+        .failure()
+        .stderr(predicate::str::contains("found only 2 Gists"));
+    run(&["--select", "last"]).failure().code(2);
+}
+
+#[test]
+fn test_search_output_formats() {
+    let temp = setup_test_env();
+    let cache_dir = temp.path().join("gist-cache");
+    fs::create_dir_all(&cache_dir).unwrap();
+    fs::write(
+        cache_dir.join("cache.json"),
+        r#"{
+        "metadata": {
+            "last_updated": "2024-01-01T12:00:00Z",
+            "total_count": 2,
+            "github_user": "testuser"
+        },
+        "gists": [
+            {
+                "id": "old111",
+                "description": "Deploy staging",
+                "files": [{"filename": "old.sh", "language": "Shell", "size": 10}],
+                "updated_at": "2024-01-01T12:00:00Z",
+                "public": true,
+                "html_url": "https://gist.github.com/old111"
+            },
+            {
+                "id": "new222",
+                "description": "Deploy\tproduction",
+                "files": [{"filename": "new.sh", "language": "Shell", "size": 10}],
+                "updated_at": "2024-03-01T12:00:00Z",
+                "public": false,
+                "html_url": "https://gist.github.com/new222"
+            }
+        ]
+    }"#,
+    )
+    .unwrap();
+
+    let search = |args: &[&str]| {
+        Command::cargo_bin("gist-cache-rs")
+            .unwrap()
+            .env("GIST_CACHE_DIR", temp.path())
+            .arg("search")
+            .args(args)
+            .assert()
+    };
+
+    let output = search(&["deploy", "--format", "ids"]).success();
+    let stdout = String::from_utf8(output.get_output().stdout.clone()).unwrap();
+    let mut ids: Vec<&str> = stdout.lines().collect();
+    ids.sort_unstable();
+    assert_eq!(ids, ["new222", "old111"]);
+
+    search(&["deploy", "--format", "ids", "--limit", "1"])
+        .success()
+        .stdout(predicate::function(|s: &str| s.lines().count() == 1));
+
+    // Tabs in descriptions never split a TSV field
+    search(&[
+        "new222",
+        "--id",
+        "--format",
+        "tsv",
+        "--fields",
+        "id,public,description",
+    ])
+    .success()
+    .stdout("new222\tfalse\tDeploy production\n");
+
+    let output = search(&["staging", "--format", "json", "--fields", "url,files"]).success();
+    let json: serde_json::Value = serde_json::from_slice(&output.get_output().stdout).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([{"url": "https://gist.github.com/old111", "files": ["old.sh"]}])
+    );
+
+    // Like grep, no match is a failure
+    search(&["nothing-matches"]).failure();
+    search(&["deploy", "--fields", "size"]).failure();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run_file_of_multi_file_gist() {
+    let temp = setup_test_env();
+    let cache_dir = temp.path().join("gist-cache");
+    fs::create_dir_all(&cache_dir).unwrap();
+    fs::write(
+        cache_dir.join("cache.json"),
+        r#"{
+        "metadata": {
+            "last_updated": "2024-01-01T12:00:00Z",
+            "total_count": 1,
+            "github_user": "testuser"
+        },
+        "gists": [
+            {
+                "id": "multi1",
+                "description": "Two scripts",
+                "files": [
+                    {"filename": "first.sh", "language": "Shell", "size": 10},
+                    {"filename": "second.sh", "language": "Shell", "size": 10},
+                    {"filename": "README.md", "language": "Markdown", "size": 10}
+                ],
+                "updated_at": "2024-01-01T12:00:00Z",
+                "public": true,
+                "html_url": "https://gist.github.com/multi1"
+            }
+        ]
+    }"#,
+    )
+    .unwrap();
+    let contents = gist_cache_rs::cache::ContentCache::new(cache_dir.join("contents"));
+    contents
+        .write("multi1", "first.sh", "#!/bin/sh\necho from-first\n")
+        .unwrap();
+    contents
+        .write("multi1", "second.sh", "#!/bin/sh\necho from-second\n")
+        .unwrap();
+    contents
+        .write("multi1", "README.md", "# Two scripts\n")
+        .unwrap();
+
+    let run = |extra: &[&str]| {
+        Command::cargo_bin("gist-cache-rs")
+            .unwrap()
+            .env("GIST_CACHE_DIR", temp.path())
+            .args(["run", "--id", "multi1"])
+            .args(extra)
+            .assert()
+    };
+
+    // Without a terminal there is no chooser: the candidates are listed
+    // and nothing runs
+    run(&["bash"])
+        .failure()
+        .stdout(predicate::str::contains("\"filename\": \"second.sh\""))
+        .stdout(predicate::str::contains("from-first").not())
+        .stderr(predicate::str::contains(
+            "2 files of Gist multi1 could run; choose one with --file",
+        ));
+
+    run(&["--file", "second.sh", "bash"])
+        .success()
+        .stdout(predicate::str::contains("from-second"));
+    run(&["--file", "sec*", "bash"])
+        .success()
+        .stdout(predicate::str::contains("from-second"));
+    run(&["--file", "*.rb", "bash"])
+        .failure()
+        .stderr(predicate::str::contains(
+            "No file of Gist multi1 matches '*.rb'",
+        ));
+}
+
+#[test]
+fn test_run_preview_keeps_status_lines_off_stdout() {
+    let temp = setup_test_env();
+    let cache_dir = temp.path().join("gist-cache");
+    fs::create_dir_all(cache_dir.join("contents")).unwrap();
+    fs::write(
+        cache_dir.join("cache.json"),
+        r#"{
+        "metadata": {
+            "last_updated": "2024-01-01T12:00:00Z",
+            "total_count": 1,
+            "github_user": "testuser"
+        },
+        "gists": [
+            {
+                "id": "uncached000000",
+                "description": "Not cached yet",
+                "files": [{"filename": "run.sh", "language": "Shell", "size": 10}],
+                "updated_at": "2024-01-01T12:00:00Z",
+                "public": true,
+                "html_url": "https://gist.github.com/uncached000000"
+            }
+        ]
+    }"#,
+    )
+    .unwrap();
+
+    // `--preview > file` must only ever capture the content; the Gist does
+    // not exist on GitHub, so the fetch fails and nothing is printed
+    Command::cargo_bin("gist-cache-rs")
+        .unwrap()
+        .env("GIST_CACHE_DIR", temp.path())
+        .args(["run", "--preview", "--id", "uncached000000"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Cache does not exist").not())
+        .stderr(predicate::str::contains("Cache does not exist"));
+}
@@ -51,6 +51,7 @@ fn create_test_gist(id: &str, filename: &str) -> GistInfo {
             filename: filename.to_string(),
             language: Some("Shell".to_string()),
             size: 100,
+            revision: None,
         }],
         updated_at: Utc::now(),
         public: true,
@@ -76,6 +77,7 @@ fn test_execute_bash_script() {
         preview: false,
         download: false,
         force_file_based: false,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -110,6 +112,7 @@ fn test_execute_python_script() {
         preview: false,
         download: false,
         force_file_based: false,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -143,6 +146,7 @@ fn test_execute_node_script() {
         preview: false,
         download: false,
         force_file_based: false,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -178,6 +182,7 @@ fn test_execute_with_arguments() {
         preview: false,
         download: false,
         force_file_based: false,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -216,6 +221,7 @@ fn test_execute_failing_script() {
         preview: false,
         download: false,
         force_file_based: false,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -249,6 +255,7 @@ fn test_preview_mode_does_not_execute() {
         preview: true, // プレビューモード
         download: false,
         force_file_based: false,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -297,6 +304,7 @@ fn test_execute_ts_node_script() {
         preview: false,
         download: false,
         force_file_based: true, // TypeScriptはファイルベース実行が必須
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -340,6 +348,7 @@ fn test_execute_deno_script() {
         preview: false,
         download: false,
         force_file_based: true, // TypeScriptはファイルベース実行が必須
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -383,6 +392,7 @@ fn test_execute_bun_script() {
         preview: false,
         download: false,
         force_file_based: true, // TypeScriptはファイルベース実行が必須
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -420,6 +430,7 @@ fn test_execute_powershell_script() {
         preview: false,
         download: false,
         force_file_based: true, // PowerShellはファイルベース実行
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -458,6 +469,7 @@ fn test_execute_powershell_with_arguments() {
         preview: false,
         download: false,
         force_file_based: true,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -496,6 +508,7 @@ fn test_execute_powershell_failing_script() {
         preview: false,
         download: false,
         force_file_based: true,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -534,6 +547,7 @@ fn test_execute_powershell_preview_mode() {
         preview: true, // プレビューモード
         download: false,
         force_file_based: true,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -582,6 +596,7 @@ fn test_execute_ruby_script() {
         preview: false,
         download: false,
         force_file_based: true, // ファイルベース実行
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -625,6 +640,7 @@ fn test_execute_perl_script() {
         preview: false,
         download: false,
         force_file_based: true, // ファイルベース実行
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -670,6 +686,7 @@ fn test_execute_php_script() {
         preview: false,
         download: false,
         force_file_based: true, // phpはファイルベース実行が必須（CLAUDE.mdより）
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
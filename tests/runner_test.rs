@@ -8,6 +8,7 @@
 use chrono::Utc;
 use gist_cache_rs::cache::types::{GistFile, GistInfo};
 use gist_cache_rs::config::Config;
+use gist_cache_rs::execution::history::History;
 use gist_cache_rs::execution::runner::{RunOptions, ScriptRunner};
 use serial_test::serial;
 use std::fs;
@@ -47,6 +48,7 @@ fn create_test_gist(id: &str, filename: &str, lang: Option<&str>) -> GistInfo {
             filename: filename.to_string(),
             language: lang.map(|s| s.to_string()),
             size: 100,
+            revision: None,
         }],
         updated_at: Utc::now(),
         public: true,
@@ -71,6 +73,7 @@ fn test_download_mode_creates_file() {
         preview: false,
         download: true, // ダウンロードモード
         force_file_based: false,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -117,6 +120,7 @@ fn test_preview_with_download_mode() {
         preview: true,  // プレビュー
         download: true, // ダウンロード
         force_file_based: false,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -138,6 +142,9 @@ fn test_preview_with_download_mode() {
         download_path.exists(),
         "Downloaded file should exist even in preview mode"
     );
+
+    // A preview is not an execution
+    assert!(History::new(&config).entries().unwrap().is_empty());
 }
 
 #[test]
@@ -162,6 +169,7 @@ fn test_cache_creation_after_execution() {
         preview: false,
         download: false,
         force_file_based: false,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -182,6 +190,13 @@ fn test_cache_creation_after_execution() {
         content_cache.exists(&gist.id, "hello.sh"),
         "Cache should exist after execution"
     );
+
+    // The execution is recorded in the history (frecency ranking)
+    let history = History::new(&config).entries().unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].gist_id, "test_cache_creation");
+    assert_eq!(history[0].interpreter, "bash");
+    assert_eq!(history[0].exit_code, Some(0));
 }
 
 #[test]
@@ -195,18 +210,24 @@ fn test_multiple_files_gist() {
         filename: "hello.py".to_string(),
         language: Some("Python".to_string()),
         size: 100,
+        revision: None,
     });
 
     let content = read_fixture("hello.sh");
     let content_cache = gist_cache_rs::cache::ContentCache::new(config.contents_dir.clone());
     content_cache.ensure_cache_dir().unwrap();
     content_cache.write(&gist.id, "hello.sh", &content).unwrap();
+    // Every file of the gist is written to the working directory
+    content_cache
+        .write(&gist.id, "hello.py", &read_fixture("hello.py"))
+        .unwrap();
 
     let options = RunOptions {
         interactive: false,
         preview: false,
         download: false,
         force_file_based: false,
+        keep_workdir: false,
     };
 
     // bash インタープリタを指定した場合、.sh ファイルが選択されるべき
@@ -244,6 +265,7 @@ fn test_force_file_based_execution() {
         preview: false,
         download: false,
         force_file_based: true, // ファイルベース実行を強制
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -277,6 +299,7 @@ fn test_script_with_empty_arguments() {
         preview: false,
         download: false,
         force_file_based: false,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -293,6 +316,161 @@ fn test_script_with_empty_arguments() {
     assert!(result.is_ok(), "Script with no arguments should succeed");
 }
 
+#[test]
+#[serial]
+#[cfg_attr(not(unix), ignore)]
+fn test_multi_file_gist_runs_in_workdir() {
+    let (config, temp_dir) = create_test_config();
+
+    let mut gist = create_test_gist("test_workdir", "main.sh", Some("Shell"));
+    gist.files.push(GistFile {
+        filename: "lib.sh".to_string(),
+        language: Some("Shell".to_string()),
+        size: 100,
+        revision: None,
+    });
+    gist.files.push(GistFile {
+        filename: "config.json".to_string(),
+        language: Some("JSON".to_string()),
+        size: 100,
+        revision: None,
+    });
+
+    let content_cache = gist_cache_rs::cache::ContentCache::new(config.contents_dir.clone());
+    content_cache.ensure_cache_dir().unwrap();
+    // The main file sources a sibling and reads a bundled file next to it,
+    // then reports where it ran
+    content_cache
+        .write(
+            &gist.id,
+            "main.sh",
+            "#!/bin/bash
+set -e
+dir=$(dirname \"$0\")
+. \"$dir/lib.sh\"
+greet \"$(cat \"$dir/config.json\")\" > \"$1\"
+echo \"$dir\" >> \"$1\"
+",
+        )
+        .unwrap();
+    content_cache
+        .write(
+            &gist.id,
+            "lib.sh",
+            "greet() { echo \"hello $1\"; }
+",
+        )
+        .unwrap();
+    content_cache
+        .write(&gist.id, "config.json", "{\"name\":\"x\"}")
+        .unwrap();
+
+    let run = |keep_workdir: bool| -> PathBuf {
+        let output = temp_dir.path().join("output.txt");
+        let runner = ScriptRunner::new(
+            gist.clone(),
+            "bash".to_string(),
+            None,
+            true,
+            RunOptions {
+                interactive: false,
+                preview: false,
+                download: false,
+                force_file_based: false,
+                keep_workdir,
+            },
+            vec![output.to_string_lossy().into_owned()],
+            config.clone(),
+        );
+        runner.run().unwrap();
+
+        let report = fs::read_to_string(&output).unwrap();
+        let mut lines = report.lines();
+        assert_eq!(lines.next(), Some("hello {\"name\":\"x\"}"));
+        PathBuf::from(lines.next().unwrap())
+    };
+
+    // The directory is private to the run and removed afterwards
+    let workdir = run(false);
+    assert!(workdir.starts_with(std::env::temp_dir()));
+    assert!(!workdir.exists());
+
+    // With keep_workdir it stays, holding every file
+    let workdir = run(true);
+    assert!(workdir.join("main.sh").exists());
+    assert!(workdir.join("lib.sh").exists());
+    assert!(workdir.join("config.json").exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&workdir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+    fs::remove_dir_all(&workdir).unwrap();
+}
+
+#[test]
+#[serial]
+#[cfg_attr(not(unix), ignore)]
+fn test_multi_file_gist_keeps_caller_directory() {
+    let (config, temp_dir) = create_test_config();
+
+    let mut gist = create_test_gist("test_workdir_cwd", "main.sh", Some("Shell"));
+    gist.files.push(GistFile {
+        filename: "config.json".to_string(),
+        language: Some("JSON".to_string()),
+        size: 100,
+        revision: None,
+    });
+
+    let content_cache = gist_cache_rs::cache::ContentCache::new(config.contents_dir.clone());
+    content_cache.ensure_cache_dir().unwrap();
+    // Finds its sibling through the working directory variable and reports
+    // the directory it runs in
+    content_cache
+        .write(
+            &gist.id,
+            "main.sh",
+            "#!/bin/bash
+set -e
+cat \"$GIST_CACHE_WORKDIR/config.json\" > \"$1\"
+echo >> \"$1\"
+pwd -P >> \"$1\"
+",
+        )
+        .unwrap();
+    content_cache
+        .write(&gist.id, "config.json", "{\"name\":\"x\"}")
+        .unwrap();
+
+    let output = temp_dir.path().join("output.txt");
+    let runner = ScriptRunner::new(
+        gist,
+        "bash".to_string(),
+        None,
+        true,
+        RunOptions {
+            interactive: false,
+            preview: false,
+            download: false,
+            force_file_based: false,
+            keep_workdir: false,
+        },
+        vec![output.to_string_lossy().into_owned()],
+        config,
+    );
+    runner.run().unwrap();
+
+    // Relative paths in the script's arguments still mean the caller's directory
+    let report = fs::read_to_string(&output).unwrap();
+    let mut lines = report.lines();
+    assert_eq!(lines.next(), Some("{\"name\":\"x\"}"));
+    assert_eq!(
+        lines.next().map(PathBuf::from),
+        Some(std::env::current_dir().unwrap().canonicalize().unwrap())
+    );
+}
+
 // Windows専用テスト (PowerShell)
 
 #[test]
@@ -314,6 +492,7 @@ fn test_powershell_download_mode() {
         preview: false,
         download: true, // ダウンロードモード
         force_file_based: true,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -367,6 +546,7 @@ fn test_powershell_cache_creation() {
         preview: false,
         download: false,
         force_file_based: true,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -408,6 +588,7 @@ fn test_powershell_force_file_based() {
         preview: false,
         download: false,
         force_file_based: true, // ファイルベース実行を強制
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -446,6 +627,7 @@ fn test_powershell_preview_with_download() {
         preview: true,  // プレビュー
         download: true, // ダウンロード
         force_file_based: true,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(
@@ -483,6 +665,7 @@ fn test_powershell_multiple_files_gist() {
         filename: "hello.py".to_string(),
         language: Some("Python".to_string()),
         size: 100,
+        revision: None,
     });
 
     let content = read_fixture("hello.ps1");
@@ -491,12 +674,17 @@ fn test_powershell_multiple_files_gist() {
     content_cache
         .write(&gist.id, "hello.ps1", &content)
         .unwrap();
+    // Every file of the gist is written to the working directory
+    content_cache
+        .write(&gist.id, "hello.py", &read_fixture("hello.py"))
+        .unwrap();
 
     let options = RunOptions {
         interactive: false,
         preview: false,
         download: false,
         force_file_based: true,
+        keep_workdir: false,
     };
 
     // pwsh インタープリタを指定した場合、.ps1 ファイルが選択されるべき
@@ -536,6 +724,7 @@ fn test_powershell_with_empty_arguments() {
         preview: false,
         download: false,
         force_file_based: true,
+        keep_workdir: false,
     };
 
     let runner = ScriptRunner::new(